@@ -0,0 +1,439 @@
+//! This module contains the bibliography of a document, which is read from a BibTeX file, and
+//! the labels of the citations of its entries.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::parser::ast::{Ast, Node};
+use crate::parser::error::{EmptyError, ErrorType, Errors};
+use crate::parser::Position;
+use crate::Result;
+
+/// The types of entries whose title is a whole publication, and is written in italic.
+const PUBLICATIONS: [&str; 6] = [
+    "book",
+    "manual",
+    "mastersthesis",
+    "phdthesis",
+    "proceedings",
+    "techreport",
+];
+
+/// The way citations refer to the entries of the bibliography.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CitationStyle {
+    /// Entries are numbered in the order of their first citation, e.g. `[1, 3]`.
+    #[default]
+    Numeric,
+
+    /// Entries are referred to by the names of their authors and their year, e.g.
+    /// `(Knuth 1984; Lamport 1994)`.
+    AuthorYear,
+}
+
+/// An entry of a bibliography, e.g. `@book{knuth84, title = {The TeXbook}, year = 1984}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The type of the entry in lowercase, e.g. `book` or `article`.
+    pub ty: String,
+
+    /// The key of the entry, which is used to cite it.
+    pub key: String,
+
+    /// The fields of the entry, whose names are in lowercase and whose values have no braces.
+    pub fields: HashMap<String, String>,
+}
+
+impl Entry {
+    /// Returns the value of a field of the entry.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+
+    /// Returns the names of the authors of the entry, or of its editors if it has no author.
+    ///
+    /// Names written as `Last, First` are returned as `First Last`.
+    pub fn authors(&self) -> Vec<String> {
+        let names = match self.field("author").or_else(|| self.field("editor")) {
+            Some(names) => names,
+            None => return vec![],
+        };
+
+        names
+            .split(" and ")
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(|name| match name.find(',') {
+                Some(index) => format!("{} {}", name[index + 1..].trim(), name[..index].trim()),
+                None => name.into(),
+            })
+            .collect()
+    }
+
+    /// Returns the year of the entry, or `n.d.` if it has none.
+    pub fn year(&self) -> &str {
+        self.field("year").unwrap_or("n.d.")
+    }
+
+    /// Returns the label of the entry in the author-year style, e.g. `Knuth 1984`, `Knuth and
+    /// Lamport 1990` or `Knuth et al. 1990`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use spandex::bibliography::Bibliography;
+    /// let bibliography = Bibliography::parse("@book{k, author = {Knuth, Donald}, year = 1984}");
+    /// let entry = &bibliography.unwrap().entries[0];
+    /// assert_eq!(entry.author_year(), "Knuth 1984");
+    /// ```
+    pub fn author_year(&self) -> String {
+        let names = self
+            .authors()
+            .iter()
+            .map(|x| x.rsplit(' ').next().unwrap_or("").to_owned())
+            .collect::<Vec<_>>();
+
+        let authors = match names.len() {
+            0 => self.key.clone(),
+            1 => names[0].clone(),
+            2 => format!("{} and {}", names[0], names[1]),
+            _ => format!("{} et al.", names[0]),
+        };
+
+        format!("{} {}", authors, self.year())
+    }
+
+    /// Returns the reference of the entry as it is written in the bibliography, e.g. `Donald
+    /// Knuth. The TeXbook. Addison-Wesley, 1984.`.
+    ///
+    /// The titles of publications and the names of the journals or the proceedings that contain
+    /// the entry are written in italic.
+    pub fn reference(&self) -> Ast {
+        let authors = self.authors();
+        let mut children = vec![];
+
+        let authors = match authors.len() {
+            0 => String::new(),
+            1 => format!("{}. ", authors[0]),
+            n => format!("{} and {}. ", authors[..n - 1].join(", "), authors[n - 1]),
+        };
+        children.push(Ast::Text(authors));
+
+        let title = self.field("title").unwrap_or(&self.key).to_owned();
+        if PUBLICATIONS.contains(&self.ty.as_str()) {
+            children.push(Ast::Italic(Box::new(Ast::Text(title).into())));
+            children.push(Ast::Text(". ".into()));
+        } else {
+            children.push(Ast::Text(format!("{}. ", title)));
+        }
+
+        if let Some(container) = self.field("journal").or_else(|| self.field("booktitle")) {
+            if self.ty != "article" {
+                children.push(Ast::Text("In ".into()));
+            }
+            children.push(Ast::Italic(Box::new(Ast::Text(container.into()).into())));
+            children.push(Ast::Text(", ".into()));
+        }
+
+        let publisher = ["publisher", "school", "institution", "howpublished"]
+            .iter()
+            .find_map(|x| self.field(x));
+
+        if let Some(publisher) = publisher {
+            children.push(Ast::Text(format!("{}, ", publisher)));
+        }
+
+        children.push(Ast::Text(format!("{}.", self.year().trim_end_matches('.'))));
+        Ast::Group(children.into_iter().map(Node::from).collect())
+    }
+}
+
+/// The entries of a BibTeX file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bibliography {
+    /// The entries, in the order of the file.
+    pub entries: Vec<Entry>,
+}
+
+impl Bibliography {
+    /// Reads and parses a BibTeX file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bibliography> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+
+        match Bibliography::parse(&content) {
+            Ok(bibliography) => Ok(bibliography),
+            Err(error) => Err(Errors {
+                path: PathBuf::from(path),
+                content,
+                errors: vec![error],
+                chain: vec![],
+                children: vec![],
+            }
+            .into()),
+        }
+    }
+
+    /// Parses the content of a BibTeX file.
+    ///
+    /// The text outside of the entries is ignored, as well as the `@comment`, `@preamble` and
+    /// `@string` entries. The values of the fields can be delimited by braces or quotes, or be
+    /// numbers, and their inner braces are removed.
+    pub fn parse(content: &str) -> std::result::Result<Bibliography, EmptyError> {
+        let mut parser = BibParser { content, offset: 0 };
+        let mut entries = vec![];
+
+        while let Some(entry) = parser.entry()? {
+            entries.push(entry);
+        }
+
+        Ok(Bibliography { entries })
+    }
+
+    /// Returns the entry with a key.
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|x| x.key == key)
+    }
+}
+
+/// The state of the parsing of a BibTeX file.
+struct BibParser<'a> {
+    /// The content of the file.
+    content: &'a str,
+
+    /// The offset of the next character to parse.
+    offset: usize,
+}
+
+impl<'a> BibParser<'a> {
+    /// Returns the rest of the content.
+    fn rest(&self) -> &'a str {
+        &self.content[self.offset..]
+    }
+
+    /// Returns an error at the current offset.
+    fn error(&self) -> EmptyError {
+        let before = &self.content[..self.offset];
+        let start = before.rfind('\n').map_or(0, |x| x + 1);
+
+        EmptyError {
+            position: Position {
+                line: before.matches('\n').count() as u32 + 1,
+                column: before[start..].chars().count() + 1,
+                offset: self.offset,
+            },
+            ty: ErrorType::InvalidBibliography,
+        }
+    }
+
+    /// Skips the whitespace.
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes a character after some whitespace, or returns an error if the next character is
+    /// different.
+    fn expect(&mut self, c: char) -> std::result::Result<(), EmptyError> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(c) {
+            self.offset += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    /// Consumes the characters while they satisfy a predicate, and returns them.
+    fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(|x| !predicate(x)).unwrap_or(rest.len());
+        self.offset += end;
+        &rest[..end]
+    }
+
+    /// Consumes some content up to its closing brace, and returns it without its inner braces.
+    ///
+    /// The opening brace must already be consumed.
+    fn braced(&mut self) -> std::result::Result<String, EmptyError> {
+        let mut depth = 0;
+        let mut value = String::new();
+
+        for (index, c) in self.rest().char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    self.offset += index + 1;
+                    return Ok(value);
+                }
+                '}' => depth -= 1,
+                _ => value.push(c),
+            }
+        }
+
+        self.offset = self.content.len();
+        Err(self.error())
+    }
+
+    /// Parses the value of a field.
+    fn value(&mut self) -> std::result::Result<String, EmptyError> {
+        self.skip_whitespace();
+
+        let value = if self.rest().starts_with('{') {
+            self.offset += 1;
+            self.braced()?
+        } else if self.rest().starts_with('"') {
+            self.offset += 1;
+            let mut depth = 0;
+            let mut value = String::new();
+            let mut end = None;
+
+            for (index, c) in self.rest().char_indices() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    '"' if depth == 0 => {
+                        end = Some(index);
+                        break;
+                    }
+                    _ => value.push(c),
+                }
+            }
+
+            match end {
+                Some(end) => self.offset += end + 1,
+                None => return Err(self.error()),
+            }
+
+            value
+        } else {
+            let value = self.take_while(|x| x.is_alphanumeric());
+            if value.is_empty() {
+                return Err(self.error());
+            }
+            value.into()
+        };
+
+        Ok(value.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+
+    /// Parses the next entry, skipping the text before it.
+    fn entry(&mut self) -> std::result::Result<Option<Entry>, EmptyError> {
+        loop {
+            match self.rest().find('@') {
+                Some(index) => self.offset += index + 1,
+                None => return Ok(None),
+            }
+
+            let ty = self.take_while(char::is_alphanumeric).to_lowercase();
+            if ty.is_empty() {
+                return Err(self.error());
+            }
+
+            self.expect('{')?;
+
+            if ty == "comment" || ty == "preamble" || ty == "string" {
+                self.braced()?;
+                continue;
+            }
+
+            self.skip_whitespace();
+            let key = self.take_while(|x| x != ',' && x != '}' && !x.is_whitespace());
+            if key.is_empty() {
+                return Err(self.error());
+            }
+
+            let mut fields = HashMap::new();
+
+            loop {
+                self.skip_whitespace();
+
+                if self.rest().starts_with('}') {
+                    self.offset += 1;
+                    break;
+                }
+
+                self.expect(',')?;
+                self.skip_whitespace();
+
+                // A comma can follow the last field.
+                if self.rest().starts_with('}') {
+                    continue;
+                }
+
+                let name = self.take_while(|x| x.is_alphanumeric() || x == '-' || x == '_');
+                if name.is_empty() {
+                    return Err(self.error());
+                }
+
+                self.expect('=')?;
+                fields.insert(name.to_lowercase(), self.value()?);
+            }
+
+            return Ok(Some(Entry {
+                ty,
+                key: key.into(),
+                fields,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::bibliography::Bibliography;
+    use crate::parser::ast::Ast;
+    use crate::parser::error::ErrorType;
+
+    #[test]
+    fn test_parse() {
+        let content = "A comment.\n\
+                       @Book{knuth84,\n\
+                       \x20 Author = {Knuth, Donald E.},\n\
+                       \x20 title = \"The {\\TeX}book\",\n\
+                       \x20 publisher = {Addison-Wesley}, year = 1984,\n\
+                       }\n\
+                       @string{tug = {TeX Users Group}}\n\
+                       @article{lamport94, author = {Leslie Lamport and Jane Doe and John Doe},\n\
+                       \x20 title = {A document preparation system}, journal = {TUGboat}}\n";
+
+        let bibliography = Bibliography::parse(content).unwrap();
+        assert_eq!(bibliography.entries.len(), 2);
+
+        let knuth = bibliography.get("knuth84").unwrap();
+        assert_eq!(knuth.ty, "book");
+        assert_eq!(knuth.field("title"), Some("The \\TeXbook"));
+        assert_eq!(knuth.authors(), vec!["Donald E. Knuth"]);
+        assert_eq!(knuth.author_year(), "Knuth 1984");
+
+        let lamport = bibliography.get("lamport94").unwrap();
+        assert_eq!(lamport.author_year(), "Lamport et al. n.d.");
+        assert_eq!(
+            lamport.reference(),
+            Ast::Group(vec![
+                Ast::Text("Leslie Lamport, Jane Doe and John Doe. ".into()).into(),
+                Ast::Text("A document preparation system. ".into()).into(),
+                Ast::Italic(Box::new(Ast::Text("TUGboat".into()).into())).into(),
+                Ast::Text(", ".into()).into(),
+                Ast::Text("n.d.".into()).into(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let error =
+            Bibliography::parse("@book{a, title = {A}}\n@book{b,\n  title {B}}").unwrap_err();
+
+        assert_eq!(error.ty, ErrorType::InvalidBibliography);
+        assert_eq!(error.position.line, 3);
+        assert_eq!(error.position.column, 9);
+        assert_eq!(error.position.offset, 39);
+    }
+}
@@ -9,8 +9,10 @@ use printpdf::{Mm, Pt};
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::bibliography::CitationStyle;
 use crate::document::{Document, Window};
 use crate::font::FontManager;
+use crate::parser::lint::Lints;
 use crate::Result as CResult;
 
 /// Serializes a `Pt` structure.
@@ -140,6 +142,22 @@ pub struct Config {
 
     /// The path to the first file of the spandex content.
     pub input: String,
+
+    /// The path to the BibTeX file containing the entries that can be cited.
+    #[serde(default)]
+    pub bibliography: Option<String>,
+
+    /// The way citations refer to the entries of the bibliography.
+    #[serde(default)]
+    pub citation_style: CitationStyle,
+
+    /// The levels of the lints.
+    #[serde(default)]
+    pub lints: Lints,
+
+    /// The column at which `spandex fmt` wraps the paragraphs.
+    #[serde(default)]
+    pub format_width: Option<usize>,
 }
 
 impl Config {
@@ -161,6 +179,10 @@ impl Config {
             text_width,
             text_height,
             input: String::from("main.dex"),
+            bibliography: None,
+            citation_style: CitationStyle::default(),
+            lints: Lints::default(),
+            format_width: None,
         }
     }
 
@@ -173,7 +195,7 @@ impl Config {
             height: self.text_height,
         };
 
-        let mut document = Document::new("Hello", self.page_width, self.page_height, window);
+        let mut document = Document::new(&self.title, self.page_width, self.page_height, window);
         let font_manager = FontManager::init(&mut document)?;
 
         Ok((document, font_manager))
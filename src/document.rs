@@ -1,30 +1,185 @@
 //! This module allows to create beautiful documents.
 
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
 use std::io::BufWriter;
+use std::iter::FromIterator;
+use std::mem;
 use std::path::Path;
 
 use hyphenation::load::Load;
 use hyphenation::{Language, Standard};
-use printpdf::{PdfDocument, PdfDocumentReference, PdfLayerReference, PdfPageReference, Pt};
+use lopdf::{Dictionary, Object, StringFormat};
+use printpdf::image::{self, DynamicImage};
+use printpdf::{
+    Image, IndirectFontRef, Line, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    PdfPageReference, Point, Pt,
+};
 
-use crate::font::{Font, FontConfig};
-use crate::parser::ast::Ast;
+use crate::bibliography::{Bibliography, CitationStyle};
+use crate::font::{Font, FontConfig, FontStyle};
+use crate::math::layout::layout;
+use crate::parser::ast::{Alignment, Ast, Node};
+use crate::parser::metadata::Metadata;
 use crate::typography::justification::{Justifier, LatexJustifier};
-use crate::typography::paragraphs::itemize_ast;
+use crate::typography::paragraphs::{
+    itemize_ast, itemize_ast_with_footnotes, itemize_footnote, itemize_list_item, minimal_width,
+    natural_width, ListMarker, Paragraph, LIST_MARKER_SEPARATION,
+};
+use crate::typography::Glyph;
+use crate::Result;
+
+/// The indentation of the items of a list, relative to the enclosing content.
+const LIST_INDENT: Pt = Pt(20.0);
+
+/// The minimal width of the content of a list item, below which nested lists stop being indented.
+const LIST_MIN_WIDTH: Pt = Pt(100.0);
+
+/// The bullets used for unordered lists, depending on their depth.
+const BULLETS: [&str; 3] = ["•", "–", "·"];
+
+/// The ratio between the size of footnotes and the size of the text.
+const FOOTNOTE_RATIO: f64 = 0.8;
+
+/// The space between the text and the footnotes of a page, in the middle of which is a rule.
+const FOOTNOTE_SEPARATION: Pt = Pt(12.0);
+
+/// The width of the rule above the footnotes, relative to the width of the text.
+const FOOTNOTE_RULE_RATIO: f64 = 0.4;
+
+/// The thickness of the rule above the footnotes, in pt.
+const FOOTNOTE_RULE_THICKNESS: f64 = 0.4;
+
+/// How far below the baseline the clickable area of a link goes, relative to the size of the text.
+const LINK_DEPTH: f64 = 0.25;
+
+/// How far above the baseline the clickable area of a link goes, relative to the size of the text.
+const LINK_HEIGHT: f64 = 0.8;
+
+/// How far below the baseline underlines are drawn, relative to the size of the text.
+const UNDERLINE_DEPTH: f64 = 0.15;
+
+/// How far above the baseline strikethrough lines are drawn, relative to the size of the text.
+const STRIKETHROUGH_HEIGHT: f64 = 0.25;
+
+/// The thickness of underlines and strikethrough lines, relative to the size of the text.
+const DECORATION_THICKNESS: f64 = 0.05;
+
+/// The space kept above the destination of an internal link when jumping to it.
+const DESTINATION_MARGIN: Pt = Pt(20.0);
+
+/// The resolution at which a pixel of an image is a pt, before it is scaled.
+const IMAGE_DPI: f64 = 72.0;
+
+/// The number of lines kept for the caption below images that are as tall as a page.
+const FIGURE_CAPTION_LINES: f64 = 3.0;
+
+/// The number of columns between two tab stops in code blocks.
+const TAB_WIDTH: usize = 4;
+
+/// The minimal width of the columns of a table, which empty columns take.
+const TABLE_MIN_COLUMN_WIDTH: Pt = Pt(10.0);
+
+/// The space between the columns of a table.
+const TABLE_COLUMN_SEPARATION: Pt = Pt(12.0);
+
+/// How far below the last baseline of a row the rule below it is, relative to the size of the
+/// text.
+const TABLE_RULE_DEPTH: f64 = 0.4;
+
+/// The thickness of the rules at the top and at the bottom of tables, in pt.
+const TABLE_OUTER_RULE_THICKNESS: f64 = 0.8;
+
+/// The thickness of the rule below the header of tables, in pt.
+const TABLE_INNER_RULE_THICKNESS: f64 = 0.4;
+
+/// The indentation of the entries of the table of contents per level of their titles.
+const CONTENTS_INDENT: Pt = Pt(15.0);
+
+/// The space kept on the right of the entries of the table of contents for the page numbers.
+const CONTENTS_PAGE_WIDTH: Pt = Pt(30.0);
+
+/// The space between two dots of the leaders of the table of contents.
+const CONTENTS_LEADER_SEPARATION: Pt = Pt(5.0);
 
 /// The struct that manages the counters for the document.
 #[derive(Clone, Default)]
 pub struct Counters {
     /// The counters.
     pub counters: Vec<usize>,
+
+    /// The counter of the numbered equations.
+    pub equations: usize,
+
+    /// The counter of the footnotes.
+    pub footnotes: usize,
+
+    /// The counter of the figures with a caption.
+    pub figures: usize,
+
+    /// The counter of the tables with a caption.
+    pub tables: usize,
 }
 
 impl Counters {
     /// Creates a new empty counters.
     pub fn new() -> Counters {
-        Counters { counters: vec![0] }
+        Counters {
+            counters: vec![0],
+            equations: 0,
+            footnotes: 0,
+            figures: 0,
+            tables: 0,
+        }
+    }
+
+    /// Increases the equation counter and returns it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use spandex::document::Counters;
+    /// let mut counters = Counters::new();
+    /// assert_eq!(counters.increment_equation(), 1);
+    /// counters.increment(0);
+    /// assert_eq!(counters.increment_equation(), 2);
+    /// ```
+    pub fn increment_equation(&mut self) -> usize {
+        self.equations += 1;
+        self.equations
+    }
+
+    /// Increases the figure counter and returns it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use spandex::document::Counters;
+    /// let mut counters = Counters::new();
+    /// assert_eq!(counters.increment_figure(), 1);
+    /// counters.increment(0);
+    /// assert_eq!(counters.increment_figure(), 2);
+    /// ```
+    pub fn increment_figure(&mut self) -> usize {
+        self.figures += 1;
+        self.figures
+    }
+
+    /// Increases the table counter and returns it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use spandex::document::Counters;
+    /// let mut counters = Counters::new();
+    /// assert_eq!(counters.increment_table(), 1);
+    /// assert_eq!(counters.increment_figure(), 1);
+    /// assert_eq!(counters.increment_table(), 2);
+    /// ```
+    pub fn increment_table(&mut self) -> usize {
+        self.tables += 1;
+        self.tables
     }
 
     /// Increases the corresponding counter and returns it if it is correct.
@@ -78,6 +233,92 @@ impl fmt::Display for Counters {
     }
 }
 
+/// The element a label refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Anchor {
+    /// The number of the element, e.g. `2.3` for a subsection.
+    pub number: String,
+
+    /// The page of the element, starting from 1.
+    pub page: usize,
+
+    /// The vertical position of the element on its page.
+    pub y: Pt,
+}
+
+/// An entry of the table of contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentsEntry {
+    /// The level of the title.
+    pub level: u8,
+
+    /// The number of the title, e.g. `2.3`.
+    pub number: String,
+
+    /// The content of the title, without its labels and its footnotes.
+    pub content: Ast,
+}
+
+/// A title of the document, which is a bookmark of the outline of the pdf.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineEntry {
+    /// The level of the title.
+    pub level: u8,
+
+    /// The number and the text of the title, without markup.
+    pub title: String,
+
+    /// The page of the title, starting from 1.
+    pub page: usize,
+
+    /// The vertical position of the title on its page.
+    pub y: Pt,
+}
+
+/// A clickable area of the document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkArea {
+    /// The page of the area, starting from 1.
+    pub page: usize,
+
+    /// The lower left and upper right corners of the area.
+    pub rectangle: ((Pt, Pt), (Pt, Pt)),
+
+    /// The url the area points to, or the label if it starts with a hash.
+    pub target: String,
+}
+
+/// A line of a footnote, waiting to be written at the bottom of its page.
+struct FootnoteLine {
+    /// The glyphs of the line, with their fonts, sizes, vertical shifts and horizontal offsets.
+    glyphs: Vec<(char, IndirectFontRef, Pt, Pt, Pt)>,
+
+    /// The links of the line, with their horizontal extents and sizes.
+    links: Vec<(String, Pt, Pt, Pt)>,
+
+    /// The underlines and strikethrough lines of the line.
+    decorations: Vec<Decoration>,
+
+    /// The height of the line.
+    height: Pt,
+}
+
+/// An underline or a strikethrough line of a line of text.
+#[derive(Copy, Clone, Debug)]
+struct Decoration {
+    /// The horizontal offset of the start of the line.
+    start: Pt,
+
+    /// The horizontal offset of the end of the line.
+    end: Pt,
+
+    /// The vertical offset of the line from the baseline, positive upwards.
+    offset: Pt,
+
+    /// The thickness of the line, in pt.
+    thickness: f64,
+}
+
 /// The window that is the part of the page on which we're allowed to write.
 #[derive(Copy, Clone)]
 pub struct Window {
@@ -116,6 +357,50 @@ pub struct Document {
 
     /// The counters of the document
     counters: Counters,
+
+    /// The number of the current page, starting from 1.
+    page_number: usize,
+
+    /// The number of the last numbered element, to which the labels refer.
+    anchor: String,
+
+    /// The elements the labels refer to.
+    anchors: HashMap<String, Anchor>,
+
+    /// Whether the document is being resolved, in which case nothing is written.
+    dry_run: bool,
+
+    /// The lines of footnotes to write at the bottom of the current page.
+    footnotes: Vec<FootnoteLine>,
+
+    /// The lines of footnotes that did not fit on the current page.
+    overflow: Vec<FootnoteLine>,
+
+    /// The clickable areas of the links.
+    links: Vec<LinkArea>,
+
+    /// The metadata of the document, written in the info dictionary and the XMP metadata.
+    metadata: Metadata,
+
+    /// The entries of the table of contents, which are unknown until the document is resolved.
+    contents: Option<Vec<ContentsEntry>>,
+
+    /// The titles that were written, which are the bookmarks of the outline of the pdf.
+    outline: Vec<OutlineEntry>,
+
+    /// The entries that can be cited.
+    bibliography: Bibliography,
+
+    /// The way citations refer to the entries of the bibliography.
+    citation_style: CitationStyle,
+
+    /// The keys of the cited entries, in the order of their first citation.
+    cited: Vec<String>,
+
+    /// The images that were decoded, by path, so that an image used many times is decoded once.
+    ///
+    /// An image that cannot be decoded is stored as `None`.
+    images: HashMap<String, Option<DynamicImage>>,
 }
 
 impl Document {
@@ -142,6 +427,20 @@ impl Document {
             cursor: (window.x, window.height + window.y),
             page_size: (width, height),
             counters: Counters::new(),
+            page_number: 1,
+            anchor: String::new(),
+            anchors: HashMap::new(),
+            dry_run: false,
+            footnotes: vec![],
+            overflow: vec![],
+            links: vec![],
+            metadata: Metadata::default(),
+            contents: None,
+            outline: vec![],
+            bibliography: Bibliography::default(),
+            citation_style: CitationStyle::default(),
+            cited: vec![],
+            images: HashMap::new(),
         }
     }
 
@@ -155,7 +454,73 @@ impl Document {
         &mut self.document
     }
 
+    /// Returns the elements the labels refer to.
+    pub fn anchors(&self) -> &HashMap<String, Anchor> {
+        &self.anchors
+    }
+
+    /// Returns the clickable areas of the links.
+    pub fn links(&self) -> &[LinkArea] {
+        &self.links
+    }
+
+    /// Returns the entries of the table of contents, which are empty until the document is
+    /// resolved.
+    pub fn contents(&self) -> &[ContentsEntry] {
+        self.contents.as_deref().unwrap_or(&[])
+    }
+
+    /// Returns the titles that were written, in the order of the document.
+    pub fn outline(&self) -> &[OutlineEntry] {
+        &self.outline
+    }
+
+    /// Sets the bibliography of the document, and the way citations refer to its entries.
+    pub fn set_bibliography(&mut self, bibliography: Bibliography, style: CitationStyle) {
+        self.bibliography = bibliography;
+        self.citation_style = style;
+    }
+
+    /// Sets the metadata of the document.
+    ///
+    /// The title is the one given when creating the document, the other metadata are added to
+    /// the pdf when it is saved.
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    /// Lays out an AST without writing it, to find the numbers and the pages of its labels.
+    ///
+    /// This must be called before rendering the AST for references to labels that are defined
+    /// later in the document to be resolved, and for its table of contents to be written.
+    pub fn resolve(&mut self, ast: &Ast, font_config: &FontConfig, size: Pt) {
+        let cursor = self.cursor;
+        self.contents = Some(contents_entries(ast));
+
+        self.cited.clear();
+        for (key, _) in ast.citations() {
+            if !self.cited.contains(&key) {
+                self.cited.push(key);
+            }
+        }
+
+        self.dry_run = true;
+        self.render(ast, font_config, size);
+        self.dry_run = false;
+
+        self.cursor = cursor;
+        self.counters = Counters::new();
+        self.page_number = 1;
+        self.anchor = String::new();
+        self.footnotes.clear();
+        self.overflow.clear();
+    }
+
     /// Renders an AST to the document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the AST has a table of contents and was not resolved first.
     pub fn render(&mut self, ast: &Ast, font_config: &FontConfig, size: Pt) {
         let en = Standard::from_embedded(Language::EnglishUS).unwrap();
 
@@ -168,13 +533,34 @@ impl Document {
 
             Ast::Title { level, content } => {
                 self.counters.increment(*level as usize);
-                match &**content {
+                self.anchor = self.counters.to_string();
+                self.anchors.insert(
+                    title_label(&self.anchor),
+                    Anchor {
+                        number: self.anchor.clone(),
+                        page: self.page_number,
+                        y: self.cursor.1,
+                    },
+                );
+
+                if !self.dry_run {
+                    let title = plain_text(&self.replace_references(content));
+                    self.outline.push(OutlineEntry {
+                        level: *level,
+                        title: format!("{} {}", self.anchor, title.trim()),
+                        page: self.page_number,
+                        y: self.cursor.1,
+                    });
+                }
+
+                match &content.ast {
                     Ast::Group(children) => {
-                        let mut new_children = vec![Ast::Text(format!("{}  ", self.counters))];
+                        let number = Ast::Text(format!("{}  ", self.counters));
+                        let mut new_children = vec![number.into()];
                         new_children.extend_from_slice(children);
                         let new_ast = Ast::Title {
                             level: *level,
-                            content: Box::new(Ast::Group(new_children)),
+                            content: Box::new(content.map(|_| Ast::Group(new_children))),
                         };
                         self.write_paragraph::<LatexJustifier>(&new_ast, font_config, size, &en);
                     }
@@ -189,73 +575,918 @@ impl Document {
                 self.new_line(size);
             }
 
+            Ast::List { .. } => {
+                self.write_list(ast, font_config, size, &en, 0);
+                self.new_line(size);
+            }
+
+            Ast::DisplayMath {
+                formula, numbered, ..
+            } => {
+                let formula = layout(formula, font_config, size, 0);
+
+                // Leave room for the parts of the formula that are taller than a line.
+                if formula.height > size {
+                    self.cursor.1 -= formula.height - size;
+                }
+
+                if self.cursor.1 <= size + self.bottom() {
+                    self.new_page();
+                }
+
+                let x = self.window.x + (self.window.width - formula.width) / 2.0;
+                for (glyph, offset) in &formula.glyphs {
+                    self.write_glyph(glyph, x + *offset);
+                }
+
+                if *numbered {
+                    self.anchor = self.counters.increment_equation().to_string();
+                    let number = format!("({})", self.anchor);
+                    let width = font_config.regular.text_width(&number, size);
+                    let x = self.window.x + self.window.width - width;
+                    self.write_text(&number, font_config.regular, size, x);
+                }
+
+                if formula.depth > Pt(0.0) {
+                    self.cursor.1 -= formula.depth;
+                }
+
+                self.new_line(size);
+                self.new_line(size);
+            }
+
+            Ast::Figure {
+                path,
+                width,
+                caption,
+                ..
+            } => {
+                self.write_image(path, *width, size);
+
+                if let Some(caption) = caption {
+                    self.anchor = self.counters.increment_figure().to_string();
+                    let number = format!("Figure {}.", self.anchor);
+                    let caption = Ast::Paragraph(vec![
+                        Ast::Bold(Box::new(Ast::Text(number).into())).into(),
+                        Ast::Text(" ".into()).into(),
+                        (**caption).clone(),
+                    ]);
+                    self.write_paragraph::<LatexJustifier>(&caption, font_config, size, &en);
+                    self.new_line(size);
+                }
+
+                self.new_line(size);
+            }
+
+            Ast::Table { .. } => {
+                self.write_table(ast, font_config, size, &en);
+                self.new_line(size);
+                self.new_line(size);
+            }
+
+            Ast::CodeBlock { content, .. } => {
+                self.write_code_block(content, font_config, size);
+                self.new_line(size);
+            }
+
+            Ast::TableOfContents => {
+                self.write_table_of_contents(font_config, size, &en);
+                self.new_line(size);
+            }
+
+            Ast::Bibliography => {
+                self.write_bibliography(font_config, size, &en);
+                self.new_line(size);
+            }
+
+            Ast::Label { .. } => self.record_labels(ast),
+
             _ => (),
         }
     }
 
-    /// Writes content on the document.
-    pub fn write_content(&mut self, content: &str, font_config: &FontConfig, size: Pt) {
-        let en = Standard::from_embedded(Language::EnglishUS).unwrap();
+    /// Writes an image centered below the cursor, scaled to a percentage of the width of the text.
+    ///
+    /// Images that are taller than a page are shrunk to leave room for a few lines of caption.
+    fn write_image(&mut self, path: &str, width: Option<u32>, size: Pt) {
+        // The image was checked when it was parsed.
+        let (pixels_width, pixels_height) = match image::image_dimensions(path) {
+            Ok(dimensions) => dimensions,
+            Err(_) => return,
+        };
 
-        for paragraph in content.split('\n') {
-            let ast = Ast::Text(paragraph.to_owned());
-            self.write_paragraph::<LatexJustifier>(&ast, font_config, size, &en);
-            self.new_line(size);
+        let ratio = f64::from(pixels_height) / f64::from(pixels_width);
+        let mut width = self.window.width * (f64::from(width.unwrap_or(100)) / 100.0);
+        let mut height = width * ratio;
+
+        let max_height = self.window.height - size * FIGURE_CAPTION_LINES;
+        if height > max_height {
+            height = max_height;
+            width = height / ratio;
         }
+
+        if self.cursor.1 - height < self.bottom() {
+            self.new_page();
+        }
+
+        let x = self.window.x + (self.window.width - width) / 2.0;
+        let y = self.cursor.1 - height;
+
+        if !self.dry_run {
+            let image = self.images.entry(path.to_owned()).or_insert_with(|| {
+                // Images with an alpha channel are not supported by printpdf.
+                let image = image::open(path).ok()?;
+                Some(DynamicImage::ImageRgb8(image.to_rgb()))
+            });
+
+            if let Some(image) = image {
+                let image = Image::from_dynamic_image(image);
+                let scale = width.0 / f64::from(pixels_width);
+                image.add_to_layer(
+                    self.layer.clone(),
+                    Some(x.into()),
+                    Some(y.into()),
+                    None,
+                    Some(scale),
+                    Some(scale),
+                    Some(IMAGE_DPI),
+                );
+            }
+        }
+
+        self.cursor.1 = y - size;
     }
 
-    /// Writes a paragraph on the document.
-    pub fn write_paragraph<J: Justifier>(
+    /// Records the labels of an AST as referring to the last numbered element.
+    fn record_labels(&mut self, ast: &Ast) {
+        for (label, _) in ast.labels() {
+            let anchor = Anchor {
+                number: self.anchor.clone(),
+                page: self.page_number,
+                y: self.cursor.1,
+            };
+            self.anchors.insert(label, anchor);
+        }
+    }
+
+    /// Returns the text of a citation, e.g. `[1, 3]` or `(Knuth 1984; Lamport 1994)` depending on
+    /// the citation style.
+    ///
+    /// Entries that are not in the bibliography are cited with question marks.
+    fn citation(&self, keys: &[String]) -> String {
+        let labels = keys.iter().map(|key| match self.citation_style {
+            CitationStyle::Numeric => match self.cited.iter().position(|x| x == key) {
+                Some(index) => (index + 1).to_string(),
+                None => String::from("?"),
+            },
+            CitationStyle::AuthorYear => match self.bibliography.get(key) {
+                Some(entry) => entry.author_year(),
+                None => String::from("?"),
+            },
+        });
+
+        match self.citation_style {
+            CitationStyle::Numeric => format!("[{}]", labels.collect::<Vec<_>>().join(", ")),
+            CitationStyle::AuthorYear => format!("({})", labels.collect::<Vec<_>>().join("; ")),
+        }
+    }
+
+    /// Replaces the references of an AST by the numbers or the pages of their labels, and its
+    /// citations by the labels of the cited entries.
+    ///
+    /// References to labels that are not known yet are replaced by question marks.
+    fn replace_references(&self, ast: &Ast) -> Ast {
+        let replace = |children: &[Node]| {
+            children
+                .iter()
+                .map(|x| x.map(|x| self.replace_references(x)))
+                .collect::<Vec<_>>()
+        };
+        let replace_content =
+            |content: &Node| Box::new(content.map(|x| self.replace_references(x)));
+
+        match ast {
+            Ast::Reference { label, page, .. } => Ast::Text(match self.anchors.get(label) {
+                Some(anchor) if *page => anchor.page.to_string(),
+                Some(anchor) => anchor.number.clone(),
+                None => String::from("??"),
+            }),
+
+            Ast::Citation { keys } => {
+                let keys = keys.iter().map(|x| x.0.clone()).collect::<Vec<_>>();
+                Ast::Text(self.citation(&keys))
+            }
+
+            Ast::Group(children) => Ast::Group(replace(children)),
+            Ast::Paragraph(children) => Ast::Paragraph(replace(children)),
+            Ast::Bold(content) => Ast::Bold(replace_content(content)),
+            Ast::Footnote(content) => Ast::Footnote(replace_content(content)),
+
+            Ast::Link {
+                url,
+                content,
+                position,
+            } => Ast::Link {
+                url: url.clone(),
+                content: replace_content(content),
+                position: *position,
+            },
+            Ast::Italic(content) => Ast::Italic(replace_content(content)),
+            Ast::SmallCaps(content) => Ast::SmallCaps(replace_content(content)),
+            Ast::Underline(content) => Ast::Underline(replace_content(content)),
+            Ast::Strikethrough(content) => Ast::Strikethrough(replace_content(content)),
+            Ast::Superscript(content) => Ast::Superscript(replace_content(content)),
+            Ast::Subscript(content) => Ast::Subscript(replace_content(content)),
+
+            Ast::Title { level, content } => Ast::Title {
+                level: *level,
+                content: replace_content(content),
+            },
+
+            Ast::List {
+                ordered,
+                start,
+                items,
+            } => Ast::List {
+                ordered: *ordered,
+                start: *start,
+                items: replace(items),
+            },
+
+            Ast::ListItem { content, children } => Ast::ListItem {
+                content: replace_content(content),
+                children: replace(children),
+            },
+
+            _ => ast.clone(),
+        }
+    }
+
+    /// Writes a list on the document.
+    ///
+    /// The items are indented once more than their list, unless the indentation would leave less
+    /// than `LIST_MIN_WIDTH` for their content, and their markers hang in their indentation.
+    pub fn write_list(
         &mut self,
-        paragraph: &Ast,
+        list: &Ast,
         font_config: &FontConfig,
         size: Pt,
         dict: &Standard,
+        depth: usize,
     ) {
-        let paragraph = itemize_ast(paragraph, font_config, size, dict, Pt(0.0));
-        let justified = J::justify(&paragraph, self.window.width);
+        let (ordered, start, items) = match list {
+            Ast::List {
+                ordered,
+                start,
+                items,
+            } => (*ordered, *start, items),
+            _ => return,
+        };
 
-        for line in justified {
-            for glyph in line {
-                self.layer.use_text(
-                    glyph.0.glyph.to_string(),
-                    Into::<Pt>::into(glyph.0.scale).0 as i64,
-                    (self.window.x + glyph.1).into(),
-                    self.cursor.1.into(),
-                    glyph.0.font.printpdf(),
-                );
-            }
+        let levels = ((self.window.width - LIST_MIN_WIDTH).0 / LIST_INDENT.0).max(0.0) as usize;
+        let indent = LIST_INDENT * (depth + 1).min(levels) as f64;
 
-            self.new_line(size);
-            self.cursor.0 = self.window.x;
+        for (index, item) in items.iter().enumerate() {
+            let text = if ordered {
+                format!("{}.", start.saturating_add(index))
+            } else {
+                String::from(BULLETS[depth % BULLETS.len()])
+            };
 
-            if self.cursor.1 <= size + self.window.y {
-                self.new_page();
+            let marker = ListMarker { text, indent };
+            self.write_list_item(item, marker, font_config, size, dict);
+
+            if let Ast::ListItem { children, .. } = &item.ast {
+                for child in children {
+                    self.write_list(child, font_config, size, dict, depth + 1);
+                }
             }
         }
     }
 
-    /// Writes a line in the document.
-    pub fn write_line(&mut self, words: &[&str], font: &Font, size: Pt, spacing: Pt) {
-        let size_i64 = Into::<Pt>::into(size).0 as i64;
-        let mut current_width = self.window.x;
+    /// Writes a table on the document, preceded by its caption.
+    ///
+    /// The columns are as wide as their content if the table fits in the width of the text, and
+    /// the widest columns are shrunk otherwise, their cells being broken into several lines.
+    pub fn write_table(
+        &mut self,
+        table: &Ast,
+        font_config: &FontConfig,
+        size: Pt,
+        dict: &Standard,
+    ) {
+        let (alignments, header, rows, caption) = match table {
+            Ast::Table {
+                alignments,
+                header,
+                rows,
+                caption,
+            } => (alignments, header, rows, caption),
+            _ => return,
+        };
 
-        for word in words {
-            let width = current_width;
-            let height = self.cursor.1;
+        if let Some(caption) = caption {
+            self.anchor = self.counters.increment_table().to_string();
+            let number = format!("Table {}.", self.anchor);
+            let caption = Ast::Paragraph(vec![
+                Ast::Bold(Box::new(Ast::Text(number).into())).into(),
+                Ast::Text(" ".into()).into(),
+                (**caption).clone(),
+            ]);
+            self.write_paragraph::<LatexJustifier>(&caption, font_config, size, dict);
+        }
 
-            self.layer.use_text(
-                word.to_owned(),
-                size_i64,
-                width.into(),
-                (height - size).into(),
-                font.printpdf(),
-            );
-            current_width += font.text_width(word, size) + spacing;
+        let mut natural = vec![TABLE_MIN_COLUMN_WIDTH; alignments.len()];
+        let mut minimum = vec![TABLE_MIN_COLUMN_WIDTH; alignments.len()];
+        for row in Some(header).into_iter().chain(rows) {
+            for ((width, min_width), cell) in natural.iter_mut().zip(&mut minimum).zip(row) {
+                let cell = self.replace_references(cell);
+                let cell_width = natural_width(&cell, font_config, FontStyle::regular(), size);
+                if cell_width > *width {
+                    *width = cell_width;
+                }
+
+                let paragraph = itemize_ast(&cell, font_config, size, dict, Pt(0.0));
+                let cell_width = minimal_width(&paragraph);
+                if cell_width > *min_width {
+                    *min_width = cell_width;
+                }
+
+                if *min_width > *width {
+                    *width = *min_width;
+                }
+            }
+        }
+
+        let separations = TABLE_COLUMN_SEPARATION * (alignments.len().max(1) - 1) as f64;
+        let widths = column_widths(&natural, &minimum, self.window.width - separations);
+        let table_width = widths
+            .iter()
+            .fold(separations, |total, width| total + *width);
+
+        let start = self.window.x + (self.window.width - table_width) / 2.0;
+        let end = start + table_width;
+        let columns = alignments.iter().zip(widths).collect::<Vec<_>>();
+
+        // The header and the first row should not be separated from the top rule.
+        if self.cursor.1 - size * 3.0 < self.bottom() {
+            self.new_page();
         }
 
+        self.write_rule(start, end, self.cursor.1, TABLE_OUTER_RULE_THICKNESS);
         self.new_line(size);
-    }
+        self.write_row(header, &columns, start, font_config, size, dict);
+
+        let rule = self.cursor.1 - size * TABLE_RULE_DEPTH;
+        self.write_rule(start, end, rule, TABLE_INNER_RULE_THICKNESS);
+
+        for row in rows {
+            self.new_line(size);
+            self.write_row(row, &columns, start, font_config, size, dict);
+        }
+
+        let rule = self.cursor.1 - size * TABLE_RULE_DEPTH;
+        self.write_rule(start, end, rule, TABLE_OUTER_RULE_THICKNESS);
+    }
+
+    /// Writes a row of a table whose first baseline is the current one, and leaves the cursor on
+    /// its last baseline.
+    ///
+    /// The row goes on the next page if it doesn't fit on the current one.
+    fn write_row(
+        &mut self,
+        row: &[Node],
+        columns: &[(&Alignment, Pt)],
+        start: Pt,
+        font_config: &FontConfig,
+        size: Pt,
+        dict: &Standard,
+    ) {
+        let mut paragraphs = vec![];
+        for cell in row {
+            self.record_labels(cell);
+            let cell = Ast::Paragraph(vec![cell.map(|x| self.replace_references(x))]);
+            let footnotes = self.counters.footnotes;
+            let paragraph =
+                itemize_ast_with_footnotes(&cell, font_config, size, dict, Pt(0.0), footnotes);
+            self.counters.footnotes += paragraph.footnotes.len();
+            paragraphs.push(paragraph);
+        }
+
+        let cells = paragraphs
+            .iter()
+            .zip(columns)
+            .map(|(paragraph, (_, width))| (paragraph, LatexJustifier::justify(paragraph, *width)))
+            .collect::<Vec<_>>();
+
+        let lines = cells.iter().map(|x| x.1.len()).max().unwrap_or(0).max(1);
+        let height = size * (lines - 1) as f64 + size * TABLE_RULE_DEPTH;
+        if self.cursor.1 - height < self.bottom() {
+            self.new_page();
+        }
+
+        let top = self.cursor.1;
+        let mut x = start;
+
+        for ((paragraph, lines), (alignment, width)) in cells.iter().zip(columns) {
+            self.cursor.1 = top;
+
+            for (index, line) in lines.iter().enumerate() {
+                if index > 0 {
+                    self.new_line(size);
+                }
+
+                let line_width = match line.last() {
+                    Some((glyph, offset)) => {
+                        *offset + glyph.font.char_width(glyph.glyph, glyph.scale)
+                    }
+                    None => Pt(0.0),
+                };
+
+                let offset = match alignment {
+                    Alignment::Left => Pt(0.0),
+                    Alignment::Center => (*width - line_width) / 2.0,
+                    Alignment::Right => *width - line_width,
+                };
+
+                self.write_justified_line(line, paragraph, x + offset, font_config, size, dict);
+            }
+
+            x += *width + TABLE_COLUMN_SEPARATION;
+        }
+
+        self.cursor.1 = top - size * (lines - 1) as f64;
+    }
+
+    /// Writes a block of code line by line, without justification and keeping its whitespaces.
+    pub fn write_code_block(&mut self, content: &str, font_config: &FontConfig, size: Pt) {
+        let font = font_config.monospace;
+        let columns = (self.window.width.0 / font.char_width(' ', size).0) as usize;
+
+        for line in content.lines() {
+            for line in wrap_code_line(&expand_tabs(line), columns) {
+                self.write_text(&line, font, size, self.window.x);
+                self.new_line(size);
+
+                if self.cursor.1 <= size + self.bottom() {
+                    self.new_page();
+                }
+            }
+        }
+    }
+
+    /// Draws a horizontal rule on the document.
+    fn write_rule(&self, start: Pt, end: Pt, y: Pt, thickness: f64) {
+        if self.dry_run {
+            return;
+        }
+
+        self.layer.set_outline_thickness(thickness);
+        self.layer.add_shape(Line {
+            points: vec![
+                (Point::new(start.into(), y.into()), false),
+                (Point::new(end.into(), y.into()), false),
+            ],
+            is_closed: false,
+            has_fill: false,
+            has_stroke: true,
+            is_clipping_path: false,
+        });
+    }
+
+    /// Writes content on the document.
+    pub fn write_content(&mut self, content: &str, font_config: &FontConfig, size: Pt) {
+        let en = Standard::from_embedded(Language::EnglishUS).unwrap();
+
+        for paragraph in content.split('\n') {
+            let ast = Ast::Text(paragraph.to_owned());
+            self.write_paragraph::<LatexJustifier>(&ast, font_config, size, &en);
+            self.new_line(size);
+        }
+    }
+
+    /// Writes a paragraph on the document.
+    pub fn write_paragraph<J: Justifier>(
+        &mut self,
+        paragraph: &Ast,
+        font_config: &FontConfig,
+        size: Pt,
+        dict: &Standard,
+    ) {
+        self.record_labels(paragraph);
+        let paragraph = self.replace_references(paragraph);
+
+        let footnotes = self.counters.footnotes;
+        let paragraph =
+            itemize_ast_with_footnotes(&paragraph, font_config, size, dict, Pt(0.0), footnotes);
+        self.write_itemized::<J>(&paragraph, font_config, size, dict);
+    }
+
+    /// Writes an item of a list as a paragraph, whose marker hangs in its indentation.
+    fn write_list_item(
+        &mut self,
+        item: &Ast,
+        marker: ListMarker,
+        font_config: &FontConfig,
+        size: Pt,
+        dict: &Standard,
+    ) {
+        self.record_labels(item);
+        let item = self.replace_references(item);
+
+        let footnotes = self.counters.footnotes;
+        let paragraph = itemize_list_item(&item, marker, font_config, size, dict, footnotes);
+        self.write_itemized::<LatexJustifier>(&paragraph, font_config, size, dict);
+    }
+
+    /// Writes a paragraph that is already itemized, and counts its footnotes.
+    fn write_itemized<J: Justifier>(
+        &mut self,
+        paragraph: &Paragraph,
+        font_config: &FontConfig,
+        size: Pt,
+        dict: &Standard,
+    ) {
+        self.counters.footnotes += paragraph.footnotes.len();
+
+        let justified = J::justify(paragraph, self.window.width);
+
+        for line in justified {
+            self.write_justified_line(&line, paragraph, self.window.x, font_config, size, dict);
+
+            self.new_line(size);
+            self.cursor.0 = self.window.x;
+
+            if self.cursor.1 <= size + self.bottom() {
+                self.new_page();
+            }
+        }
+    }
+
+    /// Writes a line of a paragraph on the current baseline, starting at a horizontal position,
+    /// and adds its footnotes and its links.
+    fn write_justified_line(
+        &mut self,
+        line: &[(Glyph, Pt)],
+        paragraph: &Paragraph,
+        x: Pt,
+        font_config: &FontConfig,
+        size: Pt,
+        dict: &Standard,
+    ) {
+        let mut markers = line.iter().filter_map(|x| x.0.footnote).collect::<Vec<_>>();
+        markers.dedup();
+
+        for (number, content) in &paragraph.footnotes {
+            if markers.contains(number) {
+                self.add_footnote(*number, content, font_config, size, dict);
+            }
+        }
+
+        for glyph in line {
+            self.write_glyph(&glyph.0, x + glyph.1);
+        }
+
+        for decoration in decorations(line) {
+            let y = self.cursor.1 + decoration.offset;
+            let (start, end) = (x + decoration.start, x + decoration.end);
+            self.write_rule(start, end, y, decoration.thickness);
+        }
+
+        for (link, start, end, scale) in link_extents(line) {
+            let target = paragraph.links[link].clone();
+            let baseline = self.cursor.1;
+            self.add_link(target, x + start, x + end, baseline, scale);
+        }
+    }
+
+    /// Writes the table of contents, with the pages of the titles found when the document was
+    /// resolved.
+    ///
+    /// The entries are indented according to the level of their titles, and their page numbers
+    /// are aligned on the right, after dot leaders. Each entry links to its title.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the document was not resolved, since its titles are not known yet.
+    pub fn write_table_of_contents(&mut self, font_config: &FontConfig, size: Pt, dict: &Standard) {
+        let contents = self
+            .contents
+            .clone()
+            .expect("the document must be resolved before its table of contents is written");
+
+        self.write_heading("Contents", font_config, size, dict);
+
+        let window = self.window;
+        let right = window.x + window.width;
+        let dot_width = font_config.regular.char_width('.', size);
+
+        for entry in contents {
+            let label = title_label(&entry.number);
+            let page = match self.anchors.get(&label) {
+                Some(anchor) => anchor.page.to_string(),
+                None => String::from("??"),
+            };
+
+            let indent = CONTENTS_INDENT * f64::from(entry.level);
+            self.window.x = window.x + indent;
+            self.window.width = window.width - indent - CONTENTS_PAGE_WIDTH;
+
+            let title = Ast::Group(vec![
+                Ast::Text(format!("{}  ", entry.number)).into(),
+                entry.content.clone().into(),
+            ]);
+            let title = if entry.level == 0 {
+                Ast::Bold(Box::new(title.into()))
+            } else {
+                title
+            };
+            let title = self.replace_references(&Ast::Paragraph(vec![title.into()]));
+
+            let paragraph = itemize_ast(&title, font_config, size, dict, Pt(0.0));
+            let justified = LatexJustifier::justify(&paragraph, self.window.width);
+            let lines = justified.len();
+
+            for (index, line) in justified.iter().enumerate() {
+                let x = self.window.x;
+                self.write_justified_line(line, &paragraph, x, font_config, size, dict);
+                self.add_link(format!("#{}", label), x, right, self.cursor.1, size);
+
+                if index + 1 == lines {
+                    let end = line.last().map_or(Pt(0.0), |(glyph, offset)| {
+                        *offset + glyph.font.char_width(glyph.glyph, glyph.scale)
+                    });
+
+                    let page_width = font_config.regular.text_width(&page, size);
+                    let page_x = right - page_width;
+
+                    // The dots are aligned on a grid, so that they line up from one entry to the
+                    // next.
+                    let separation = CONTENTS_LEADER_SEPARATION;
+                    let mut dot = ((x + end - window.x).0 / separation.0).ceil() + 1.0;
+                    while window.x + separation * dot + dot_width + separation <= page_x {
+                        let dot_x = window.x + separation * dot;
+                        self.write_text(".", font_config.regular, size, dot_x);
+                        dot += 1.0;
+                    }
+
+                    self.write_text(&page, font_config.regular, size, page_x);
+                }
+
+                self.new_line(size);
+                self.cursor.0 = self.window.x;
+
+                if self.cursor.1 <= size + self.bottom() {
+                    self.new_page();
+                }
+            }
+        }
+
+        self.window = window;
+    }
+
+    /// Writes the cited entries of the bibliography, in the order of their numbers or of their
+    /// labels depending on the citation style.
+    ///
+    /// The numbers hang in the indentation of the entries.
+    pub fn write_bibliography(&mut self, font_config: &FontConfig, size: Pt, dict: &Standard) {
+        self.write_heading("References", font_config, size, dict);
+
+        let mut entries = self
+            .cited
+            .iter()
+            .filter_map(|x| self.bibliography.get(x))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if self.citation_style == CitationStyle::AuthorYear {
+            entries.sort_by_key(|x| x.author_year());
+            for entry in entries {
+                let reference = Ast::Paragraph(vec![entry.reference().into()]);
+                self.write_paragraph::<LatexJustifier>(&reference, font_config, size, dict);
+            }
+            return;
+        }
+
+        let labels = (1..=entries.len())
+            .map(|x| format!("[{}]", x))
+            .collect::<Vec<_>>();
+        let indent = labels
+            .iter()
+            .map(|x| font_config.regular.text_width(x, size))
+            .fold(Pt(0.0), |max, x| if x > max { x } else { max });
+
+        let window = self.window;
+        self.window.x += indent + LIST_MARKER_SEPARATION;
+        self.window.width -= indent + LIST_MARKER_SEPARATION;
+
+        for (entry, label) in entries.iter().zip(labels) {
+            let x = window.x + indent - font_config.regular.text_width(&label, size);
+            self.write_text(&label, font_config.regular, size, x);
+
+            let reference = Ast::Paragraph(vec![entry.reference().into()]);
+            self.write_paragraph::<LatexJustifier>(&reference, font_config, size, dict);
+        }
+
+        self.window = window;
+    }
+
+    /// Writes an unnumbered heading, such as the one of the table of contents.
+    fn write_heading(&mut self, text: &str, font_config: &FontConfig, size: Pt, dict: &Standard) {
+        let heading = Ast::Title {
+            level: 0,
+            content: Box::new(Ast::Group(vec![Ast::Text(text.into()).into()]).into()),
+        };
+        self.write_paragraph::<LatexJustifier>(&heading, font_config, size, dict);
+        self.new_line(size);
+    }
+
+    /// Lays out a footnote and reserves space for it at the bottom of the current page.
+    ///
+    /// The lines of the footnote that don't fit below the current line continue on the next page.
+    pub fn add_footnote(
+        &mut self,
+        number: usize,
+        content: &Ast,
+        font_config: &FontConfig,
+        size: Pt,
+        dict: &Standard,
+    ) {
+        let footnote_size = size * FOOTNOTE_RATIO;
+        let paragraph = itemize_footnote(number, content, font_config, footnote_size, dict);
+
+        for line in LatexJustifier::justify(&paragraph, self.window.width) {
+            let glyphs = line
+                .iter()
+                .map(|(glyph, x)| {
+                    let font = glyph.font.printpdf().clone();
+                    (glyph.glyph, font, glyph.scale, glyph.shift, *x)
+                })
+                .collect();
+
+            let links = link_extents(&line)
+                .into_iter()
+                .map(|(link, start, end, scale)| (paragraph.links[link].clone(), start, end, scale))
+                .collect();
+
+            let line = FootnoteLine {
+                glyphs,
+                links,
+                decorations: decorations(&line),
+                height: footnote_size,
+            };
+
+            self.add_footnote_line(line, size);
+        }
+    }
+
+    /// Adds a line of footnote to the current page if it fits below the current line, or keeps it
+    /// for the next page otherwise.
+    fn add_footnote_line(&mut self, line: FootnoteLine, size: Pt) {
+        let height = if self.footnotes.is_empty() {
+            FOOTNOTE_SEPARATION + line.height
+        } else {
+            line.height
+        };
+
+        if self.overflow.is_empty() && self.bottom() + height + size <= self.cursor.1 {
+            self.footnotes.push(line);
+        } else {
+            self.overflow.push(line);
+        }
+    }
+
+    /// Returns the height taken by the footnotes at the bottom of the current page.
+    fn footnotes_height(&self) -> Pt {
+        if self.footnotes.is_empty() {
+            return Pt(0.0);
+        }
+
+        self.footnotes
+            .iter()
+            .fold(FOOTNOTE_SEPARATION, |height, line| height + line.height)
+    }
+
+    /// Returns the lowest position the text can reach on the current page.
+    fn bottom(&self) -> Pt {
+        self.window.y + self.footnotes_height()
+    }
+
+    /// Writes the footnotes at the bottom of the current page.
+    fn write_footnotes(&mut self) {
+        let mut y = self.bottom() - FOOTNOTE_SEPARATION;
+        let lines = mem::take(&mut self.footnotes);
+
+        if lines.is_empty() || self.dry_run {
+            return;
+        }
+
+        let rule_y = y + FOOTNOTE_SEPARATION / 2.0;
+        let rule_width = self.window.width * FOOTNOTE_RULE_RATIO;
+        let (start, end) = (self.window.x, self.window.x + rule_width);
+        self.write_rule(start, end, rule_y, FOOTNOTE_RULE_THICKNESS);
+
+        for line in lines {
+            y -= line.height;
+
+            for (glyph, font, scale, shift, x) in line.glyphs {
+                self.layer.use_text(
+                    glyph.to_string(),
+                    scale.0 as i64,
+                    (self.window.x + x).into(),
+                    (y + shift).into(),
+                    &font,
+                );
+            }
+
+            for (target, start, end, scale) in line.links {
+                self.add_link(target, self.window.x + start, self.window.x + end, y, scale);
+            }
+
+            for decoration in line.decorations {
+                let (start, end) = (
+                    self.window.x + decoration.start,
+                    self.window.x + decoration.end,
+                );
+                self.write_rule(start, end, y + decoration.offset, decoration.thickness);
+            }
+        }
+    }
+
+    /// Adds a clickable area for a link on the current page, around some text.
+    fn add_link(&mut self, target: String, start: Pt, end: Pt, baseline: Pt, size: Pt) {
+        if self.dry_run {
+            return;
+        }
+
+        self.links.push(LinkArea {
+            page: self.page_number,
+            rectangle: (
+                (start, baseline - size * LINK_DEPTH),
+                (end, baseline + size * LINK_HEIGHT),
+            ),
+            target,
+        });
+    }
+
+    /// Writes some text on the current line of the document.
+    pub fn write_text(&self, text: &str, font: &Font, size: Pt, x: Pt) {
+        if self.dry_run {
+            return;
+        }
+
+        self.layer.use_text(
+            text,
+            size.0 as i64,
+            x.into(),
+            self.cursor.1.into(),
+            font.printpdf(),
+        );
+    }
+
+    /// Writes a glyph on the current line of the document.
+    pub fn write_glyph(&self, glyph: &Glyph, x: Pt) {
+        if self.dry_run {
+            return;
+        }
+
+        if let Some(width) = glyph.rule {
+            let (height, depth) = glyph.font.char_bounds(glyph.glyph, glyph.scale);
+            let y = self.cursor.1 + glyph.shift + (height - depth) / 2.0;
+            self.write_rule(x, x + width, y, (height + depth).0);
+            return;
+        }
+
+        self.layer.use_text(
+            glyph.glyph.to_string(),
+            Into::<Pt>::into(glyph.scale).0 as i64,
+            x.into(),
+            (self.cursor.1 + glyph.shift).into(),
+            glyph.font.printpdf(),
+        );
+    }
+
+    /// Writes a line in the document.
+    pub fn write_line(&mut self, words: &[&str], font: &Font, size: Pt, spacing: Pt) {
+        let size_i64 = Into::<Pt>::into(size).0 as i64;
+        let mut current_width = self.window.x;
+
+        for word in words {
+            let width = current_width;
+            let height = self.cursor.1;
+
+            self.layer.use_text(
+                word.to_owned(),
+                size_i64,
+                width.into(),
+                (height - size).into(),
+                font.printpdf(),
+            );
+            current_width += font.text_width(word, size) + spacing;
+        }
+
+        self.new_line(size);
+    }
 
     /// Goes to the beginning of the next line.
     pub fn new_line(&mut self, size: Pt) {
@@ -264,18 +1495,1068 @@ impl Document {
 
     /// Creates a new page and append it to the document.
     pub fn new_page(&mut self) {
-        let page = self
-            .document
-            .add_page(self.page_size.0.into(), self.page_size.1.into(), "");
-        self.page = self.document.get_page(page.0);
-        self.layer = self.page.get_layer(page.1);
+        self.write_footnotes();
+
+        self.page_number += 1;
         self.cursor.1 = self.window.height + self.window.y;
+
+        if !self.dry_run {
+            let page = self
+                .document
+                .add_page(self.page_size.0.into(), self.page_size.1.into(), "");
+            self.page = self.document.get_page(page.0);
+            self.layer = self.page.get_layer(page.1);
+        }
+
+        for line in mem::take(&mut self.overflow) {
+            self.add_footnote_line(line, Pt(0.0));
+        }
     }
 
     /// Saves the document into a file.
-    pub fn save<P: AsRef<Path>>(self, path: P) {
-        let file = File::create(path.as_ref()).unwrap();
+    ///
+    /// The footnotes that are still waiting to be written are written first, on new pages if they
+    /// don't fit on the current one. An error is returned if the pdf cannot be generated or
+    /// written.
+    pub fn save<P: AsRef<Path>>(mut self, path: P) -> Result<()> {
+        while !self.overflow.is_empty() {
+            self.new_page();
+        }
+        self.write_footnotes();
+
+        // Printpdf doesn't support annotations, outlines nor most metadata, so they are added to
+        // the saved pdf.
+        let mut bytes = vec![];
+        self.document.save(&mut BufWriter::new(&mut bytes))?;
+
+        let mut pdf = lopdf::Document::load_mem(&bytes)?;
+        add_link_annotations(&mut pdf, &self.links, &self.anchors);
+        add_metadata(&mut pdf, &self.metadata);
+        add_outline(&mut pdf, &self.outline);
+
+        let file = File::create(path.as_ref())?;
         let mut writer = BufWriter::new(file);
-        self.document.save(&mut writer).unwrap();
+        pdf.save_to(&mut writer)?;
+        Ok(())
+    }
+}
+
+/// Returns the label of the anchor of a title from its number.
+///
+/// It contains a space, which can't be part of the labels of the dex files.
+fn title_label(number: &str) -> String {
+    format!("title {}", number)
+}
+
+/// Returns the entries of the table of contents of an AST, numbered as its titles are rendered.
+fn contents_entries(ast: &Ast) -> Vec<ContentsEntry> {
+    fn collect(ast: &Ast, counters: &mut Counters, entries: &mut Vec<ContentsEntry>) {
+        match ast {
+            Ast::Group(children) => {
+                for child in children {
+                    collect(child, counters, entries);
+                }
+            }
+
+            Ast::Title { level, content } => {
+                counters.increment(*level as usize);
+                entries.push(ContentsEntry {
+                    level: *level,
+                    number: counters.to_string(),
+                    content: contents_title(content),
+                });
+            }
+
+            _ => (),
+        }
+    }
+
+    let mut entries = vec![];
+    collect(ast, &mut Counters::new(), &mut entries);
+    entries
+}
+
+/// Removes the labels and the footnotes of the content of a title, which must not be repeated in
+/// the table of contents.
+fn contents_title(ast: &Ast) -> Ast {
+    let filter = |children: &[Node]| {
+        children
+            .iter()
+            .filter(|x| !matches!(x.ast, Ast::Label { .. } | Ast::Footnote(_)))
+            .map(|x| x.map(contents_title))
+            .collect()
+    };
+
+    match ast {
+        Ast::Group(children) => Ast::Group(filter(children)),
+        Ast::Bold(content) => Ast::Bold(Box::new(content.map(contents_title))),
+        Ast::Italic(content) => Ast::Italic(Box::new(content.map(contents_title))),
+        Ast::SmallCaps(content) => Ast::SmallCaps(Box::new(content.map(contents_title))),
+        Ast::Underline(content) => Ast::Underline(Box::new(content.map(contents_title))),
+        Ast::Strikethrough(content) => Ast::Strikethrough(Box::new(content.map(contents_title))),
+        Ast::Superscript(content) => Ast::Superscript(Box::new(content.map(contents_title))),
+        Ast::Subscript(content) => Ast::Subscript(Box::new(content.map(contents_title))),
+        _ => ast.clone(),
+    }
+}
+
+/// Returns the text of an AST without its markup, its labels and its footnotes.
+fn plain_text(ast: &Ast) -> String {
+    match ast {
+        Ast::Text(content)
+        | Ast::Escaped(content)
+        | Ast::Code(content)
+        | Ast::InlineMath { content, .. } => content.clone(),
+        Ast::Label { .. } | Ast::Footnote(_) => String::new(),
+        _ => ast.children().into_iter().map(|x| plain_text(x)).collect(),
+    }
+}
+
+/// Replaces the tabulations of a line of code by spaces, up to the next tab stop.
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    let mut column = 0;
+
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - column % TAB_WIDTH;
+            expanded.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+
+    expanded
+}
+
+/// Splits a line of code into lines of at most a number of columns, so that a long line is wrapped
+/// instead of overflowing the window.
+fn wrap_code_line(line: &str, columns: usize) -> Vec<String> {
+    let chars = line.chars().collect::<Vec<_>>();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+
+    chars
+        .chunks(columns.max(1))
+        .map(|x| x.iter().collect())
+        .collect()
+}
+
+/// Distributes the available width between the columns of a table.
+///
+/// If the columns don't fit, the ones that are narrower than an equal share of the width keep
+/// their width, and the others share the rest in proportion to their widths, without getting
+/// narrower than their minimal widths. The columns take their minimal widths when even those
+/// don't fit, the table being wider than the available width.
+fn column_widths(natural: &[Pt], minimum: &[Pt], available: Pt) -> Vec<Pt> {
+    let total = natural.iter().fold(Pt(0.0), |total, width| total + *width);
+    if total <= available {
+        return natural.to_vec();
+    }
+
+    let total = minimum.iter().fold(Pt(0.0), |total, width| total + *width);
+    if total >= available {
+        return minimum.to_vec();
+    }
+
+    let mut fixed: Vec<Option<Pt>> = vec![None; natural.len()];
+
+    while fixed.iter().any(Option::is_none) {
+        let (mut free, mut wide, mut count) = (available, Pt(0.0), 0);
+        for (width, fixed) in natural.iter().zip(&fixed) {
+            match fixed {
+                Some(fixed) => free -= *fixed,
+                None => {
+                    wide += *width;
+                    count += 1;
+                }
+            }
+        }
+
+        let share = free / count as f64;
+        let mut changed = false;
+        for (width, fixed) in natural.iter().zip(fixed.iter_mut()) {
+            if fixed.is_none() && *width <= share {
+                *fixed = Some(*width);
+                changed = true;
+            }
+        }
+
+        if changed {
+            continue;
+        }
+
+        for ((width, min_width), fixed) in natural.iter().zip(minimum).zip(fixed.iter_mut()) {
+            if fixed.is_none() && free * (width.0 / wide.0) < *min_width {
+                *fixed = Some(*min_width);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return natural
+                .iter()
+                .zip(&fixed)
+                .map(|(width, fixed)| fixed.unwrap_or(free * (width.0 / wide.0)))
+                .collect();
+        }
+    }
+
+    fixed.into_iter().flatten().collect()
+}
+
+/// Returns the links of a line, with their horizontal extents and the size of their largest glyph.
+fn link_extents(line: &[(Glyph, Pt)]) -> Vec<(usize, Pt, Pt, Pt)> {
+    let mut extents: Vec<(usize, Pt, Pt, Pt)> = vec![];
+
+    for (glyph, x) in line {
+        let link = match glyph.link {
+            Some(link) => link,
+            None => continue,
+        };
+
+        let end = *x + glyph.font.char_width(glyph.glyph, glyph.scale);
+
+        match extents.last_mut() {
+            Some(last) if last.0 == link => {
+                last.2 = end;
+                if glyph.scale > last.3 {
+                    last.3 = glyph.scale;
+                }
+            }
+            _ => extents.push((link, *x, end, glyph.scale)),
+        }
+    }
+
+    extents
+}
+
+/// Returns the underlines and the strikethrough lines of a line, which span the consecutive
+/// decorated glyphs and the spaces between them.
+fn decorations(line: &[(Glyph, Pt)]) -> Vec<Decoration> {
+    let underlines = decorated_runs(line, |glyph| glyph.underline)
+        .into_iter()
+        .map(|run| (run, -UNDERLINE_DEPTH));
+
+    let strikethroughs = decorated_runs(line, |glyph| glyph.strikethrough)
+        .into_iter()
+        .map(|run| (run, STRIKETHROUGH_HEIGHT));
+
+    underlines
+        .chain(strikethroughs)
+        .map(|((start, end, size), offset)| Decoration {
+            start,
+            end,
+            offset: size * offset,
+            thickness: size.0 * DECORATION_THICKNESS,
+        })
+        .collect()
+}
+
+/// Returns the runs of consecutive glyphs of a line that are decorated, with their horizontal
+/// extents and the size of their largest glyph.
+fn decorated_runs<F: Fn(&Glyph) -> bool>(line: &[(Glyph, Pt)], decorated: F) -> Vec<(Pt, Pt, Pt)> {
+    let mut runs = vec![];
+    let mut run: Option<(Pt, Pt, Pt)> = None;
+
+    for (glyph, x) in line {
+        if !decorated(glyph) {
+            runs.extend(run.take());
+            continue;
+        }
+
+        let end = *x + glyph.font.char_width(glyph.glyph, glyph.scale);
+        run = match run {
+            Some((start, _, size)) if size > glyph.scale => Some((start, end, size)),
+            Some((start, _, _)) => Some((start, end, glyph.scale)),
+            None => Some((*x, end, glyph.scale)),
+        };
+    }
+
+    runs.extend(run);
+    runs
+}
+
+/// Adds link annotations to the pages of a pdf for the clickable areas of the links.
+///
+/// Links to labels become GoTo actions, other links become URI actions.
+fn add_link_annotations(
+    pdf: &mut lopdf::Document,
+    links: &[LinkArea],
+    anchors: &HashMap<String, Anchor>,
+) {
+    let pages = pdf.get_pages();
+
+    for link in links {
+        let page = match pages.get(&(link.page as u32)) {
+            Some(page) => *page,
+            None => continue,
+        };
+
+        let action = if link.target.starts_with('#') {
+            let anchor = match anchors.get(&link.target[1..]) {
+                Some(anchor) => anchor,
+                None => continue,
+            };
+
+            let destination = match pages.get(&(anchor.page as u32)) {
+                Some(destination) => *destination,
+                None => continue,
+            };
+
+            Dictionary::from_iter(vec![
+                ("S", Object::Name(b"GoTo".to_vec())),
+                (
+                    "D",
+                    Object::Array(vec![
+                        Object::Reference(destination),
+                        Object::Name(b"XYZ".to_vec()),
+                        Object::Null,
+                        Object::Real((anchor.y + DESTINATION_MARGIN).0),
+                        Object::Null,
+                    ]),
+                ),
+            ])
+        } else {
+            Dictionary::from_iter(vec![
+                ("S", Object::Name(b"URI".to_vec())),
+                (
+                    "URI",
+                    Object::String(link.target.clone().into_bytes(), StringFormat::Literal),
+                ),
+            ])
+        };
+
+        let ((left, bottom), (right, top)) = link.rectangle;
+
+        let annotation = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Annot".to_vec())),
+            ("Subtype", Object::Name(b"Link".to_vec())),
+            (
+                "Rect",
+                Object::Array(vec![
+                    Object::Real(left.0),
+                    Object::Real(bottom.0),
+                    Object::Real(right.0),
+                    Object::Real(top.0),
+                ]),
+            ),
+            (
+                "Border",
+                Object::Array(vec![
+                    Object::Integer(0),
+                    Object::Integer(0),
+                    Object::Integer(0),
+                ]),
+            ),
+            ("A", Object::Dictionary(action)),
+        ]);
+
+        let annotation = pdf.add_object(annotation);
+
+        if let Ok(Object::Dictionary(page)) = pdf.get_object_mut(page) {
+            match page.get_mut(b"Annots") {
+                Ok(Object::Array(annotations)) => annotations.push(Object::Reference(annotation)),
+                _ => page.set("Annots", Object::Array(vec![Object::Reference(annotation)])),
+            }
+        }
+    }
+}
+
+/// Adds the authors, the keywords and the language of a document to the info dictionary and the
+/// catalog of a pdf, and all its metadata to its XMP metadata.
+fn add_metadata(pdf: &mut lopdf::Document, metadata: &Metadata) {
+    let info = pdf.trailer.get(b"Info").and_then(Object::as_reference);
+    if let Ok(Object::Dictionary(info)) = info.and_then(|id| pdf.get_object_mut(id)) {
+        if !metadata.authors.is_empty() {
+            info.set("Author", text_string(&metadata.authors.join(", ")));
+        }
+
+        if !metadata.keywords.is_empty() {
+            info.set("Keywords", text_string(&metadata.keywords.join(", ")));
+        }
+    }
+
+    let catalog = pdf.trailer.get(b"Root").and_then(Object::as_reference);
+    let catalog = match catalog.and_then(|id| pdf.get_object_mut(id)) {
+        Ok(Object::Dictionary(catalog)) => catalog,
+        _ => return,
+    };
+
+    if let Some(language) = &metadata.language {
+        catalog.set("Lang", text_string(language));
+    }
+
+    let xmp = catalog.get(b"Metadata").and_then(Object::as_reference);
+    if let Ok(Object::Stream(xmp)) = xmp.and_then(|id| pdf.get_object_mut(id)) {
+        let content = String::from_utf8_lossy(&xmp.content).into_owned();
+
+        // The description is inserted on its own lines, before the closing tag of the RDF.
+        if let Some(index) = content.find("</rdf:RDF>") {
+            let index = content[..index].rfind('\n').map_or(index, |x| x + 1);
+            let mut content = content;
+            content.insert_str(index, &xmp_description(metadata));
+            xmp.set_content(content.into_bytes());
+        }
+    }
+}
+
+/// Adds the outline of a pdf, in which the bookmarks of the titles are nested according to their
+/// levels, and shows it when the pdf is opened.
+fn add_outline(pdf: &mut lopdf::Document, outline: &[OutlineEntry]) {
+    if outline.is_empty() {
+        return;
+    }
+
+    let pages = pdf.get_pages();
+    let parents = outline_parents(outline);
+    let children = |parent: Option<usize>| {
+        (0..outline.len())
+            .filter(|x| parents[*x] == parent)
+            .collect::<Vec<_>>()
+    };
+
+    let root = pdf.new_object_id();
+    let ids = outline
+        .iter()
+        .map(|_| pdf.new_object_id())
+        .collect::<Vec<_>>();
+
+    for (index, entry) in outline.iter().enumerate() {
+        let parent = parents[index];
+        let mut bookmark = Dictionary::from_iter(vec![
+            ("Title", text_string(&entry.title)),
+            ("Parent", Object::Reference(parent.map_or(root, |x| ids[x]))),
+        ]);
+
+        let siblings = children(parent);
+        let position = siblings.iter().position(|x| *x == index).unwrap_or(0);
+
+        if position > 0 {
+            bookmark.set("Prev", Object::Reference(ids[siblings[position - 1]]));
+        }
+
+        if let Some(next) = siblings.get(position + 1) {
+            bookmark.set("Next", Object::Reference(ids[*next]));
+        }
+
+        // The descendants of a bookmark are the entries that follow it with a higher level.
+        let own = children(Some(index));
+        if let (Some(first), Some(last)) = (own.first(), own.last()) {
+            let descendants = outline[index + 1..]
+                .iter()
+                .take_while(|x| x.level > entry.level)
+                .count();
+
+            bookmark.set("First", Object::Reference(ids[*first]));
+            bookmark.set("Last", Object::Reference(ids[*last]));
+            bookmark.set("Count", Object::Integer(descendants as i64));
+        }
+
+        if let Some(page) = pages.get(&(entry.page as u32)) {
+            bookmark.set(
+                "Dest",
+                Object::Array(vec![
+                    Object::Reference(*page),
+                    Object::Name(b"XYZ".to_vec()),
+                    Object::Null,
+                    Object::Real((entry.y + DESTINATION_MARGIN).0),
+                    Object::Null,
+                ]),
+            );
+        }
+
+        pdf.objects.insert(ids[index], Object::Dictionary(bookmark));
+    }
+
+    let roots = children(None);
+    let mut dictionary = Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"Outlines".to_vec())),
+        ("Count", Object::Integer(outline.len() as i64)),
+    ]);
+
+    if let (Some(first), Some(last)) = (roots.first(), roots.last()) {
+        dictionary.set("First", Object::Reference(ids[*first]));
+        dictionary.set("Last", Object::Reference(ids[*last]));
+    }
+
+    pdf.objects.insert(root, Object::Dictionary(dictionary));
+
+    let catalog = pdf.trailer.get(b"Root").and_then(Object::as_reference);
+    if let Ok(Object::Dictionary(catalog)) = catalog.and_then(|id| pdf.get_object_mut(id)) {
+        catalog.set("Outlines", Object::Reference(root));
+        catalog.set("PageMode", Object::Name(b"UseOutlines".to_vec()));
+    }
+}
+
+/// Returns the index of the parent of each entry of an outline, which is the closest previous
+/// entry of a lower level, if any.
+fn outline_parents(outline: &[OutlineEntry]) -> Vec<Option<usize>> {
+    let mut ancestors: Vec<usize> = vec![];
+
+    outline
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            while let Some(last) = ancestors.last() {
+                if outline[*last].level < entry.level {
+                    break;
+                }
+                ancestors.pop();
+            }
+
+            let parent = ancestors.last().cloned();
+            ancestors.push(index);
+            parent
+        })
+        .collect()
+}
+
+/// Returns a text string of a pdf, encoded in UTF-16 if it is not ASCII.
+fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::String(text.as_bytes().to_vec(), StringFormat::Literal);
+    }
+
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// Returns the description of the metadata of a document in XMP, with the Dublin Core and the
+/// Adobe PDF schemas.
+///
+/// The title is not described, since printpdf already writes it.
+fn xmp_description(metadata: &Metadata) -> String {
+    let mut properties = String::new();
+
+    let mut push = |name: &str, container: &str, values: &[String]| {
+        if values.is_empty() {
+            return;
+        }
+
+        properties.push_str(&format!("         <{}>\n", name));
+        properties.push_str(&format!("            <rdf:{}>\n", container));
+        for value in values {
+            properties.push_str(&format!(
+                "               <rdf:li>{}</rdf:li>\n",
+                escape_xml(value)
+            ));
+        }
+        properties.push_str(&format!("            </rdf:{}>\n", container));
+        properties.push_str(&format!("         </{}>\n", name));
+    };
+
+    push("dc:creator", "Seq", &metadata.authors);
+    push("dc:date", "Seq", metadata.date.as_slice());
+    push("dc:language", "Bag", metadata.language.as_slice());
+    push("dc:subject", "Bag", &metadata.keywords);
+
+    if !metadata.keywords.is_empty() {
+        properties.push_str(&format!(
+            "         <pdf:Keywords>{}</pdf:Keywords>\n",
+            escape_xml(&metadata.keywords.join(", "))
+        ));
+    }
+
+    if properties.is_empty() {
+        return properties;
+    }
+
+    format!(
+        "      <rdf:Description rdf:about=\"\"\n            \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n            \
+         xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">\n{}      </rdf:Description>\n",
+        properties
+    )
+}
+
+/// Escapes the characters of a text that have a meaning in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use printpdf::Pt;
+
+    use crate::bibliography::{Bibliography, CitationStyle};
+    use crate::config::Config;
+    use hyphenation::load::Load;
+    use hyphenation::{Language, Standard};
+    use nom::types::CompleteStr;
+
+    use crate::document::{
+        column_widths, decorations, expand_tabs, outline_parents, wrap_code_line, xmp_description,
+        Anchor, ContentsEntry, OutlineEntry,
+    };
+    use crate::parser::ast::{Alignment, Ast};
+    use crate::parser::metadata::Metadata;
+    use crate::parser::{combinators, parse, parse_with_bibliography, Position, Span};
+    use crate::typography::justification::{Justifier, LatexJustifier};
+    use crate::typography::paragraphs::itemize_ast;
+
+    #[test]
+    fn test_resolve() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-labels.dex").unwrap().ast;
+
+        document.resolve(&ast, &font_config, Pt(10.0));
+
+        let anchor = |label| {
+            let Anchor { number, page, .. } = document.anchors()[label].clone();
+            (number, page)
+        };
+
+        assert_eq!(anchor("intro"), ("1".into(), 1));
+        assert_eq!(anchor("euler"), ("1".into(), 1));
+
+        // The title is above the equation.
+        assert!(document.anchors()["intro"].y > document.anchors()["euler"].y);
+
+        // The equation is defined after the paragraph that references it.
+        let paragraph = match &ast {
+            Ast::Group(children) => &children[1],
+            _ => unreachable!(),
+        };
+
+        let expected = Ast::Paragraph(vec![
+            Ast::Text("See ".into()).into(),
+            Ast::Text("1".into()).into(),
+            Ast::Text(" on page ".into()).into(),
+            Ast::Text("1".into()).into(),
+            Ast::Text(", and ".into()).into(),
+            Ast::Text("1".into()).into(),
+            Ast::Text(".".into()).into(),
+        ]);
+
+        assert_eq!(
+            document.replace_references(paragraph).without_spans(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-footnote.dex")
+            .unwrap()
+            .ast;
+
+        document.render(&ast, &font_config, Pt(10.0));
+        assert_eq!(document.footnotes.len(), 1);
+        assert!(document.overflow.is_empty());
+        assert!(document.bottom() > document.window.y);
+
+        // A long footnote close to the bottom of the page continues on the next page.
+        let note = Ast::Text(vec!["Lorem ipsum dolor sit amet."; 20].join(" "));
+        let paragraph = Ast::Paragraph(vec![
+            Ast::Text("Text".into()).into(),
+            Ast::Footnote(Box::new(note.into())).into(),
+        ]);
+
+        // The paragraph fills the page, which moves the rest of the footnote to the next one.
+        let page = document.page_number;
+        document.cursor.1 = document.bottom() + Pt(40.0);
+        document.render(&paragraph, &font_config, Pt(10.0));
+        assert_eq!(document.page_number, page + 1);
+        assert!(document.overflow.is_empty());
+        assert!(!document.footnotes.is_empty());
+    }
+
+    #[test]
+    fn test_links() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-link.dex").unwrap().ast;
+
+        document.resolve(&ast, &font_config, Pt(10.0));
+        assert!(document.links().is_empty());
+
+        document.render(&ast, &font_config, Pt(10.0));
+        assert_eq!(document.links().len(), 2);
+        assert_eq!(document.links()[0].target, "https://rust-spandex.github.io");
+        assert_eq!(document.links()[1].target, "https://example.com");
+
+        // Both links are on the same line, one after the other.
+        let ((_, bottom), (end, top)) = document.links()[0].rectangle;
+        let ((start, _), _) = document.links()[1].rectangle;
+        assert_eq!(document.links()[1].rectangle.0 .1, bottom);
+        assert!(bottom < top);
+        assert!(end < start);
+
+        // A link broken across lines has one area per line.
+        let content = Ast::Text(vec!["Lorem ipsum dolor sit amet."; 20].join(" "));
+        let paragraph = Ast::Paragraph(vec![Ast::Link {
+            url: "https://example.com".into(),
+            content: Box::new(content.into()),
+            position: Position {
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
+        }
+        .into()]);
+
+        document.links.clear();
+        document.render(&paragraph, &font_config, Pt(10.0));
+        let lines = document.links().len();
+        assert!(lines > 1);
+        assert!(document.links()[0].rectangle.0 .1 > document.links()[1].rectangle.0 .1);
+
+        // And one area per page when it is broken across pages.
+        document.links.clear();
+        document.cursor.1 = document.bottom() + Pt(20.0);
+        let page = document.page_number;
+        document.render(&paragraph, &font_config, Pt(10.0));
+        assert_eq!(document.links().len(), lines);
+        assert_eq!(document.links()[0].page, page);
+        assert_eq!(document.links()[lines - 1].page, page + 1);
+    }
+
+    #[test]
+    fn test_figures() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-figure.dex").unwrap().ast;
+
+        let figure = match &ast {
+            Ast::Group(children) => children[0].clone(),
+            _ => unreachable!(),
+        };
+
+        // The image is twice as wide as it is tall, and takes half of the width of the text.
+        let top = document.cursor.1;
+        document.render(&figure, &font_config, Pt(10.0));
+        let height = document.window.width / 4.0;
+        assert!(document.cursor.1 < top - height);
+        assert_eq!(document.anchors()["rectangle"].number, "1");
+
+        // An image that doesn't fit on the page goes on the next one.
+        let page = document.page_number;
+        document.cursor.1 = document.bottom() + height / 2.0;
+        document.render(&figure, &font_config, Pt(10.0));
+        assert_eq!(document.page_number, page + 1);
+        assert_eq!(document.anchors()["rectangle"].number, "2");
+        assert_eq!(document.anchors()["rectangle"].page, page + 1);
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+
+        // A deeply nested list stops being indented before its items get too narrow.
+        let mut list = Ast::Group(vec![]);
+        for _ in 0..50 {
+            let item = Ast::ListItem {
+                content: Box::new(Ast::Text("Lorem ipsum dolor sit amet.".into()).into()),
+                children: vec![list.into()],
+            };
+            list = Ast::List {
+                ordered: false,
+                start: 1,
+                items: vec![item.into()],
+            };
+        }
+
+        let window = document.window;
+        let top = document.cursor.1;
+        document.render(&list, &font_config, Pt(10.0));
+        assert!(document.cursor.1 < top || document.page_number > 1);
+        assert_eq!(document.window.x, window.x);
+        assert_eq!(document.window.width, window.width);
+    }
+
+    #[test]
+    fn test_tables() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-table.dex").unwrap().ast;
+
+        // The header is two lines below the caption, the rows follow, and an empty line ends the table.
+        let top = document.cursor.1;
+        document.render(&ast, &font_config, Pt(10.0));
+        assert_eq!(document.anchors()["values"].number, "1");
+        assert_eq!(document.cursor.1, top - Pt(10.0) * 6.0);
+
+        // The long cell is broken into several lines, that the next row goes below.
+        let long = Ast::Text(["Lorem ipsum dolor sit amet."; 10].join(" "));
+        let table = Ast::Table {
+            alignments: vec![Alignment::Left, Alignment::Right],
+            header: vec![
+                Ast::Text("Text".into()).into(),
+                Ast::Text("Number".into()).into(),
+            ],
+            rows: vec![
+                vec![long.into(), Ast::Text("1".into()).into()],
+                vec![
+                    Ast::Text("Short".into()).into(),
+                    Ast::Text("2".into()).into(),
+                ],
+            ],
+            caption: None,
+        };
+
+        let top = document.cursor.1;
+        document.render(&table, &font_config, Pt(10.0));
+        assert!(document.cursor.1 < top - Pt(10.0) * 7.0);
+
+        // An empty column and columns narrower than their words don't prevent the rendering.
+        let ast = combinators::parse(Span::new(CompleteStr("| a | |\n|---|---|\n| b | |\n")));
+        document.render(&ast, &font_config, Pt(10.0));
+
+        let cell = "internationalization considerations";
+        let row = [cell; 6].join(" | ");
+        let rule = ["---"; 6].join("|");
+        let table = format!("| {} |\n|{}|\n| {} |\n", row, rule, row);
+        let ast = combinators::parse(Span::new(CompleteStr(&table)));
+        let top = document.cursor.1;
+        let page = document.page_number;
+        document.render(&ast, &font_config, Pt(10.0));
+        assert!(document.cursor.1 < top - Pt(10.0) * 4.0 || document.page_number > page);
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-code.dex").unwrap().ast;
+
+        let block = match &ast {
+            Ast::Group(children) => children[1].clone(),
+            _ => unreachable!(),
+        };
+
+        // Each line of code takes a line, even the empty one, and an empty line follows.
+        let top = document.cursor.1;
+        document.render(&block, &font_config, Pt(10.0));
+        assert_eq!(document.cursor.1, top - Pt(10.0) * 5.0);
+
+        assert_eq!(expand_tabs("\tx\tyz"), "    x   yz");
+        assert_eq!(expand_tabs("abcd\te"), "abcd    e");
+
+        assert_eq!(wrap_code_line("", 4), vec![""]);
+        assert_eq!(wrap_code_line("abcdéfghi", 4), vec!["abcd", "éfgh", "i"]);
+    }
+
+    #[test]
+    fn test_styles() {
+        let (_, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let en = Standard::from_embedded(Language::EnglishUS).unwrap();
+
+        let ast = parse("assets/tests/successes/test-styles.dex").unwrap().ast;
+        let paragraph = itemize_ast(&ast, &font_config, Pt(10.0), &en, Pt(0.0));
+        let lines = LatexJustifier::justify(&paragraph, Pt(400.0));
+        assert_eq!(lines.len(), 1);
+
+        let line = &lines[0];
+        let glyph = |c| &line.iter().find(|x| x.0.glyph == c).unwrap().0;
+
+        // Small capitals are smaller capitals, but the capitals keep their size.
+        assert_eq!(glyph('N').scale, Pt(10.0));
+        assert_eq!(glyph('A').scale, Pt(10.0) * 0.8);
+
+        // Superscripts and subscripts are smaller, and shifted from the baseline.
+        assert!(glyph('1').shift > Pt(0.0));
+        assert!(line.iter().any(|x| x.0.glyph == '2' && x.0.shift < Pt(0.0)));
+        assert_eq!(glyph('1').scale, Pt(10.0) * 0.7);
+
+        // One underline below "not" and one line through "quite".
+        let decorations = decorations(line);
+        assert_eq!(decorations.len(), 2);
+        assert!(decorations[0].offset < Pt(0.0));
+        assert!(decorations[1].offset > Pt(0.0));
+
+        let not = line.iter().position(|x| x.0.glyph == 'n').unwrap();
+        assert_eq!(decorations[0].start, line[not].1);
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let minimum = [Pt(5.0); 3];
+        let widths = column_widths(&[Pt(10.0), Pt(20.0)], &minimum, Pt(100.0));
+        assert_eq!(widths, vec![Pt(10.0), Pt(20.0)]);
+
+        // The narrow column keeps its width, the others share the rest.
+        let widths = column_widths(&[Pt(10.0), Pt(200.0), Pt(100.0)], &minimum, Pt(110.0));
+        assert_eq!(widths[0], Pt(10.0));
+        assert!((widths[1].0 - 200.0 / 3.0).abs() < 1e-9);
+        assert!((widths[2].0 - 100.0 / 3.0).abs() < 1e-9);
+
+        // But not below their minimal widths.
+        let minimum = [Pt(5.0), Pt(30.0), Pt(50.0)];
+        let widths = column_widths(&[Pt(10.0), Pt(200.0), Pt(100.0)], &minimum, Pt(110.0));
+        assert_eq!(widths, vec![Pt(10.0), Pt(50.0), Pt(50.0)]);
+
+        // Even if the table gets wider than the available width.
+        let widths = column_widths(&[Pt(10.0), Pt(200.0), Pt(100.0)], &minimum, Pt(50.0));
+        assert_eq!(widths, vec![Pt(5.0), Pt(30.0), Pt(50.0)]);
+    }
+
+    #[test]
+    fn test_xmp_description() {
+        assert_eq!(xmp_description(&Metadata::default()), "");
+
+        let metadata = Metadata {
+            title: Some("Ignored".into()),
+            authors: vec!["Ada Lovelace".into()],
+            date: None,
+            language: Some("en".into()),
+            keywords: vec!["engine".into(), "notes & letters".into()],
+        };
+
+        let description = xmp_description(&metadata);
+
+        assert!(description.starts_with("      <rdf:Description rdf:about=\"\""));
+        assert!(description.contains("<rdf:Seq>\n               <rdf:li>Ada Lovelace</rdf:li>"));
+        assert!(description.contains("<rdf:li>notes &amp; letters</rdf:li>"));
+        assert!(description.contains("<pdf:Keywords>engine, notes &amp; letters</pdf:Keywords>"));
+        assert!(description.contains("<dc:language>"));
+        assert!(!description.contains("<dc:date>"));
+        assert!(!description.contains("Ignored"));
+        assert!(description.ends_with("      </rdf:Description>\n"));
+    }
+
+    #[test]
+    fn test_table_of_contents() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-contents.dex")
+            .unwrap()
+            .ast;
+
+        document.resolve(&ast, &font_config, Pt(10.0));
+
+        // The labels and the footnotes of the titles are not repeated.
+        let entry = |level, number: &str, text: &str| ContentsEntry {
+            level,
+            number: number.into(),
+            content: Ast::Group(vec![Ast::Text(text.into()).into()]),
+        };
+        let expected = [
+            entry(0, "1", "Introduction "),
+            entry(1, "1.1", "Details"),
+            entry(0, "2", "Conclusion"),
+        ];
+        let contents = document
+            .contents()
+            .iter()
+            .map(|x| ContentsEntry {
+                content: x.content.without_spans(),
+                ..x.clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(contents, expected);
+        assert_eq!(document.anchors()["title 1.1"].page, 1);
+
+        // Each entry links to its title, which is below the table of contents.
+        document.render(&ast, &font_config, Pt(10.0));
+        let targets = document
+            .links()
+            .iter()
+            .map(|x| x.target.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(targets, vec!["#title 1", "#title 1.1", "#title 2"]);
+
+        let ((_, entry), _) = document.links()[2].rectangle;
+        assert!(document.anchors()["title 1"].y < entry);
+        assert_eq!(document.anchors()["intro"].number, "1");
+    }
+
+    #[test]
+    #[should_panic(expected = "the document must be resolved")]
+    fn test_unresolved_table_of_contents() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-contents.dex")
+            .unwrap()
+            .ast;
+
+        document.render(&ast, &font_config, Pt(10.0));
+    }
+
+    #[test]
+    fn test_outline() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let ast = parse("assets/tests/successes/test-contents.dex")
+            .unwrap()
+            .ast;
+
+        document.resolve(&ast, &font_config, Pt(10.0));
+        assert!(document.outline().is_empty());
+
+        document.render(&ast, &font_config, Pt(10.0));
+        let titles = document
+            .outline()
+            .iter()
+            .map(|x| (x.level, x.title.as_str(), x.page))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![
+                (0, "1 Introduction", 1),
+                (1, "1.1 Details", 1),
+                (0, "2 Conclusion", 1)
+            ]
+        );
+        assert!(document.outline()[0].y > document.outline()[1].y);
+    }
+
+    #[test]
+    fn test_outline_parents() {
+        let outline = [0, 1, 2, 1, 0, 2, 1]
+            .iter()
+            .map(|level| OutlineEntry {
+                level: *level,
+                title: String::new(),
+                page: 1,
+                y: Pt(0.0),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            outline_parents(&outline),
+            vec![None, Some(0), Some(1), Some(0), None, Some(4), Some(4)]
+        );
+    }
+
+    #[test]
+    fn test_citations() {
+        let (mut document, font_manager) = Config::with_title("Test").init().unwrap();
+        let font_config = font_manager.default_config();
+        let path = "assets/tests/successes/test-citations.bib";
+        let bibliography = Bibliography::load(path).unwrap();
+        let path = "assets/tests/successes/test-citations.dex";
+        let ast = parse_with_bibliography(path, &bibliography).unwrap().ast;
+
+        let keys = ["knuth84".into(), "lamport94".into()];
+
+        // Entries are numbered in the order of their first citation.
+        document.set_bibliography(bibliography.clone(), CitationStyle::Numeric);
+        document.resolve(&ast, &font_config, Pt(10.0));
+        assert_eq!(document.citation(&keys), "[2, 1]");
+        assert_eq!(document.citation(&["knuth85".into()]), "[?]");
+
+        let top = document.cursor.1;
+        document.render(&ast, &font_config, Pt(10.0));
+        assert!(document.cursor.1 < top);
+
+        document.set_bibliography(bibliography, CitationStyle::AuthorYear);
+        assert_eq!(document.citation(&keys), "(Knuth 1984; Lamport 1994)");
     }
 }
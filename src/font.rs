@@ -81,6 +81,50 @@ impl Font {
         Pt(width as f64 / (vert_scale as f64 / scale))
     }
 
+    /// Computes the height and the depth of a char of the font at a specified size.
+    ///
+    /// The height is the distance between the baseline and the top of the char, and the depth is
+    /// the distance between the baseline and the bottom of the char. The depth is negative if the
+    /// char is entirely above the baseline.
+    pub fn char_bounds(&self, c: char, scale: Pt) -> (Pt, Pt) {
+        let scale = scale.0;
+
+        // vertical scale for the space character
+        let vert_scale = {
+            if self
+                .freetype
+                .load_char(0x0020, face::LoadFlag::NO_SCALE)
+                .is_ok()
+            {
+                self.freetype.glyph().metrics().vertAdvance
+            } else {
+                1000
+            }
+        };
+
+        let is_ok = self
+            .freetype
+            .load_char(c as usize, face::LoadFlag::NO_SCALE)
+            .is_ok();
+
+        let (height, depth) = if is_ok {
+            let metrics = self.freetype.glyph().metrics();
+            (metrics.horiBearingY, metrics.height - metrics.horiBearingY)
+        } else {
+            (0, 0)
+        };
+
+        (
+            Pt(height as f64 / (vert_scale as f64 / scale)),
+            Pt(depth as f64 / (vert_scale as f64 / scale)),
+        )
+    }
+
+    /// Returns true if the font contains a glyph for the char.
+    pub fn has_char(&self, c: char) -> bool {
+        self.freetype.get_char_index(c as usize) != 0
+    }
+
     /// Computes the text width of the font at a specified size.
     pub fn text_width(&self, text: &str, scale: Pt) -> Pt {
         let scale = scale.0;
@@ -134,6 +178,9 @@ pub struct FontConfig<'a> {
 
     /// The bold italic font.
     pub bold_italic: &'a Font,
+
+    /// The monospace font, used for code.
+    pub monospace: &'a Font,
 }
 
 impl<'a> FontConfig<'a> {
@@ -225,6 +272,7 @@ impl FontManager {
         bold: &str,
         italic: &str,
         bold_italic: &str,
+        monospace: &str,
     ) -> Result<FontConfig<'a>> {
         Ok(FontConfig {
             regular: self
@@ -243,25 +291,31 @@ impl FontManager {
                 .fonts
                 .get(bold_italic)
                 .ok_or_else(|| Error::FontNotFound(PathBuf::from(bold_italic)))?,
+            monospace: self
+                .fonts
+                .get(monospace)
+                .ok_or_else(|| Error::FontNotFound(PathBuf::from(monospace)))?,
         })
     }
 
     /// Returns the default configuration for computer modern fonts.
-    pub fn default_config(&self) -> FontConfig {
+    pub fn default_config(&self) -> FontConfig<'_> {
         let regular = "CMU Serif Roman";
         let bold = "CMU Serif Bold";
         let italic = "CMU Serif Italic";
         let bold_italic = "CMU Serif BoldItalic";
+        let monospace = "CMU Typewriter Text Regular";
 
         // This should never fail.
-        match self.config(regular, bold, italic, bold_italic) {
+        match self.config(regular, bold, italic, bold_italic, monospace) {
             Ok(c) => c,
             Err(_) => unreachable!("Default font not found, this should never happen"),
         }
     }
 }
 
-/// A style for a font. It can be bold, italic, both or none.
+/// A style for a font. It can be bold, italic, both or none, in small capitals, and underlined or
+/// struck through.
 #[derive(Copy, Clone, Debug)]
 pub struct FontStyle {
     /// Whether the bold is activated or not.
@@ -269,6 +323,15 @@ pub struct FontStyle {
 
     /// Whether the italic is activated or not.
     pub italic: bool,
+
+    /// Whether the lowercase letters are written as smaller capitals or not.
+    pub small_caps: bool,
+
+    /// Whether the text is underlined or not.
+    pub underline: bool,
+
+    /// Whether the text is struck through or not.
+    pub strikethrough: bool,
 }
 
 impl FontStyle {
@@ -277,22 +340,46 @@ impl FontStyle {
         FontStyle {
             bold: false,
             italic: false,
+            small_caps: false,
+            underline: false,
+            strikethrough: false,
         }
     }
 
     /// Adds the bold style to the font.
     pub fn bold(self) -> FontStyle {
-        FontStyle {
-            bold: true,
-            italic: self.italic,
-        }
+        FontStyle { bold: true, ..self }
     }
 
     /// Adds the italic style to the font.
     pub fn italic(self) -> FontStyle {
         FontStyle {
-            bold: self.bold,
             italic: true,
+            ..self
+        }
+    }
+
+    /// Adds the small capitals to the font.
+    pub fn small_caps(self) -> FontStyle {
+        FontStyle {
+            small_caps: true,
+            ..self
+        }
+    }
+
+    /// Underlines the text.
+    pub fn underline(self) -> FontStyle {
+        FontStyle {
+            underline: true,
+            ..self
+        }
+    }
+
+    /// Strikes the text through.
+    pub fn strikethrough(self) -> FontStyle {
+        FontStyle {
+            strikethrough: true,
+            ..self
         }
     }
 
@@ -300,15 +387,15 @@ impl FontStyle {
     pub fn unbold(self) -> FontStyle {
         FontStyle {
             bold: false,
-            italic: self.italic,
+            ..self
         }
     }
 
     /// Removes the italic style from the font.
     pub fn unitalic(self) -> FontStyle {
         FontStyle {
-            bold: self.bold,
             italic: false,
+            ..self
         }
     }
 }
@@ -4,23 +4,31 @@
 #![warn(clippy::cargo)]
 #![allow(clippy::multiple_crate_versions)]
 
+pub mod bibliography;
 pub mod config;
 pub mod document;
 pub mod font;
 pub mod ligature;
+pub mod math;
 pub mod parser;
+pub mod smart;
 pub mod typography;
 
-use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
 use std::{error, fmt, io, result};
 
 use printpdf::Pt;
 
+use crate::bibliography::Bibliography;
 use crate::config::Config;
+use crate::parser::ast::Ast;
 use crate::parser::error::Errors;
-use crate::parser::parse;
+use crate::parser::format::{format, FormatError};
+use crate::parser::markdown::is_markdown;
+use crate::parser::visit::Fold;
+use crate::parser::{check_ast, parse_with_lints, Parsed, Position};
 
 macro_rules! impl_from_error {
     ($type: ty, $variant: path, $from: ty) => {
@@ -47,6 +55,9 @@ pub enum Error {
     /// Error while dealing with printpdf.
     PrintpdfError(printpdf::errors::Error),
 
+    /// Error while adding the annotations, outline and metadata to the pdf with lopdf.
+    LopdfError(lopdf::Error),
+
     /// The specified font was not found.
     FontNotFound(PathBuf),
 
@@ -61,13 +72,28 @@ pub enum Error {
 
     /// Some error occured while parsing a dex file.
     DexError(Errors),
+
+    /// A lint of the config does not exist.
+    UnknownLint(String),
+
+    /// An error occured while reading or writing an ast as JSON.
+    JsonError(serde_json::Error),
+
+    /// The input is not a dex, Markdown or JSON file, so it has no ast.
+    NoAst(PathBuf),
+
+    /// A dex file cannot be formatted without changing its content, from the bloc at the given
+    /// position.
+    Unformattable(PathBuf, Position),
 }
 
 impl_from_error!(Error, Error::FreetypeError, freetype::Error);
 impl_from_error!(Error, Error::PrintpdfError, printpdf::errors::Error);
+impl_from_error!(Error, Error::LopdfError, lopdf::Error);
 impl_from_error!(Error, Error::IoError, io::Error);
 impl_from_error!(Error, Error::HyphenationLoadError, hyphenation::load::Error);
 impl_from_error!(Error, Error::DexError, Errors);
+impl_from_error!(Error, Error::JsonError, serde_json::Error);
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
@@ -76,6 +102,7 @@ impl fmt::Display for Error {
             Error::NoConfigFile => write!(fmt, "no spandex.toml was found"),
             Error::FreetypeError(e) => write!(fmt, "freetype error: {}", e),
             Error::PrintpdfError(e) => write!(fmt, "printpdf error: {}", e),
+            Error::LopdfError(e) => write!(fmt, "lopdf error: {}", e),
             Error::FontNotFound(path) => write!(fmt, "couldn't find font \"{}\"", path.display()),
             Error::FontWithoutName(path) => {
                 write!(fmt, "font has no name or style \"{}\"", path.display())
@@ -83,6 +110,20 @@ impl fmt::Display for Error {
             Error::HyphenationLoadError(e) => write!(fmt, "Problem with hyphenation: {}", e),
             Error::IoError(e) => write!(fmt, "an io error occured: {}", e),
             Error::DexError(e) => write!(fmt, "{}", e),
+            Error::UnknownLint(name) => write!(fmt, "unknown lint \"{}\" in spandex.toml", name),
+            Error::JsonError(e) => write!(fmt, "json error: {}", e),
+            Error::NoAst(path) => write!(
+                fmt,
+                "\"{}\" is not a dex, Markdown or JSON file",
+                path.display()
+            ),
+            Error::Unformattable(path, position) => write!(
+                fmt,
+                "cannot format \"{}\" without changing its content, from line {}, column {}",
+                path.display(),
+                position.line,
+                position.column
+            ),
         }
     }
 }
@@ -93,22 +134,125 @@ impl error::Error for Error {}
 pub type Result<T> = result::Result<T, Error>;
 
 /// Compiles a spandex project.
+///
+/// Dex and Markdown files are parsed, and the metadata of their front matter override the ones of
+/// the config. JSON files contain an ast, and other files are written as plain text.
 pub fn build(config: &Config) -> Result<()> {
-    let (mut document, font_manager) = config.init()?;
-    let font_config = font_manager.default_config();
-
-    let mut content = String::new();
-    let mut file = File::open(&config.input)?;
-    file.read_to_string(&mut content)?;
-
-    if config.input.ends_with(".dex") {
-        let parsed = parse(&config.input)?;
-        println!("{}", parsed.warnings);
-        println!("{:?}", parsed.ast);
-        document.render(&parsed.ast, &font_config, Pt(10.0));
+    build_with(config, &mut [])
+}
+
+/// Returns true if the input of a project is an ast serialized as JSON.
+fn is_json(input: &str) -> bool {
+    input.ends_with(".json")
+}
+
+/// Parses the input of a project, which is a dex, Markdown or JSON file.
+///
+/// A JSON file contains an ast, e.g. one written by `spandex ast`, which has no metadata. It is
+/// checked like the ast of a dex file, except for the lints that check the source, and the paths
+/// of its images are relative to the current directory.
+fn parse_input(config: &Config, bibliography: &Bibliography) -> Result<Parsed> {
+    if is_json(&config.input) {
+        let file = File::open(&config.input)?;
+        let ast: Ast = serde_json::from_reader(BufReader::new(file))?;
+        check_ast(&config.input, ast, bibliography, &config.lints)
+    } else if config.input.ends_with(".dex") || is_markdown(&config.input) {
+        parse_with_lints(&config.input, bibliography, &config.lints)
+    } else {
+        Err(Error::NoAst(PathBuf::from(&config.input)))
+    }
+}
+
+/// Returns the ast of the input of a project serialized as JSON, so that it can be read by other
+/// tools, or modified and built again.
+pub fn ast_json(config: &Config) -> Result<String> {
+    let bibliography = match &config.bibliography {
+        Some(path) => Bibliography::load(path)?,
+        None => Bibliography::default(),
+    };
+
+    let parsed = parse_input(config, &bibliography)?;
+    eprint!("{}", parsed.warnings);
+    Ok(serde_json::to_string_pretty(&parsed.ast)?)
+}
+
+/// Formats a dex file in place, whose paragraphs are wrapped at a column, and returns true if it
+/// was already formatted.
+///
+/// In check mode, the file is left as is. A file with errors is not formatted, and neither is a
+/// file whose formatting would change its content, which would be a bug of the formatter.
+pub fn format_file<P: AsRef<Path>>(path: P, width: usize, check: bool) -> Result<bool> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)?;
+
+    let formatted = match format(&content, width) {
+        Ok(formatted) => formatted,
+        Err(FormatError::Errors(errors)) => {
+            return Err(Error::DexError(Errors {
+                path: PathBuf::from(path),
+                content,
+                errors,
+                chain: vec![],
+                children: vec![],
+            }))
+        }
+        Err(FormatError::Unformattable(position)) => {
+            return Err(Error::Unformattable(PathBuf::from(path), position))
+        }
+    };
+
+    if formatted == content {
+        return Ok(true);
+    }
+
+    if !check {
+        fs::write(path, formatted)?;
+    }
+
+    Ok(false)
+}
+
+/// Compiles a spandex project, whose ast goes through some passes, in order, before being
+/// rendered.
+///
+/// The passes only apply to dex, Markdown and JSON files.
+pub fn build_with(config: &Config, passes: &mut [&mut dyn Fold]) -> Result<()> {
+    if is_json(&config.input) || config.input.ends_with(".dex") || is_markdown(&config.input) {
+        let bibliography = match &config.bibliography {
+            Some(path) => Bibliography::load(path)?,
+            None => Bibliography::default(),
+        };
+
+        let parsed = parse_input(config, &bibliography)?;
+        let ast = passes
+            .iter_mut()
+            .fold(parsed.ast, |ast, pass| pass.fold_ast(ast));
+        eprint!("{}", parsed.warnings);
+
+        let mut config = config.clone();
+        if let Some(title) = &parsed.metadata.title {
+            config.title = title.clone();
+        }
+
+        let (mut document, font_manager) = config.init()?;
+        let font_config = font_manager.default_config();
+
+        document.set_metadata(parsed.metadata);
+        document.set_bibliography(bibliography, config.citation_style);
+        document.resolve(&ast, &font_config, Pt(10.0));
+        document.render(&ast, &font_config, Pt(10.0));
+        document.save("output.pdf")?;
     } else {
+        let (mut document, font_manager) = config.init()?;
+        let font_config = font_manager.default_config();
+
+        let mut content = String::new();
+        let mut file = File::open(&config.input)?;
+        file.read_to_string(&mut content)?;
+
         document.write_content(&content, &font_config, Pt(10.0));
+        document.save("output.pdf")?;
     }
-    document.save("output.pdf");
+
     Ok(())
 }
@@ -107,3 +107,30 @@ pub fn ligature(input: &str) -> String {
 
     output
 }
+
+/// Replaces the ligatures of a string by the characters they ligate, so that `ligature` gives the
+/// string back.
+pub fn unligature(input: &str) -> String {
+    let mut output = String::new();
+
+    for c in input.chars() {
+        match c {
+            'ﬃ' => output.push_str("ffi"),
+            'ﬄ' => output.push_str("ffl"),
+            'ﬀ' => output.push_str("ff"),
+            'ﬁ' => output.push_str("fi"),
+            'ﬂ' => output.push_str("fl"),
+            'Ĳ' => output.push_str("IJ"),
+            'ĳ' => output.push_str("ij"),
+            'Ǉ' => output.push_str("LJ"),
+            'ǈ' => output.push_str("Lj"),
+            'ǉ' => output.push_str("lj"),
+            'Ǌ' => output.push_str("NJ"),
+            'ǋ' => output.push_str("Nj"),
+            'ǌ' => output.push_str("nj"),
+            c => output.push(c),
+        }
+    }
+
+    output
+}
@@ -1,12 +1,14 @@
 use std::env::current_dir;
 use std::fs::{create_dir_all, File};
 use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::process::exit;
 
-use clap::{crate_authors, crate_description, crate_version, App, AppSettings, Arg, SubCommand};
+use clap::{crate_description, crate_version, value_t, App, AppSettings, Arg, SubCommand};
 
 use spandex::config::Config;
-use spandex::{build, Error};
+use spandex::parser::format::DEFAULT_WIDTH;
+use spandex::{ast_json, build, format_file, Error};
 
 macro_rules! unwrap {
     ($e: expr, $error: expr) => {
@@ -28,7 +30,7 @@ fn run() -> Result<(), Error> {
     let mut app = App::new("SpanDeX")
         .bin_name("spandex")
         .version(crate_version!())
-        .author(crate_authors!("\n"))
+        .author(env!("CARGO_PKG_AUTHORS"))
         .about(crate_description!())
         .setting(AppSettings::ColoredHelp)
         .subcommand(
@@ -36,7 +38,37 @@ fn run() -> Result<(), Error> {
                 .about("Creates a new default SpanDeX project")
                 .arg(Arg::with_name("TITLE").required(false)),
         )
-        .subcommand(SubCommand::with_name("build").about("Builds the SpanDeX project"));
+        .subcommand(SubCommand::with_name("build").about("Builds the SpanDeX project"))
+        .subcommand(
+            SubCommand::with_name("ast")
+                .about("Prints the ast of the SpanDeX project as JSON")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .required(false)
+                        .help("The file to parse instead of the input of the project"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Formats the dex files of the SpanDeX project")
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("Checks that the files are formatted instead of formatting them"),
+                )
+                .arg(
+                    Arg::with_name("width")
+                        .long("width")
+                        .value_name("COLUMN")
+                        .help("The column at which the paragraphs are wrapped"),
+                )
+                .arg(
+                    Arg::with_name("FILES")
+                        .multiple(true)
+                        .required(false)
+                        .help("The files to format instead of the input of the project"),
+                ),
+        );
 
     let matches = app.clone().get_matches();
 
@@ -76,30 +108,44 @@ fn run() -> Result<(), Error> {
         let mut file = File::create(&current_dir)?;
         file.write_all(b"# Hello world")?;
     } else if matches.subcommand_matches("build").is_some() {
-        // Look up for spandex config file
-        let mut current_dir = unwrap!(current_dir().ok(), Error::CannotReadCurrentDir);
-        let config_path = loop {
-            current_dir.push("spandex.toml");
-
-            if current_dir.is_file() {
-                break current_dir;
-            } else {
-                // Remove spandex.toml
-                current_dir.pop();
-
-                // Go to the parent directory
-                if !current_dir.pop() {
-                    return Err(Error::NoConfigFile);
-                }
+        build(&read_config()?)?;
+    } else if let Some(ast) = matches.subcommand_matches("ast") {
+        let mut config = read_config()?;
+        if let Some(input) = ast.value_of("INPUT") {
+            config.input = input.into();
+        }
+        println!("{}", ast_json(&config)?);
+    } else if let Some(fmt) = matches.subcommand_matches("fmt") {
+        // The files given on the command line can be formatted outside of a project
+        let config = read_config();
+
+        let width = if fmt.is_present("width") {
+            value_t!(fmt, "width", usize).unwrap_or_else(|e| e.exit())
+        } else {
+            match &config {
+                Ok(config) => config.format_width.unwrap_or(DEFAULT_WIDTH),
+                Err(_) => DEFAULT_WIDTH,
             }
         };
 
-        // Read config file
-        let mut file = File::open(&config_path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
-        let config: Config = toml::from_str(&content).expect("Failed to parse toml");
-        build(&config)?;
+        let files = match fmt.values_of("FILES") {
+            Some(files) => files.map(PathBuf::from).collect(),
+            None => vec![PathBuf::from(config?.input)],
+        };
+
+        let check = fmt.is_present("check");
+        let mut unformatted = false;
+
+        for file in files {
+            if !format_file(&file, width, check)? && check {
+                eprintln!("\"{}\" is not formatted", file.display());
+                unformatted = true;
+            }
+        }
+
+        if unformatted {
+            exit(1);
+        }
     } else {
         // Nothing to do, print help
         app.print_help().ok();
@@ -108,3 +154,30 @@ fn run() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Reads the config of the project containing the current directory.
+fn read_config() -> Result<Config, Error> {
+    // Look up for spandex config file
+    let mut current_dir = unwrap!(current_dir().ok(), Error::CannotReadCurrentDir);
+    let config_path = loop {
+        current_dir.push("spandex.toml");
+
+        if current_dir.is_file() {
+            break current_dir;
+        } else {
+            // Remove spandex.toml
+            current_dir.pop();
+
+            // Go to the parent directory
+            if !current_dir.pop() {
+                return Err(Error::NoConfigFile);
+            }
+        }
+    };
+
+    // Read config file
+    let mut file = File::open(&config_path)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(toml::from_str(&content).expect("Failed to parse toml"))
+}
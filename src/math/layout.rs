@@ -0,0 +1,494 @@
+//! This module lays out formulas into boxes of glyphs.
+
+use std::cmp::Ordering;
+use std::f64;
+use std::iter::once;
+
+use printpdf::Pt;
+
+use crate::font::{Font, FontConfig};
+use crate::math::{Class, Math};
+use crate::typography::items::Item;
+use crate::typography::Glyph;
+
+/// The ratio between the size of scripts and the size of their base.
+const SCRIPT_RATIO: f64 = 0.7;
+
+/// The ratio between the size of scripts of scripts and the size of the text.
+const SCRIPT_SCRIPT_RATIO: f64 = 0.5;
+
+/// The minimal shift of superscripts, relative to the size of their base.
+const SUPERSCRIPT_SHIFT: f64 = 0.4;
+
+/// The minimal shift of subscripts, relative to the size of their base.
+const SUBSCRIPT_SHIFT: f64 = 0.15;
+
+/// How much lower than the top of their base superscripts are, relative to their size.
+const SUPERSCRIPT_DROP: f64 = 0.386;
+
+/// How much lower than the bottom of their base subscripts are, relative to their size.
+const SUBSCRIPT_DROP: f64 = 0.05;
+
+/// The minimal gap between a superscript and a subscript, relative to the size of their base.
+const SCRIPTS_GAP: f64 = 0.16;
+
+/// The space after scripts, relative to the size of their base.
+const SCRIPT_SPACE: f64 = 0.05;
+
+/// The gap between a fraction bar and its numerator or denominator, relative to the size.
+const FRACTION_GAP: f64 = 0.1;
+
+/// The gap between the parts of a composed symbol, relative to the size.
+const COMPOSITION_GAP: f64 = 0.08;
+
+/// The penalty of a line break after a relation at the top level of an inline formula.
+const RELATION_PENALTY: f64 = 500.0;
+
+/// The penalty of a line break after a binary operator at the top level of an inline formula.
+const BINARY_PENALTY: f64 = 700.0;
+
+/// The glyph whose bounds give the thickness and the height of rules.
+///
+/// In Computer Modern, the en dash is a rule of the default rule thickness.
+const RULE_GLYPH: char = '–';
+
+/// Returns the greatest of two lengths.
+fn max(a: Pt, b: Pt) -> Pt {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// Returns the size of the elements at a certain level of scripts.
+fn scaled(size: Pt, level: u8) -> Pt {
+    match level {
+        0 => size,
+        1 => size * SCRIPT_RATIO,
+        _ => size * SCRIPT_SCRIPT_RATIO,
+    }
+}
+
+/// A formula laid out as glyphs around a baseline.
+#[derive(Debug, Clone)]
+pub struct MathBox<'a> {
+    /// The width of the box.
+    pub width: Pt,
+
+    /// The distance between the baseline and the top of the box.
+    pub height: Pt,
+
+    /// The distance between the baseline and the bottom of the box.
+    pub depth: Pt,
+
+    /// The glyphs of the box with their horizontal offsets.
+    ///
+    /// The vertical offset of each glyph is its shift.
+    pub glyphs: Vec<(Glyph<'a>, Pt)>,
+
+    /// The offsets before which a line can be broken, with the penalties of the breaks.
+    ///
+    /// As in TeX, an inline formula can only be broken after a relation or a binary operator at
+    /// its top level.
+    pub breaks: Vec<(Pt, f64)>,
+}
+
+impl<'a> MathBox<'a> {
+    /// Creates an empty box.
+    pub fn empty() -> MathBox<'a> {
+        MathBox {
+            width: Pt(0.0),
+            height: Pt(0.0),
+            depth: Pt(0.0),
+            glyphs: vec![],
+            breaks: vec![],
+        }
+    }
+
+    /// Creates a box containing a single glyph.
+    pub fn glyph(glyph: char, font: &'a Font, size: Pt) -> MathBox<'a> {
+        let (height, depth) = font.char_bounds(glyph, size);
+        MathBox {
+            width: font.char_width(glyph, size),
+            height,
+            depth,
+            glyphs: vec![(Glyph::new(glyph, font, size), Pt(0.0))],
+            breaks: vec![],
+        }
+    }
+
+    /// Creates a horizontal rule of a specified width.
+    ///
+    /// The rule has the thickness and the height of the rule glyph.
+    pub fn rule(width: Pt, font: &'a Font, size: Pt) -> MathBox<'a> {
+        let mut rule = MathBox::glyph(RULE_GLYPH, font, size);
+        rule.glyphs[0].0.rule = Some(width);
+        rule.width = width;
+        rule
+    }
+
+    /// Returns the height of the vertical center of the box.
+    pub fn center(&self) -> Pt {
+        (self.height - self.depth) / 2.0
+    }
+
+    /// Adds the glyphs of another box, moved by the specified offsets.
+    ///
+    /// This does not change the width of the box, and the breaks of the other box are lost.
+    pub fn add(&mut self, other: MathBox<'a>, x: Pt, shift: Pt) {
+        self.height = max(self.height, other.height + shift);
+        self.depth = max(self.depth, other.depth - shift);
+        for (glyph, offset) in other.glyphs {
+            self.glyphs.push((glyph.shifted(shift), offset + x));
+        }
+    }
+
+    /// Converts the box into items that can be added to a paragraph.
+    ///
+    /// Items are sequential, so the glyphs are sorted by horizontal offset and each of them
+    /// occupies the space until the next one. This allows stacked glyphs, such as the ones of a
+    /// fraction, to flow through the algorithm without any possible break between them. The breaks
+    /// of the box become penalties before the glyphs that follow them.
+    pub fn into_items(self) -> Vec<Item<'a>> {
+        let mut glyphs = self.glyphs;
+        glyphs.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+
+        let mut items = vec![];
+
+        if let Some((_, first)) = glyphs.first() {
+            if *first > Pt(0.0) {
+                // The penalty prevents a break before the glue.
+                items.push(Item::penalty(Pt(0.0), f64::INFINITY, false));
+                items.push(Item::glue(*first, Pt(0.0), Pt(0.0)));
+            }
+        }
+
+        let next_offsets = glyphs
+            .iter()
+            .skip(1)
+            .map(|x| x.1)
+            .chain(once(self.width))
+            .collect::<Vec<_>>();
+
+        let mut breaks = self.breaks.into_iter().peekable();
+
+        for ((glyph, offset), next_offset) in glyphs.into_iter().zip(next_offsets) {
+            let mut penalty = None;
+            while let Some((_, next)) = breaks.next_if(|x| x.0 <= offset) {
+                penalty = Some(next);
+            }
+
+            if let (Some(penalty), false) = (penalty, items.is_empty()) {
+                items.push(Item::penalty(Pt(0.0), penalty, false));
+            }
+
+            let width = max(next_offset - offset, Pt(0.0));
+            items.push(Item::from_glyph_with_width(glyph, width));
+        }
+
+        items
+    }
+}
+
+/// Returns the classes of the elements of a row, as they are used for spacing.
+///
+/// Binary operators that have nothing to operate on become ordinary symbols, e.g. a minus sign at
+/// the beginning of a formula.
+fn classes(children: &[Math]) -> Vec<Class> {
+    let mut classes = children
+        .iter()
+        .map(|x| match x.class() {
+            Class::Variable => Class::Ordinary,
+            class => class,
+        })
+        .collect::<Vec<_>>();
+
+    for i in 0..classes.len() {
+        if classes[i] == Class::Binary {
+            let after_operator = i == 0
+                || matches!(
+                    classes[i - 1],
+                    Class::Binary | Class::Relation | Class::Open | Class::Punctuation
+                );
+
+            let before_operator = i + 1 == classes.len()
+                || matches!(
+                    classes[i + 1],
+                    Class::Relation | Class::Close | Class::Punctuation
+                );
+
+            if after_operator || before_operator {
+                classes[i] = Class::Ordinary;
+            }
+        }
+    }
+
+    classes
+}
+
+/// Returns the space between two elements, in eighteenths of the size.
+///
+/// These are the spaces used by TeX in text style, scripts are typeset without spaces.
+fn spacing(left: Class, right: Class, level: u8) -> f64 {
+    let (thin, medium, thick) = if level == 0 {
+        (3.0, 4.0, 5.0)
+    } else {
+        (0.0, 0.0, 0.0)
+    };
+
+    match (left, right) {
+        (Class::Open, _) | (_, Class::Close) => 0.0,
+        (Class::Binary, _) | (_, Class::Binary) => medium,
+        (Class::Relation, _) | (_, Class::Relation) => thick,
+        (Class::Punctuation, _) | (Class::Inner, _) | (_, Class::Inner) => thin,
+        _ => 0.0,
+    }
+}
+
+/// Lays out a symbol, composing it from other glyphs when the font lacks it.
+fn symbol<'a>(c: char, class: Class, font_config: &'a FontConfig, size: Pt) -> MathBox<'a> {
+    let font = if class == Class::Variable && font_config.italic.has_char(c) {
+        font_config.italic
+    } else {
+        font_config.regular
+    };
+
+    if font.has_char(c) {
+        return MathBox::glyph(c, font, size);
+    }
+
+    match c {
+        // A minus is a dash as wide as a plus.
+        '−' => overlay('+', RULE_GLYPH, font, size, false),
+
+        // A negated equal is a slashed equal.
+        '≠' => overlay('=', '/', font, size, true),
+
+        // A lower or equal is a lower raised above a rule.
+        '≤' | '≥' => {
+            let sign = MathBox::glyph(if c == '≤' { '<' } else { '>' }, font, size);
+            let gap = size * COMPOSITION_GAP;
+            let rule = MathBox::rule(sign.width, font, size);
+            let rule_top = rule.height;
+            let width = sign.width;
+            let sign_depth = sign.depth;
+
+            // The rule is one gap below the raised sign.
+            let lift = gap;
+            let rule_shift = lift - sign_depth - gap - rule_top;
+
+            let mut composed = MathBox::empty();
+            composed.add(sign, Pt(0.0), lift);
+            composed.add(rule, Pt(0.0), rule_shift);
+            composed.width = width;
+            composed
+        }
+
+        _ => MathBox::glyph(c, font, size),
+    }
+}
+
+/// Lays out a glyph centered on another one.
+///
+/// The box has the width of the base glyph, and the other glyph is kept visible if it is drawn
+/// with the base.
+fn overlay<'a>(base: char, over: char, font: &'a Font, size: Pt, draw_base: bool) -> MathBox<'a> {
+    let base = MathBox::glyph(base, font, size);
+    let over = MathBox::glyph(over, font, size);
+
+    let x = (base.width - over.width) / 2.0;
+    let shift = base.center() - over.center();
+    let width = base.width;
+
+    let mut composed = MathBox::empty();
+    if draw_base {
+        composed.add(base, Pt(0.0), Pt(0.0));
+    }
+    composed.add(over, x, shift);
+    composed.width = width;
+    composed
+}
+
+/// Lays out a formula.
+///
+/// The size is the size of the text around the formula, and the level is the depth of scripts at
+/// which the formula is.
+pub fn layout<'a>(math: &Math, font_config: &'a FontConfig, size: Pt, level: u8) -> MathBox<'a> {
+    let current_size = scaled(size, level);
+
+    match math {
+        Math::Symbol(c, class) => symbol(*c, *class, font_config, current_size),
+
+        Math::Row(children) => {
+            let mut row = MathBox::empty();
+            let mut previous = None;
+
+            for (child, class) in children.iter().zip(classes(children)) {
+                if let Some(previous) = previous {
+                    row.width += current_size * spacing(previous, class, level) / 18.0;
+
+                    let penalty = match (previous, class) {
+                        (Class::Relation, Class::Relation) => None,
+                        (Class::Relation, _) => Some(RELATION_PENALTY),
+                        (Class::Binary, _) => Some(BINARY_PENALTY),
+                        _ => None,
+                    };
+
+                    if let (Some(penalty), 0) = (penalty, level) {
+                        row.breaks.push((row.width, penalty));
+                    }
+                }
+
+                let child = layout(child, font_config, size, level);
+                let width = child.width;
+                let x = row.width;
+                row.add(child, x, Pt(0.0));
+                row.width += width;
+                previous = Some(class);
+            }
+
+            row
+        }
+
+        Math::Scripts {
+            base,
+            superscript,
+            subscript,
+        } => {
+            let base = layout(base, font_config, size, level);
+            let superscript = superscript
+                .as_ref()
+                .map(|x| layout(x, font_config, size, level + 1));
+            let subscript = subscript
+                .as_ref()
+                .map(|x| layout(x, font_config, size, level + 1));
+            let script_size = scaled(size, level + 1);
+
+            let mut superscript_shift = max(
+                current_size * SUPERSCRIPT_SHIFT,
+                base.height - script_size * SUPERSCRIPT_DROP,
+            );
+
+            let mut subscript_shift = max(
+                current_size * SUBSCRIPT_SHIFT,
+                base.depth + script_size * SUBSCRIPT_DROP,
+            );
+
+            if let Some(superscript) = &superscript {
+                superscript_shift = max(superscript_shift, superscript.depth + script_size * 0.25);
+            }
+
+            if let (Some(superscript), Some(subscript)) = (&superscript, &subscript) {
+                let gap =
+                    (superscript_shift - superscript.depth) - (subscript.height - subscript_shift);
+                let min_gap = current_size * SCRIPTS_GAP;
+                if gap < min_gap {
+                    subscript_shift += min_gap - gap;
+                }
+            }
+
+            let mut scripts_width = Pt(0.0);
+            let mut result = MathBox::empty();
+            let base_width = base.width;
+            result.add(base, Pt(0.0), Pt(0.0));
+
+            if let Some(superscript) = superscript {
+                scripts_width = max(scripts_width, superscript.width);
+                result.add(superscript, base_width, superscript_shift);
+            }
+
+            if let Some(subscript) = subscript {
+                scripts_width = max(scripts_width, subscript.width);
+                result.add(subscript, base_width, Pt(0.0) - subscript_shift);
+            }
+
+            result.width = base_width + scripts_width + current_size * SCRIPT_SPACE;
+            result
+        }
+
+        Math::Fraction {
+            numerator,
+            denominator,
+        } => {
+            let numerator = layout(numerator, font_config, size, level + 1);
+            let denominator = layout(denominator, font_config, size, level + 1);
+
+            let width = max(numerator.width, denominator.width);
+            let rule = MathBox::rule(width, font_config.regular, current_size);
+            let width = rule.width;
+            let axis = rule.center();
+            let thickness = rule.height + rule.depth;
+            let gap = current_size * FRACTION_GAP;
+
+            let numerator_x = (width - numerator.width) / 2.0;
+            let numerator_shift = axis + thickness / 2.0 + gap + numerator.depth;
+            let denominator_x = (width - denominator.width) / 2.0;
+            let denominator_shift = axis - thickness / 2.0 - gap - denominator.height;
+
+            let mut fraction = MathBox::empty();
+            fraction.add(rule, Pt(0.0), Pt(0.0));
+            fraction.add(numerator, numerator_x, numerator_shift);
+            fraction.add(denominator, denominator_x, denominator_shift);
+            fraction.width = width;
+            fraction
+        }
+
+        Math::Error(_) => MathBox::empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use nom::types::CompleteStr;
+    use printpdf::Pt;
+
+    use crate::config::Config;
+    use crate::math::layout::layout;
+    use crate::math::parser::parse;
+    use crate::parser::Span;
+    use crate::typography::items::Content;
+    use crate::Result;
+
+    #[test]
+    fn test_items() -> Result<()> {
+        let (_, font_manager) = Config::with_title("Test").init()?;
+        let config = font_manager.default_config();
+
+        let formula = parse(Span::new(CompleteStr("\\frac{a^2}{b_i} + 1")));
+        let laid_out = layout(&formula, &config, Pt(10.0), 0);
+        let width = laid_out.width;
+        let items = laid_out.into_items();
+
+        // The bar, a, 2, b, i, +, a penalty, 1
+        assert_eq!(items.len(), 8);
+
+        let total = items.iter().fold(Pt(0.0), |acc, x| acc + x.width);
+        assert!((total.0 - width.0).abs() < 1e-9);
+        assert!(items.iter().all(|x| x.width >= Pt(0.0)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_breaks() -> Result<()> {
+        let (_, font_manager) = Config::with_title("Test").init()?;
+        let config = font_manager.default_config();
+
+        let formula = parse(Span::new(CompleteStr("-a + b^{c + d} = e")));
+        let penalties = layout(&formula, &config, Pt(10.0), 0)
+            .into_items()
+            .into_iter()
+            .filter_map(|x| match x.content {
+                Content::Penalty { value, .. } if value.is_finite() => Some(value),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // The leading minus and the plus of the script cannot be broken.
+        assert_eq!(penalties, vec![700.0, 500.0]);
+
+        Ok(())
+    }
+}
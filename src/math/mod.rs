@@ -0,0 +1,215 @@
+//! This module contains everything related to mathematical formulas.
+//!
+//! Formulas are written in a subset of TeX: superscripts, subscripts, fractions, greek letters and
+//! common operators.
+
+pub mod layout;
+pub mod parser;
+
+use serde::{Deserialize, Serialize};
+
+use crate::parser::error::EmptyError;
+
+/// The class of a symbol, which drives the spacing around it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Class {
+    /// A variable, typeset in italic.
+    Variable,
+
+    /// An ordinary symbol, such as a digit.
+    Ordinary,
+
+    /// A binary operator, such as a plus.
+    Binary,
+
+    /// A relation, such as an equal sign.
+    Relation,
+
+    /// An opening delimiter.
+    Open,
+
+    /// A closing delimiter.
+    Close,
+
+    /// A punctuation mark.
+    Punctuation,
+
+    /// A compound element, such as a fraction.
+    Inner,
+}
+
+/// The abstract syntax tree of a formula.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Math {
+    /// A single symbol.
+    Symbol(char, Class),
+
+    /// A sequence of elements, typeset next to each other.
+    Row(Vec<Math>),
+
+    /// An element with a superscript, a subscript or both.
+    Scripts {
+        /// The element that receives the scripts.
+        base: Box<Math>,
+
+        /// The superscript, if any.
+        superscript: Option<Box<Math>>,
+
+        /// The subscript, if any.
+        subscript: Option<Box<Math>>,
+    },
+
+    /// A fraction.
+    Fraction {
+        /// The numerator of the fraction.
+        numerator: Box<Math>,
+
+        /// The denominator of the fraction.
+        denominator: Box<Math>,
+    },
+
+    /// An error.
+    ///
+    /// As in the dex ast, errors are stored in the tree so that many of them can be reported.
+    Error(EmptyError),
+}
+
+impl Math {
+    /// Returns all the errors contained in the formula.
+    pub fn errors(&self) -> Vec<EmptyError> {
+        let mut errors = vec![];
+
+        match self {
+            Math::Error(e) => errors.push(e.clone()),
+
+            Math::Row(children) => {
+                for child in children {
+                    errors.extend(child.errors());
+                }
+            }
+
+            Math::Scripts {
+                base,
+                superscript,
+                subscript,
+            } => {
+                errors.extend(base.errors());
+                if let Some(superscript) = superscript {
+                    errors.extend(superscript.errors());
+                }
+                if let Some(subscript) = subscript {
+                    errors.extend(subscript.errors());
+                }
+            }
+
+            Math::Fraction {
+                numerator,
+                denominator,
+            } => {
+                errors.extend(numerator.errors());
+                errors.extend(denominator.errors());
+            }
+
+            Math::Symbol(_, _) => (),
+        }
+
+        errors
+    }
+
+    /// Returns the class of the element, as seen from its neighbours.
+    pub fn class(&self) -> Class {
+        match self {
+            Math::Symbol(_, class) => *class,
+            Math::Scripts { base, .. } => base.class(),
+            Math::Fraction { .. } => Class::Inner,
+            Math::Row(_) | Math::Error(_) => Class::Ordinary,
+        }
+    }
+}
+
+/// Returns the symbol corresponding to a single character of a formula.
+///
+/// Returns None for the characters that have a special meaning in formulas.
+pub fn symbol(c: char) -> Option<Math> {
+    let class = match c {
+        '{' | '}' | '^' | '_' | '\\' | '$' => return None,
+        '+' | '*' => Class::Binary,
+        '=' | '<' | '>' | ':' => Class::Relation,
+        '(' | '[' => Class::Open,
+        ')' | ']' | '!' | '?' => Class::Close,
+        ',' | ';' => Class::Punctuation,
+        '-' => return Some(Math::Symbol('−', Class::Binary)),
+        c if c.is_alphabetic() => Class::Variable,
+        _ => Class::Ordinary,
+    };
+
+    Some(Math::Symbol(c, class))
+}
+
+/// Returns the symbol corresponding to a command, e.g. `\alpha`.
+pub fn command(name: &str) -> Option<Math> {
+    let (c, class) = match name {
+        // Lowercase greek letters
+        "alpha" => ('α', Class::Variable),
+        "beta" => ('β', Class::Variable),
+        "gamma" => ('γ', Class::Variable),
+        "delta" => ('δ', Class::Variable),
+        "epsilon" | "varepsilon" => ('ε', Class::Variable),
+        "zeta" => ('ζ', Class::Variable),
+        "eta" => ('η', Class::Variable),
+        "theta" => ('θ', Class::Variable),
+        "vartheta" => ('ϑ', Class::Variable),
+        "iota" => ('ι', Class::Variable),
+        "kappa" => ('κ', Class::Variable),
+        "lambda" => ('λ', Class::Variable),
+        "mu" => ('μ', Class::Variable),
+        "nu" => ('ν', Class::Variable),
+        "xi" => ('ξ', Class::Variable),
+        "omicron" => ('ο', Class::Variable),
+        "pi" => ('π', Class::Variable),
+        "rho" => ('ρ', Class::Variable),
+        "sigma" => ('σ', Class::Variable),
+        "varsigma" => ('ς', Class::Variable),
+        "tau" => ('τ', Class::Variable),
+        "upsilon" => ('υ', Class::Variable),
+        "phi" => ('ϕ', Class::Variable),
+        "varphi" => ('φ', Class::Variable),
+        "chi" => ('χ', Class::Variable),
+        "psi" => ('ψ', Class::Variable),
+        "omega" => ('ω', Class::Variable),
+
+        // Uppercase greek letters
+        "Gamma" => ('Γ', Class::Ordinary),
+        "Delta" => ('Δ', Class::Ordinary),
+        "Theta" => ('Θ', Class::Ordinary),
+        "Lambda" => ('Λ', Class::Ordinary),
+        "Xi" => ('Ξ', Class::Ordinary),
+        "Pi" => ('Π', Class::Ordinary),
+        "Sigma" => ('Σ', Class::Ordinary),
+        "Upsilon" => ('Υ', Class::Ordinary),
+        "Phi" => ('Φ', Class::Ordinary),
+        "Psi" => ('Ψ', Class::Ordinary),
+        "Omega" => ('Ω', Class::Ordinary),
+
+        // Binary operators
+        "pm" => ('±', Class::Binary),
+        "cdot" => ('·', Class::Binary),
+        "div" => ('÷', Class::Binary),
+
+        // Relations
+        "neq" | "ne" => ('≠', Class::Relation),
+        "leq" | "le" => ('≤', Class::Relation),
+        "geq" | "ge" => ('≥', Class::Relation),
+
+        // Ordinary symbols
+        "neg" | "lnot" => ('¬', Class::Ordinary),
+        "ldots" | "dots" => ('…', Class::Ordinary),
+        "{" => ('{', Class::Open),
+        "}" => ('}', Class::Close),
+        "|" => ('‖', Class::Ordinary),
+
+        _ => return None,
+    };
+
+    Some(Math::Symbol(c, class))
+}
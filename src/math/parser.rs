@@ -0,0 +1,242 @@
+//! This module contains the parser for formulas.
+
+// This module contains marcos that can't be documented, so we'll allow missing docs here.
+#![allow(missing_docs)]
+// Allow redundant closure because of nom.
+#![allow(clippy::redundant_closure)]
+// Doc comments on nom macros are not rendered by rustdoc.
+#![allow(unused_doc_comments)]
+
+use nom::*;
+
+use crate::math::{command, symbol, Math};
+use crate::parser::error::{EmptyError, ErrorType};
+use crate::parser::{position, Span};
+
+/// Creates an error.
+pub fn error(span: Span, ty: ErrorType) -> Math {
+    Math::Error(EmptyError {
+        position: position(&span),
+        ty,
+    })
+}
+
+/// Attaches the scripts parsed after an element to it.
+///
+/// A script that is already present starts a new level of scripts, e.g. `x^2^3` is `{x^2}^3`.
+pub fn attach_scripts(base: Math, scripts: Vec<(Span, Math)>) -> Math {
+    scripts.into_iter().fold(base, |base, (kind, script)| {
+        let is_superscript = kind.fragment.0 == "^";
+        let script = Some(Box::new(script));
+
+        match base {
+            Math::Scripts {
+                base,
+                superscript: None,
+                subscript,
+            } if is_superscript => Math::Scripts {
+                base,
+                superscript: script,
+                subscript,
+            },
+
+            Math::Scripts {
+                base,
+                superscript,
+                subscript: None,
+            } if !is_superscript => Math::Scripts {
+                base,
+                superscript,
+                subscript: script,
+            },
+
+            base if is_superscript => Math::Scripts {
+                base: Box::new(base),
+                superscript: script,
+                subscript: None,
+            },
+
+            base => Math::Scripts {
+                base: Box::new(base),
+                superscript: None,
+                subscript: script,
+            },
+        }
+    })
+}
+
+/// Parses some whitespace.
+named!(pub parse_whitespace<Span, Span>,
+    take_while!(char::is_whitespace)
+);
+
+/// Parses a single character.
+named!(pub parse_symbol<Span, Math>,
+    map_opt!(take!(1), |x: Span| x.fragment.0.chars().next().and_then(symbol))
+);
+
+/// Parses the name of a command, after its backslash.
+named!(pub parse_command_name<Span, Span>,
+    alt!(take_while1!(|x: char| x.is_ascii_alphabetic()) | take!(1))
+);
+
+/// Parses an element between braces.
+named!(pub parse_braced<Span, Math>,
+    delimited!(tag!("{"), parse_row, preceded!(parse_whitespace, tag!("}")))
+);
+
+/// Parses a command, e.g. `\alpha` or `\frac{a}{b}`.
+pub fn parse_command(input: Span) -> IResult<Span, Math> {
+    let (rest, name) = preceded!(input, tag!("\\"), parse_command_name)?;
+
+    match name.fragment.0 {
+        "frac" => do_parse!(
+            rest,
+            numerator: preceded!(parse_whitespace, parse_argument)
+                >> denominator: preceded!(parse_whitespace, parse_argument)
+                >> (Math::Fraction {
+                    numerator: Box::new(numerator),
+                    denominator: Box::new(denominator),
+                })
+        ),
+
+        name => match command(name) {
+            Some(symbol) => Ok((rest, symbol)),
+            None => Ok((rest, error(input, ErrorType::UnknownMathCommand))),
+        },
+    }
+}
+
+/// Parses an element that can receive scripts.
+named!(pub parse_atom<Span, Math>,
+    alt!(
+        parse_braced
+        | parse_command
+        | tag!("\\") => { |x| error(x, ErrorType::UnknownMathCommand) }
+        | tag!("{") => { |x| error(x, ErrorType::UnmatchedBrace) }
+        | parse_symbol
+    )
+);
+
+/// Parses the argument of a script or a command.
+named!(pub parse_argument<Span, Math>,
+    alt!(
+        parse_atom
+        | take!(0) => { |x| error(x, ErrorType::MissingMathArgument) }
+    )
+);
+
+/// Parses a superscript or a subscript.
+named!(pub parse_script<Span, (Span, Math)>,
+    do_parse!(
+        kind: preceded!(parse_whitespace, alt!(tag!("^") | tag!("_"))) >>
+        script: preceded!(parse_whitespace, parse_argument) >>
+        (kind, script)
+    )
+);
+
+/// Parses an element and its scripts.
+named!(pub parse_term<Span, Math>,
+    do_parse!(
+        base: alt!(
+            preceded!(parse_whitespace, parse_atom)
+            | map!(peek!(parse_script), |_| Math::Row(vec![]))
+        ) >>
+        scripts: many0!(parse_script) >>
+        (attach_scripts(base, scripts))
+    )
+);
+
+/// Parses a sequence of elements.
+named!(pub parse_row<Span, Math>,
+    map!(many0!(parse_term), Math::Row)
+);
+
+/// Parses a whole formula.
+named!(pub parse_formula<Span, Math>,
+    map!(
+        many0!(alt!(
+            parse_term
+            | preceded!(parse_whitespace, tag!("}")) => { |x| error(x, ErrorType::UnmatchedBrace) }
+        )),
+        Math::Row
+    )
+);
+
+/// Parses a formula.
+///
+/// The errors of the formula are stored in the returned tree.
+pub fn parse(input: Span) -> Math {
+    match parse_formula(input) {
+        Ok((_, math)) => math,
+        // many0 cannot fail on complete input.
+        Err(_) => Math::Row(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use nom::types::CompleteStr;
+
+    use crate::math::parser::parse;
+    use crate::math::{Class, Math};
+    use crate::parser::error::ErrorType;
+    use crate::parser::Span;
+
+    fn parse_str(input: &str) -> Math {
+        parse(Span::new(CompleteStr(input)))
+    }
+
+    #[test]
+    fn test_scripts() {
+        let expected = Math::Row(vec![Math::Scripts {
+            base: Box::new(Math::Symbol('x', Class::Variable)),
+            superscript: Some(Box::new(Math::Symbol('2', Class::Ordinary))),
+            subscript: Some(Box::new(Math::Symbol('α', Class::Variable))),
+        }]);
+
+        assert_eq!(parse_str("x^2_\\alpha"), expected);
+        assert_eq!(parse_str("x _ \\alpha ^ 2"), expected);
+    }
+
+    #[test]
+    fn test_fraction() {
+        let expected = Math::Row(vec![
+            Math::Fraction {
+                numerator: Box::new(Math::Row(vec![
+                    Math::Symbol('a', Class::Variable),
+                    Math::Symbol('+', Class::Binary),
+                    Math::Symbol('1', Class::Ordinary),
+                ])),
+                denominator: Box::new(Math::Symbol('π', Class::Variable)),
+            },
+            Math::Symbol('≤', Class::Relation),
+            Math::Symbol('1', Class::Ordinary),
+        ]);
+
+        assert_eq!(parse_str("\\frac{a + 1}\\pi \\leq 1"), expected);
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors = parse_str("x^{\\foo}").errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].ty, ErrorType::UnknownMathCommand);
+        assert_eq!(errors[0].position.offset, 3);
+
+        let errors = parse_str("\\frac{a}").errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].ty, ErrorType::MissingMathArgument);
+
+        let errors = parse_str("{a").errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].ty, ErrorType::UnmatchedBrace);
+        assert_eq!(errors[0].position.offset, 0);
+
+        let errors = parse_str("a}").errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].ty, ErrorType::UnmatchedBrace);
+        assert_eq!(errors[0].position.offset, 1);
+    }
+}
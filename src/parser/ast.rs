@@ -1,14 +1,92 @@
 //! This module contains everything related to the ast.
 
 use std::fmt;
+use std::ops::{Deref, DerefMut};
 
 use colored::*;
+use serde::{Deserialize, Serialize};
 
+use crate::math::Math;
 use crate::parser::error::EmptyError;
+use crate::parser::visit::Visit;
 use crate::parser::warning::EmptyWarning;
+use crate::parser::{Position, SourceSpan};
+
+/// The alignment of the cells of a column of a table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Alignment {
+    /// The cells are aligned on the left, e.g. `|:---|` or `|---|`.
+    Left,
+
+    /// The cells are centered, e.g. `|:---:|`.
+    Center,
+
+    /// The cells are aligned on the right, e.g. `|---:|`.
+    Right,
+}
+
+/// A node of the abstract syntax tree, along with the part of the file it was parsed from.
+///
+/// Nodes dereference to their ast, and the nodes created after the parsing, e.g. while rendering,
+/// have a default span.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Node {
+    /// The content of the node.
+    pub ast: Ast,
+
+    /// The part of the file the node was parsed from.
+    #[serde(default)]
+    pub span: SourceSpan,
+}
+
+impl Node {
+    /// Creates a node from an ast and a span.
+    pub fn new(ast: Ast, span: SourceSpan) -> Node {
+        Node { ast, span }
+    }
+
+    /// Returns a node with the same span, whose ast is computed from this one.
+    pub fn map<F: FnOnce(&Ast) -> Ast>(&self, f: F) -> Node {
+        Node::new(f(&self.ast), self.span)
+    }
+}
+
+impl From<Ast> for Node {
+    fn from(ast: Ast) -> Node {
+        Node::new(ast, SourceSpan::default())
+    }
+}
+
+impl Deref for Node {
+    type Target = Ast;
+
+    fn deref(&self) -> &Ast {
+        &self.ast
+    }
+}
+
+impl DerefMut for Node {
+    fn deref_mut(&mut self) -> &mut Ast {
+        &mut self.ast
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.ast)
+    }
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{:?}", self.ast)
+    }
+}
 
 /// The abstract syntax tree representing the parsed file.
-#[derive(PartialEq, Eq, Clone)]
+///
+/// Every child of an ast is a node, which knows where it comes from.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum Ast {
     /// A title.
     Title {
@@ -16,32 +94,200 @@ pub enum Ast {
         level: u8,
 
         /// The content of the title.
-        content: Box<Ast>,
+        content: Box<Node>,
     },
 
     /// Some bold content.
-    Bold(Box<Ast>),
+    Bold(Box<Node>),
 
     /// Some italic content.
-    Italic(Box<Ast>),
+    Italic(Box<Node>),
 
-    /// A math inlinemath.
-    InlineMath(String),
+    /// Some content in small capitals.
+    SmallCaps(Box<Node>),
+
+    /// Some underlined content.
+    Underline(Box<Node>),
+
+    /// Some struck through content.
+    Strikethrough(Box<Node>),
+
+    /// Some content raised above the baseline, in a smaller size.
+    Superscript(Box<Node>),
+
+    /// Some content lowered below the baseline, in a smaller size.
+    Subscript(Box<Node>),
+
+    /// A link to a url, or to a label if the url starts with a hash.
+    Link {
+        /// The target of the link.
+        url: String,
+
+        /// The text of the link.
+        content: Box<Node>,
+
+        /// The position of the link.
+        position: Position,
+    },
+
+    /// A footnote, whose marker is placed in the text and whose content is placed at the bottom
+    /// of the page.
+    Footnote(Box<Node>),
+
+    /// A math formula inside a paragraph.
+    InlineMath {
+        /// The content of the formula.
+        content: String,
+
+        /// The parsed formula.
+        formula: Math,
+    },
+
+    /// A math formula on its own line.
+    DisplayMath {
+        /// The content of the formula.
+        content: String,
+
+        /// The parsed formula.
+        formula: Math,
+
+        /// Whether the equation receives a number.
+        numbered: bool,
+    },
 
     /// Some text.
     Text(String),
 
+    /// Some escaped characters, e.g. `\-`, which are typeset as they are written, without smart
+    /// typography.
+    Escaped(String),
+
+    /// Some inline code, e.g. `` `let x = 1;` ``, written verbatim in a monospace font.
+    Code(String),
+
+    /// A block of code, written verbatim in a monospace font.
+    CodeBlock {
+        /// The language of the code, written after the opening fence.
+        language: Option<String>,
+
+        /// The lines of the code.
+        content: String,
+    },
+
+    /// A label, e.g. `{#intro}`.
+    ///
+    /// Labels refer to the last numbered element before them, such as a title or an equation.
+    Label {
+        /// The name of the label.
+        name: String,
+
+        /// The position of the label.
+        position: Position,
+    },
+
+    /// A reference to a label, e.g. `{@intro}`, or `{@page:intro}` for its page.
+    Reference {
+        /// The name of the label.
+        label: String,
+
+        /// Whether the reference is to the page of the label rather than to its number.
+        page: bool,
+
+        /// The position of the reference.
+        position: Position,
+    },
+
+    /// A citation of entries of the bibliography, e.g. `[@knuth84; @lamport94]`.
+    Citation {
+        /// The keys of the cited entries, with their positions.
+        keys: Vec<(String, Position)>,
+    },
+
     /// A paragraph.
     ///
     /// It contains many elements but must be rendered on a single paragraph.
-    Paragraph(Vec<Ast>),
+    Paragraph(Vec<Node>),
 
     /// A group of content.
-    Group(Vec<Ast>),
+    Group(Vec<Node>),
+
+    /// A bulleted or numbered list.
+    List {
+        /// Whether the items of the list are numbered.
+        ordered: bool,
+
+        /// The number of the first item of the list, which is 1 if the list is not numbered.
+        start: usize,
+
+        /// The items of the list.
+        items: Vec<Node>,
+    },
+
+    /// An item of a list.
+    ListItem {
+        /// The content of the item.
+        content: Box<Node>,
+
+        /// The lists nested in the item.
+        children: Vec<Node>,
+    },
 
     /// An empty line.
     Newline,
 
+    /// A comment, e.g. `|| A note for the authors.`, which is not rendered but is kept so that
+    /// the source can be written back.
+    Comment(String),
+
+    /// An include directive.
+    ///
+    /// Directives are replaced by the content of the included files once the file is parsed.
+    Include {
+        /// The path of the included file, relative to the including file.
+        path: String,
+
+        /// The position of the directive.
+        position: Position,
+    },
+
+    /// The table of contents, written where the `!contents` directive is.
+    TableOfContents,
+
+    /// The list of the cited entries of the bibliography, written where the `!bibliography`
+    /// directive is.
+    Bibliography,
+
+    /// An image, with an optional caption.
+    Figure {
+        /// The path of the image, relative to the file containing the figure until the file is
+        /// loaded, and relative to the current directory afterwards.
+        path: String,
+
+        /// The width of the image, as a percentage of the width of the text.
+        width: Option<u32>,
+
+        /// The caption of the figure, which makes it numbered.
+        caption: Option<Box<Node>>,
+
+        /// The position of the figure.
+        position: Position,
+    },
+
+    /// A table, whose first row is a header.
+    Table {
+        /// The alignments of the columns.
+        alignments: Vec<Alignment>,
+
+        /// The cells of the header.
+        header: Vec<Node>,
+
+        /// The cells of the other rows.
+        rows: Vec<Vec<Node>>,
+
+        /// The caption of the table, which makes it numbered.
+        caption: Option<Box<Node>>,
+    },
+
     /// An error.
     ///
     /// Error will be stored in the abstract syntax tree so we can keep parsing what's parsable and
@@ -57,80 +303,179 @@ pub enum Ast {
 impl Ast {
     /// Returns all the errors contained in the ast.
     pub fn errors(&self) -> Vec<EmptyError> {
-        let mut errors = vec![];
+        /// Collects the errors of an ast.
+        struct Errors(Vec<EmptyError>);
 
-        match self {
-            Ast::Error(e) => errors.push(e.clone()),
+        impl Visit for Errors {
+            fn visit_error(&mut self, error: &EmptyError) {
+                self.0.push(error.clone());
+            }
+        }
 
-            Ast::Warning(_) => {}
+        let mut errors = Errors(vec![]);
+        errors.visit_ast(self);
+        errors.0
+    }
 
-            Ast::Group(children) => {
-                for child in children {
-                    errors.extend(child.errors());
-                }
-            }
+    /// Returns all the warnings contained in the ast.
+    pub fn warnings(&self) -> Vec<EmptyWarning> {
+        /// Collects the warnings of an ast.
+        struct Warnings(Vec<EmptyWarning>);
 
-            Ast::Paragraph(children) => {
-                for child in children {
-                    errors.extend(child.errors());
-                }
+        impl Visit for Warnings {
+            fn visit_warning(&mut self, warning: &EmptyWarning) {
+                self.0.push(warning.clone());
             }
+        }
 
-            Ast::Title { content: ast, .. } => {
-                errors.extend(ast.errors());
-            }
+        let mut warnings = Warnings(vec![]);
+        warnings.visit_ast(self);
+        warnings.0
+    }
 
-            Ast::Bold(ast) => {
-                errors.extend(ast.errors());
+    /// Returns the direct children of the ast.
+    pub fn children(&self) -> Vec<&Node> {
+        match self {
+            Ast::Group(children) | Ast::Paragraph(children) => children.iter().collect(),
+            Ast::List { items, .. } => items.iter().collect(),
+            Ast::ListItem { content, children } => {
+                let mut result = vec![&**content];
+                result.extend(children);
+                result
             }
-
-            Ast::Italic(ast) => {
-                errors.extend(ast.errors());
+            Ast::Title { content, .. }
+            | Ast::Bold(content)
+            | Ast::Italic(content)
+            | Ast::SmallCaps(content)
+            | Ast::Underline(content)
+            | Ast::Strikethrough(content)
+            | Ast::Superscript(content)
+            | Ast::Subscript(content)
+            | Ast::Footnote(content)
+            | Ast::Link { content, .. } => {
+                vec![&**content]
             }
-
-            Ast::Text(_) | Ast::Newline | Ast::InlineMath(_) => (),
+            Ast::Figure {
+                caption: Some(caption),
+                ..
+            } => vec![&**caption],
+            Ast::Table {
+                header,
+                rows,
+                caption,
+                ..
+            } => {
+                let mut result = vec![];
+                result.extend(caption.iter().map(|x| &**x));
+                result.extend(header);
+                result.extend(rows.iter().flatten());
+                result
+            }
+            _ => vec![],
         }
-
-        errors
     }
 
-    /// Returns all the warnings contained in the ast.
-    pub fn warnings(&self) -> Vec<EmptyWarning> {
-        let mut warnings = vec![];
-
+    /// Returns mutable references to the direct children of the ast.
+    pub fn children_mut(&mut self) -> Vec<&mut Node> {
         match self {
-            Ast::Warning(e) => warnings.push(e.clone()),
-
-            Ast::Error(_) => {}
-
-            Ast::Group(children) => {
-                for child in children {
-                    warnings.extend(child.warnings());
-                }
+            Ast::Group(children) | Ast::Paragraph(children) => children.iter_mut().collect(),
+            Ast::List { items, .. } => items.iter_mut().collect(),
+            Ast::ListItem { content, children } => {
+                let mut result = vec![&mut **content];
+                result.extend(children);
+                result
             }
-
-            Ast::Paragraph(children) => {
-                for child in children {
-                    warnings.extend(child.warnings());
-                }
+            Ast::Title { content, .. }
+            | Ast::Bold(content)
+            | Ast::Italic(content)
+            | Ast::SmallCaps(content)
+            | Ast::Underline(content)
+            | Ast::Strikethrough(content)
+            | Ast::Superscript(content)
+            | Ast::Subscript(content)
+            | Ast::Footnote(content)
+            | Ast::Link { content, .. } => {
+                vec![&mut **content]
             }
-
-            Ast::Title { content: ast, .. } => {
-                warnings.extend(ast.warnings());
+            Ast::Figure {
+                caption: Some(caption),
+                ..
+            } => vec![&mut **caption],
+            Ast::Table {
+                header,
+                rows,
+                caption,
+                ..
+            } => {
+                let mut result = vec![];
+                result.extend(caption.iter_mut().map(|x| &mut **x));
+                result.extend(header);
+                result.extend(rows.iter_mut().flatten());
+                result
             }
+            _ => vec![],
+        }
+    }
 
-            Ast::Bold(ast) => {
-                warnings.extend(ast.warnings());
-            }
+    /// Returns a copy of the ast whose nodes have default spans, e.g. to compare the structure of
+    /// two asts regardless of where they were parsed from.
+    pub fn without_spans(&self) -> Ast {
+        let mut ast = self.clone();
+        ast.clear_spans();
+        ast
+    }
 
-            Ast::Italic(ast) => {
-                warnings.extend(ast.warnings());
+    /// Resets the spans of the nodes of the ast to their default.
+    fn clear_spans(&mut self) {
+        for child in self.children_mut() {
+            child.span = SourceSpan::default();
+            child.ast.clear_spans();
+        }
+    }
+
+    /// Returns the names and the positions of all the labels contained in the ast.
+    pub fn labels(&self) -> Vec<(String, Position)> {
+        match self {
+            Ast::Label { name, position } => vec![(name.clone(), *position)],
+            _ => self
+                .children()
+                .into_iter()
+                .flat_map(|x| x.labels())
+                .collect(),
+        }
+    }
+
+    /// Returns the labels and the positions of all the references contained in the ast.
+    ///
+    /// Links to labels count as references.
+    pub fn references(&self) -> Vec<(String, Position)> {
+        let mut references = match self {
+            Ast::Reference {
+                label, position, ..
+            } => vec![(label.clone(), *position)],
+            Ast::Link { url, position, .. } if url.starts_with('#') => {
+                vec![(url[1..].to_owned(), *position)]
             }
+            _ => vec![],
+        };
 
-            Ast::Text(_) | Ast::Newline | Ast::InlineMath(_) => (),
+        for child in self.children() {
+            references.extend(child.references());
         }
 
-        warnings
+        references
+    }
+
+    /// Returns the keys and the positions of all the citations contained in the ast.
+    pub fn citations(&self) -> Vec<(String, Position)> {
+        match self {
+            Ast::Citation { keys } => keys.clone(),
+            _ => self
+                .children()
+                .into_iter()
+                .flat_map(|x| x.citations())
+                .collect(),
+        }
     }
 
     /// Pretty prints the ast.
@@ -140,7 +485,7 @@ impl Ast {
         indent: &str,
         last_child: bool,
     ) -> fmt::Result {
-        let delimiter1 = if indent == "" {
+        let delimiter1 = if indent.is_empty() {
             "─"
         } else if last_child {
             "└"
@@ -149,9 +494,23 @@ impl Ast {
         };
 
         let delimiter2 = match self {
-            Ast::Error(_) | Ast::Warning(_) | Ast::Text(_) | Ast::Newline | Ast::InlineMath(_) => {
-                "──"
-            }
+            Ast::Error(_)
+            | Ast::Warning(_)
+            | Ast::Text(_)
+            | Ast::Escaped(_)
+            | Ast::Newline
+            | Ast::Comment(_)
+            | Ast::InlineMath { .. }
+            | Ast::DisplayMath { .. }
+            | Ast::Code(_)
+            | Ast::CodeBlock { .. }
+            | Ast::Include { .. }
+            | Ast::TableOfContents
+            | Ast::Bibliography
+            | Ast::Figure { caption: None, .. }
+            | Ast::Label { .. }
+            | Ast::Reference { .. }
+            | Ast::Citation { .. } => "──",
             _ => "─┬",
         };
 
@@ -179,8 +538,46 @@ impl Ast {
                 &format!("{:?}", t).dimmed(),
                 ")".green()
             )?,
+            Ast::Escaped(t) => writeln!(
+                fmt,
+                "{}{}{}{}",
+                new_indent,
+                "Escaped(".green(),
+                &format!("{:?}", t).dimmed(),
+                ")".green()
+            )?,
+            Ast::Code(code) => writeln!(fmt, "{}Code({:?})", new_indent, code)?,
+            Ast::CodeBlock { language, content } => writeln!(
+                fmt,
+                "{}CodeBlock(language={:?}, {:?})",
+                new_indent, language, content
+            )?,
             Ast::Newline => writeln!(fmt, "{}NewLine", new_indent)?,
-            Ast::InlineMath(math) => writeln!(fmt, "{}Math({:?})", new_indent, math)?,
+            Ast::Comment(comment) => writeln!(
+                fmt,
+                "{}{}",
+                new_indent,
+                &format!("Comment({:?})", comment).dimmed()
+            )?,
+            Ast::InlineMath { content, .. } => writeln!(fmt, "{}Math({:?})", new_indent, content)?,
+            Ast::Include { path, .. } => writeln!(fmt, "{}Include({:?})", new_indent, path)?,
+            Ast::TableOfContents => writeln!(fmt, "{}TableOfContents", new_indent)?,
+            Ast::Bibliography => writeln!(fmt, "{}Bibliography", new_indent)?,
+            Ast::Label { name, .. } => writeln!(fmt, "{}Label({:?})", new_indent, name)?,
+            Ast::Reference { label, page, .. } => {
+                writeln!(fmt, "{}Reference(page={}, {:?})", new_indent, page, label)?
+            }
+            Ast::Citation { keys } => {
+                let keys = keys.iter().map(|x| &x.0).collect::<Vec<_>>();
+                writeln!(fmt, "{}Citation({:?})", new_indent, keys)?
+            }
+            Ast::DisplayMath {
+                content, numbered, ..
+            } => writeln!(
+                fmt,
+                "{}DisplayMath(numbered={}, {:?})",
+                new_indent, numbered, content
+            )?,
 
             Ast::Group(children) => {
                 writeln!(fmt, "{}{}", new_indent, "Group".blue().bold())?;
@@ -208,6 +605,34 @@ impl Ast {
                 content.print_debug(fmt, &indent, true)?;
             }
 
+            Ast::List {
+                ordered,
+                start,
+                items,
+            } => {
+                writeln!(
+                    fmt,
+                    "{}{}",
+                    new_indent,
+                    &format!("List(ordered={}, start={})", ordered, start)
+                        .blue()
+                        .bold()
+                )?;
+                let len = items.len();
+                for (index, item) in items.iter().enumerate() {
+                    item.print_debug(fmt, &indent, index == len - 1)?;
+                }
+            }
+
+            Ast::ListItem { content, children } => {
+                writeln!(fmt, "{}{}", new_indent, "ListItem".blue().bold())?;
+                content.print_debug(fmt, &indent, children.is_empty())?;
+                let len = children.len();
+                for (index, child) in children.iter().enumerate() {
+                    child.print_debug(fmt, &indent, index == len - 1)?;
+                }
+            }
+
             Ast::Bold(ast) => {
                 writeln!(fmt, "{}{}", new_indent, "Bold".cyan().bold())?;
                 ast.print_debug(fmt, &indent, true)?;
@@ -217,6 +642,81 @@ impl Ast {
                 writeln!(fmt, "{}{}", new_indent, "Italic".cyan().bold())?;
                 ast.print_debug(fmt, &indent, true)?;
             }
+
+            Ast::SmallCaps(ast) => {
+                writeln!(fmt, "{}{}", new_indent, "SmallCaps".cyan().bold())?;
+                ast.print_debug(fmt, &indent, true)?;
+            }
+
+            Ast::Underline(ast) => {
+                writeln!(fmt, "{}{}", new_indent, "Underline".cyan().bold())?;
+                ast.print_debug(fmt, &indent, true)?;
+            }
+
+            Ast::Strikethrough(ast) => {
+                writeln!(fmt, "{}{}", new_indent, "Strikethrough".cyan().bold())?;
+                ast.print_debug(fmt, &indent, true)?;
+            }
+
+            Ast::Superscript(ast) => {
+                writeln!(fmt, "{}{}", new_indent, "Superscript".cyan().bold())?;
+                ast.print_debug(fmt, &indent, true)?;
+            }
+
+            Ast::Subscript(ast) => {
+                writeln!(fmt, "{}{}", new_indent, "Subscript".cyan().bold())?;
+                ast.print_debug(fmt, &indent, true)?;
+            }
+
+            Ast::Footnote(ast) => {
+                writeln!(fmt, "{}{}", new_indent, "Footnote".cyan().bold())?;
+                ast.print_debug(fmt, &indent, true)?;
+            }
+
+            Ast::Link { url, content, .. } => {
+                writeln!(fmt, "{}{}({:?})", new_indent, "Link".cyan().bold(), url)?;
+                content.print_debug(fmt, &indent, true)?;
+            }
+
+            Ast::Figure {
+                path,
+                width,
+                caption,
+                ..
+            } => {
+                writeln!(
+                    fmt,
+                    "{}{}",
+                    new_indent,
+                    &format!("Figure(width={:?}, {:?})", width, path)
+                        .magenta()
+                        .bold()
+                )?;
+                if let Some(caption) = caption {
+                    caption.print_debug(fmt, &indent, true)?;
+                }
+            }
+
+            Ast::Table {
+                alignments,
+                header,
+                rows,
+                caption,
+            } => {
+                writeln!(
+                    fmt,
+                    "{}{}",
+                    new_indent,
+                    &format!("Table({:?})", alignments).magenta().bold()
+                )?;
+                if let Some(caption) = caption {
+                    caption.print_debug(fmt, &indent, false)?;
+                }
+                let len = rows.len() + 1;
+                for (index, row) in Some(header).into_iter().chain(rows).enumerate() {
+                    Ast::Group(row.clone()).print_debug(fmt, &indent, index == len - 1)?;
+                }
+            }
         }
 
         Ok(())
@@ -235,8 +735,85 @@ impl fmt::Display for Ast {
 
             Ast::Bold(subast) => write!(fmt, "{}", &format!("{}", subast).red())?,
             Ast::Italic(subast) => write!(fmt, "{}", &format!("{}", subast).blue())?,
-            Ast::InlineMath(content) => write!(fmt, "${}$", content)?,
+            Ast::SmallCaps(subast) => write!(fmt, "[{}]{{.smallcaps}}", subast)?,
+            Ast::Underline(subast) => write!(fmt, "[{}]{{.underline}}", subast)?,
+            Ast::Strikethrough(subast) => write!(fmt, "[{}]{{.strikethrough}}", subast)?,
+            Ast::Superscript(subast) => write!(fmt, "[{}]{{.superscript}}", subast)?,
+            Ast::Subscript(subast) => write!(fmt, "[{}]{{.subscript}}", subast)?,
+            Ast::Footnote(subast) => write!(fmt, "^[{}]", subast)?,
+            Ast::Link { url, content, .. } => write!(fmt, "[{}]({})", content, url)?,
+            Ast::InlineMath { content, .. } => write!(fmt, "${}$", content)?,
+            Ast::DisplayMath {
+                content, numbered, ..
+            } => writeln!(fmt, "$${}{}$$", if *numbered { "" } else { "*" }, content)?,
+            Ast::Include { path, .. } => writeln!(fmt, "!include {}", path)?,
+            Ast::TableOfContents => writeln!(fmt, "!contents")?,
+            Ast::Bibliography => writeln!(fmt, "!bibliography")?,
+            Ast::Figure {
+                path,
+                width,
+                caption,
+                ..
+            } => {
+                write!(fmt, "!image {}", path)?;
+                if let Some(width) = width {
+                    write!(fmt, " {}%", width)?;
+                }
+                writeln!(fmt)?;
+                if let Some(caption) = caption {
+                    writeln!(fmt, "{}", caption)?;
+                }
+            }
+            Ast::Table {
+                alignments,
+                header,
+                rows,
+                caption,
+            } => {
+                let row = |fmt: &mut fmt::Formatter, cells: &[Node]| {
+                    for cell in cells {
+                        write!(fmt, "| {} ", cell)?;
+                    }
+                    writeln!(fmt, "|")
+                };
+
+                row(fmt, header)?;
+                for alignment in alignments {
+                    match alignment {
+                        Alignment::Left => write!(fmt, "|---")?,
+                        Alignment::Center => write!(fmt, "|:-:")?,
+                        Alignment::Right => write!(fmt, "|--:")?,
+                    }
+                }
+                writeln!(fmt, "|")?;
+                for cells in rows {
+                    row(fmt, cells)?;
+                }
+                if let Some(caption) = caption {
+                    writeln!(fmt, "{}", caption)?;
+                }
+            }
+            Ast::Label { name, .. } => write!(fmt, "{{#{}}}", name)?,
+            Ast::Reference { label, page, .. } => {
+                write!(fmt, "{{@{}{}}}", if *page { "page:" } else { "" }, label)?
+            }
+            Ast::Citation { keys } => {
+                let keys = keys.iter().map(|x| format!("@{}", x.0)).collect::<Vec<_>>();
+                write!(fmt, "[{}]", keys.join("; "))?
+            }
             Ast::Text(content) => write!(fmt, "{}", content)?,
+            Ast::Escaped(content) => {
+                for c in content.chars() {
+                    write!(fmt, "\\{}", c)?;
+                }
+            }
+            Ast::Code(content) => write!(fmt, "`{}`", content)?,
+            Ast::CodeBlock { language, content } => writeln!(
+                fmt,
+                "```{}\n{}\n```",
+                language.as_deref().unwrap_or(""),
+                content
+            )?,
             Ast::Group(children) => {
                 for child in children {
                     write!(fmt, "{}", child)?;
@@ -247,9 +824,29 @@ impl fmt::Display for Ast {
                     write!(fmt, "{}", child)?;
                 }
             }
+            Ast::List {
+                ordered,
+                start,
+                items,
+            } => {
+                for (index, item) in items.iter().enumerate() {
+                    if *ordered {
+                        write!(fmt, "{}. {}", start.saturating_add(index), item)?;
+                    } else {
+                        write!(fmt, "- {}", item)?;
+                    }
+                }
+            }
+            Ast::ListItem { content, children } => {
+                writeln!(fmt, "{}", content)?;
+                for child in children {
+                    write!(fmt, "{}", child)?;
+                }
+            }
 
             Ast::Error(_) => writeln!(fmt, "?")?,
             Ast::Newline => writeln!(fmt)?,
+            Ast::Comment(comment) => writeln!(fmt, "|| {}", comment)?,
             Ast::Warning(_) => (),
         }
         Ok(())
@@ -4,18 +4,43 @@
 #![allow(missing_docs)]
 // Allow redundant closure because of nom.
 #![allow(clippy::redundant_closure)]
+// Doc comments on nom macros are not rendered by rustdoc.
+#![allow(unused_doc_comments)]
 
 use nom::*;
 
 use crate::ligature::ligature;
-use crate::parser::ast::Ast;
+use crate::math::parser::parse as parse_formula;
+use crate::parser::ast::{Alignment, Ast, Node};
 use crate::parser::error::{EmptyError, ErrorType};
+use crate::parser::metadata::Metadata;
 use crate::parser::warning::{EmptyWarning, WarningType};
-use crate::parser::{position, Span};
+use crate::parser::{position, source_span, Position, SourceSpan, Span};
 
 /// Returns true if the character passed as parameter changes the type of parsing we're going to do.
 pub fn should_stop(c: char) -> bool {
-    c == '*' || c == '/' || c == '$' || c == '|'
+    c == '*'
+        || c == '/'
+        || c == '$'
+        || c == '|'
+        || c == '{'
+        || c == '^'
+        || c == '['
+        || c == ']'
+        || c == '<'
+        || c == '`'
+        || c == '\\'
+}
+
+/// Returns true if the character has a meaning in the dex format, and can be escaped with a
+/// backslash to be written literally.
+pub fn is_escapable(c: char) -> bool {
+    should_stop(c) || "}>#-!~\"'.".contains(c)
+}
+
+/// Returns true if the character can be part of the name of a label.
+pub fn is_label_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || c == ':' || c == '.'
 }
 
 /// Creates an error.
@@ -34,61 +59,454 @@ pub fn warning(span: Span, ty: WarningType) -> Ast {
     })
 }
 
-/// Parses some bold content.
-named!(pub parse_bold<Span, Ast>,
-    map!(
-        map_res!(preceded!(tag!("*"), take_until_and_consume!("*")), parse_group),
-        { |(_,x)| Ast::Bold(Box::new(x)) }
+/// Parses some content, and returns it as a node that knows the part of the input it comes from.
+pub fn node<'a, F>(input: Span<'a>, parser: F) -> IResult<Span<'a>, Node>
+where
+    F: Fn(Span<'a>) -> IResult<Span<'a>, Ast>,
+{
+    let (rest, ast) = parser(input)?;
+    Ok((rest, Node::new(ast, source_span(&input, &rest))))
+}
+
+/// Creates a node that comes from a whole span.
+pub fn spanned(span: Span, ast: Ast) -> Node {
+    Node::new(
+        ast,
+        source_span(&span, &span.slice(span.fragment.0.len()..)),
+    )
+}
+
+/// Creates the text of an escaped character, or a warning followed by the character if it
+/// didn't need to be escaped.
+pub fn escape(backslash: Span, character: Span) -> Ast {
+    match character.fragment.0.chars().next() {
+        Some(c) if is_escapable(c) => Ast::Escaped(character.fragment.0.into()),
+        _ => Ast::Group(vec![
+            spanned(backslash, warning(backslash, WarningType::UnknownEscape)),
+            spanned(character, Ast::Text(character.fragment.0.into())),
+        ]),
+    }
+}
+
+/// Parses an escaped character, e.g. `\*`.
+named!(pub parse_escape<Span, Ast>,
+    do_parse!(
+        backslash: tag!("\\") >>
+        character: take!(1) >>
+        (escape(backslash, character))
     )
 );
 
-/// Parses some italic content.
-named!(pub parse_italic<Span, Ast>,
+/// Creates an inline math, or the errors of the formula if it is invalid.
+pub fn inline_math(span: Span) -> Ast {
+    let formula = parse_formula(span);
+    let errors = formula.errors();
+
+    if errors.is_empty() {
+        Ast::InlineMath {
+            content: span.fragment.0.into(),
+            formula,
+        }
+    } else {
+        Ast::Group(
+            errors
+                .into_iter()
+                .map(|x| spanned(span, Ast::Error(x)))
+                .collect(),
+        )
+    }
+}
+
+/// Creates a display math, or the errors of the formula if it is invalid.
+pub fn display_math(span: Span, numbered: bool) -> Ast {
+    let formula = parse_formula(span);
+    let errors = formula.errors();
+
+    if errors.is_empty() {
+        Ast::DisplayMath {
+            content: span.fragment.0.trim().into(),
+            formula,
+            numbered,
+        }
+    } else {
+        Ast::Group(
+            errors
+                .into_iter()
+                .map(|x| spanned(span, Ast::Error(x)))
+                .collect(),
+        )
+    }
+}
+
+/// Parses some inline code, whose content is kept verbatim.
+named!(pub parse_code<Span, Ast>,
     map!(
-        map_res!(preceded!(tag!("/"), take_until_and_consume!("/")), parse_group),
-        { |(_,x)| Ast::Italic(Box::new(x)) }
+        preceded!(tag!("`"), take_until_and_consume!("`")),
+        |x| Ast::Code(x.fragment.0.into())
     )
 );
 
 /// Parses some math inline math.
 named!(pub parse_inline_math<Span, Ast>,
-    map!(preceded!(tag!("$"), take_until_and_consume!("$")), { |x: Span| Ast::InlineMath(x.fragment.0.into())} )
+    map!(preceded!(tag!("$"), take_until_and_consume!("$")), inline_math)
+);
+
+/// Parses the url that follows the content of a link, e.g. `(https://rust-spandex.github.io)`,
+/// or `(#intro)` for a link to a label.
+named!(pub parse_link_url<Span, Span>,
+    preceded!(tag!("("), take_until_and_consume!(")"))
+);
+
+/// Parses a citation of entries of the bibliography, e.g. `[@knuth84]` or
+/// `[@knuth84; @lamport94]`.
+named!(pub parse_citation<Span, Ast>,
+    do_parse!(
+        tag!("[@") >>
+        keys: separated_nonempty_list!(
+            tuple!(
+                take_while!(char::is_whitespace),
+                tag!(";"),
+                take_while!(char::is_whitespace),
+                tag!("@")
+            ),
+            take_while1!(is_label_char)
+        ) >>
+        take_while!(char::is_whitespace) >>
+        tag!("]") >>
+        (Ast::Citation {
+            keys: keys.into_iter().map(|x| (x.fragment.0.into(), position(&x))).collect(),
+        })
+    )
+);
+
+/// Creates a span of text in a style, or an error if the style does not exist.
+pub fn styled_span(content: Node, style: Span) -> Ast {
+    let content = Box::new(content);
+
+    match style.fragment.0 {
+        "smallcaps" => Ast::SmallCaps(content),
+        "underline" => Ast::Underline(content),
+        "strikethrough" => Ast::Strikethrough(content),
+        "superscript" => Ast::Superscript(content),
+        "subscript" => Ast::Subscript(content),
+        _ => error(style, ErrorType::UnknownStyle),
+    }
+}
+
+/// Parses the style that follows the content of a span, e.g. `{.smallcaps}`.
+named!(pub parse_span_style<Span, Span>,
+    delimited!(tag!("{."), take_while1!(char::is_alphanumeric), tag!("}"))
+);
+
+/// Parses an automatic link, e.g. `<https://rust-spandex.github.io>`, whose text is its url.
+named!(pub parse_autolink<Span, Ast>,
+    do_parse!(
+        start: tag!("<") >>
+        url: verify!(
+            take_till1!(|x: char| x == '>' || x.is_whitespace()),
+            |x: Span| x.fragment.0.contains(':')
+        ) >>
+        tag!(">") >>
+        (Ast::Link {
+            url: url.fragment.0.into(),
+            content: Box::new(spanned(url, Ast::Text(url.fragment.0.into()))),
+            position: position(&start),
+        })
+    )
 );
 
 /// Parses a styled element.
 named!(pub parse_styled<Span, Ast>,
     alt!(
-        parse_bold | parse_italic | parse_inline_math
+        parse_code
+            | parse_inline_math
+            | parse_citation
+            | parse_autolink
     )
 );
 
-/// Parses a comment.
+/// Parses a label, e.g. `{#intro}`.
+named!(pub parse_label<Span, Ast>,
+    do_parse!(
+        start: tag!("{#") >>
+        name: take_while1!(is_label_char) >>
+        tag!("}") >>
+        (Ast::Label {
+            name: name.fragment.0.into(),
+            position: position(&start),
+        })
+    )
+);
+
+/// Parses a reference, e.g. `{@intro}`, or `{@page:intro}` for the page of the label.
+named!(pub parse_reference<Span, Ast>,
+    do_parse!(
+        start: tag!("{@") >>
+        page: opt!(tag!("page:")) >>
+        label: take_while1!(is_label_char) >>
+        tag!("}") >>
+        (Ast::Reference {
+            label: label.fragment.0.into(),
+            page: page.is_some(),
+            position: position(&start),
+        })
+    )
+);
+
+/// Parses a comment, e.g. `|| A note for the authors.`, up to the end of its line.
 named!(pub parse_comment<Span, Ast>,
-    map!(preceded!(tag!("||"), alt!(take_until_and_consume!("\n") | call!(rest))), { |_|  Ast::Newline })
+    map!(
+        preceded!(tag!("||"), alt!(take_until_and_consume!("\n") | call!(rest))),
+        |x| Ast::Comment(x.fragment.0.trim().into())
+    )
 );
 
 /// Parses some multiline inline content.
 named!(pub parse_any<Span, Ast>,
     alt!(
-        tag!("**") => { |x| warning(x, WarningType::ConsecutiveStars) }
+        parse_escape
+        | tag!("\\") => { |_| { Ast::Text(String::from("\\")) } }
         | parse_comment
         | parse_styled
-        | tag!("*") => { |x| error(x, ErrorType::UnmatchedStar) }
-        | tag!("/") => { |x| error(x, ErrorType::UnmatchedSlash) }
         | tag!("$") => { |x| error(x, ErrorType::UnmatchedDollar) }
+        | tag!("`") => { |x| error(x, ErrorType::UnmatchedBacktick) }
+        | tag!("^") => { |_| { Ast::Text(String::from("^")) } }
         | tag!("|") => { |_| { Ast::Text(String::from("|")) } }
+        | parse_label
+        | parse_reference
+        | tag!("{") => { |_| { Ast::Text(String::from("{")) } }
+        | tag!("<") => { |_| { Ast::Text(String::from("<")) } }
         | take_till!(should_stop) => { |x: Span| { Ast::Text(ligature(x.fragment.0)) } }
     )
 );
 
+/// A content whose closing delimiter has not been found yet.
+struct OpenStyle<'a> {
+    /// The delimiter that opened the content, `*` for bold, `/` for italic, `^[` for a footnote,
+    /// and `[` for a link, a span or some text between brackets.
+    delimiter: Span<'a>,
+
+    /// The children of the content parsed so far.
+    children: Vec<Node>,
+}
+
+impl<'a> OpenStyle<'a> {
+    /// Returns true if the content was opened by a delimiter.
+    fn is_opened_by(&self, delimiter: char) -> bool {
+        self.delimiter.fragment.0.starts_with(delimiter)
+    }
+
+    /// Returns true if the content is closed by a bracket.
+    fn is_bracket(&self) -> bool {
+        self.delimiter.fragment.0.ends_with('[')
+    }
+
+    /// Returns the group of the children, given the delimiter that closes them.
+    fn content(self, closing: Span<'a>) -> Box<Node> {
+        let start = self.delimiter.slice(self.delimiter.fragment.0.len()..);
+        Box::new(Node::new(
+            Ast::Group(self.children),
+            source_span(&start, &closing),
+        ))
+    }
+
+    /// Returns the content, now that its closing delimiter is found, given the delimiter and the
+    /// input after it.
+    fn close(self, closing: Span<'a>, rest: Span<'a>) -> Node {
+        let delimiter = self.delimiter;
+        let bold = self.is_opened_by('*');
+        let content = self.content(closing);
+
+        let ast = if bold {
+            Ast::Bold(content)
+        } else {
+            Ast::Italic(content)
+        };
+
+        Node::new(ast, source_span(&delimiter, &rest))
+    }
+
+    /// Returns the content, now that its closing bracket is found, given the bracket and the
+    /// input after it, as well as the input that remains after the url of a link or the style of
+    /// a span.
+    ///
+    /// A bracket that is neither a footnote, a link nor a span is some text between brackets.
+    fn close_bracket(self, closing: Span<'a>, rest: Span<'a>) -> (Vec<Node>, Span<'a>) {
+        let delimiter = self.delimiter;
+
+        if self.is_opened_by('^') {
+            let ast = Ast::Footnote(self.content(closing));
+            return (vec![Node::new(ast, source_span(&delimiter, &rest))], rest);
+        }
+
+        if let Ok((after, url)) = parse_link_url(rest) {
+            let ast = Ast::Link {
+                url: url.fragment.0.trim().into(),
+                content: self.content(closing),
+                position: position(&delimiter),
+            };
+            return (vec![Node::new(ast, source_span(&delimiter, &after))], after);
+        }
+
+        if let Ok((after, style)) = parse_span_style(rest) {
+            let ast = styled_span(*self.content(closing), style);
+            return (vec![Node::new(ast, source_span(&delimiter, &after))], after);
+        }
+
+        let mut result = self.unclosed();
+        result.push(spanned(closing, Ast::Text(String::from("]"))));
+        (result, rest)
+    }
+
+    /// Returns the error of the delimiter that is never closed, followed by the children.
+    ///
+    /// A bracket that is never closed is not an error, since it may just be some text.
+    fn unclosed(self) -> Vec<Node> {
+        let ast = match self.delimiter.fragment.0 {
+            "*" => error(self.delimiter, ErrorType::UnmatchedStar),
+            "/" => error(self.delimiter, ErrorType::UnmatchedSlash),
+            "^[" => error(self.delimiter, ErrorType::UnmatchedFootnote),
+            _ => Ast::Text(String::from("[")),
+        };
+
+        let mut result = vec![spanned(self.delimiter, ast)];
+        result.extend(self.children);
+        result
+    }
+}
+
+/// Parses some inline content, in which bold, italic, footnotes, links and spans can be nested in
+/// any order and at any depth, e.g. `*bold /italic ^[a note with a [link](#intro)] italic/ bold*`.
+///
+/// A star or a slash closes the innermost content if it opened it. Otherwise it opens a new
+/// content, unless it is followed by a whitespace and closes an outer content. A closing bracket
+/// closes the innermost footnote, link or span. In both cases, the contents opened in between are
+/// reported as unclosed, so that only the innermost delimiter that is never closed is reported.
+pub fn parse_inline(input: Span) -> IResult<Span, Vec<Node>> {
+    let mut root = vec![];
+    let mut stack: Vec<OpenStyle> = vec![];
+    let mut input = input;
+
+    // Returns the children of the innermost open content.
+    fn current<'a, 'b>(
+        stack: &'b mut [OpenStyle<'a>],
+        root: &'b mut Vec<Node>,
+    ) -> &'b mut Vec<Node> {
+        match stack.last_mut() {
+            Some(style) => &mut style.children,
+            None => root,
+        }
+    }
+
+    // Closes the content at an index of the stack, after the contents opened in between.
+    fn close_at<'a>(stack: &mut Vec<OpenStyle<'a>>, index: usize) -> Option<OpenStyle<'a>> {
+        let unclosed = stack.split_off(index + 1);
+        let mut style = stack.pop()?;
+
+        for inner in unclosed {
+            style.children.extend(inner.unclosed());
+        }
+
+        Some(style)
+    }
+
+    while let Some(c) = input.fragment.0.chars().next() {
+        if input.fragment.0.starts_with("**") {
+            let stars = input.slice(..2);
+            let ast = warning(stars, WarningType::ConsecutiveStars);
+            current(&mut stack, &mut root).push(spanned(stars, ast));
+            input = input.slice(2..);
+            continue;
+        }
+
+        if c == '*' || c == '/' {
+            let delimiter = input.slice(..1);
+            input = input.slice(1..);
+
+            let can_open = match input.fragment.0.chars().next() {
+                Some(next) => !next.is_whitespace(),
+                None => false,
+            };
+
+            let outer = stack.iter().rposition(|x| x.is_opened_by(c));
+
+            match outer {
+                Some(index) if index == stack.len() - 1 || !can_open => {
+                    if let Some(style) = close_at(&mut stack, index) {
+                        current(&mut stack, &mut root).push(style.close(delimiter, input));
+                    }
+                }
+                _ => stack.push(OpenStyle {
+                    delimiter,
+                    children: vec![],
+                }),
+            }
+
+            continue;
+        }
+
+        let opening = if input.fragment.0.starts_with("^[") {
+            Some(2)
+        } else if c == '[' && parse_citation(input).is_err() {
+            Some(1)
+        } else {
+            None
+        };
+
+        if let Some(length) = opening {
+            stack.push(OpenStyle {
+                delimiter: input.slice(..length),
+                children: vec![],
+            });
+            input = input.slice(length..);
+            continue;
+        }
+
+        if c == ']' {
+            let closing = input.slice(..1);
+            input = input.slice(1..);
+
+            match stack.iter().rposition(OpenStyle::is_bracket) {
+                Some(index) => {
+                    if let Some(style) = close_at(&mut stack, index) {
+                        let (nodes, rest) = style.close_bracket(closing, input);
+                        current(&mut stack, &mut root).extend(nodes);
+                        input = rest;
+                    }
+                }
+                None => {
+                    let ast = Ast::Text(String::from("]"));
+                    current(&mut stack, &mut root).push(spanned(closing, ast));
+                }
+            }
+
+            continue;
+        }
+
+        match node(input, parse_any) {
+            Ok((rest, node)) if rest.offset > input.offset => {
+                current(&mut stack, &mut root).push(node);
+                input = rest;
+            }
+            _ => break,
+        }
+    }
+
+    for style in stack {
+        root.extend(style.unclosed());
+    }
+
+    Ok((input, root))
+}
+
 /// Parses some text content.
 named!(pub parse_group<Span, Ast>,
-    map!(many0!(parse_any), Ast::Group)
+    map!(parse_inline, Ast::Group)
 );
 
 /// Parses a paragraph of text content.
 named!(pub parse_paragraph<Span, Ast>,
-    map!(many0!(parse_any), Ast::Paragraph)
+    map!(parse_inline, Ast::Paragraph)
 );
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -99,7 +517,7 @@ named!(pub parse_paragraph<Span, Ast>,
 named!(pub parse_line<Span, Ast>,
     alt!(
         map!(preceded!(take_until_and_consume!("\n"), take!(0)), |x| { error(x, ErrorType::MultipleLinesTitle) })
-        | map!(many0!(parse_any), Ast::Group)
+        | map!(parse_inline, Ast::Group)
     )
 );
 
@@ -111,44 +529,896 @@ named!(pub parse_title_level<Span, usize>,
     )
 );
 
+/// The deepest level of title, written with six hashes.
+pub const MAX_TITLE_LEVEL: usize = 6;
+
 /// Parses a whole title.
 named!(pub parse_title<Span, Ast>,
     do_parse!(
+        hashes: peek!(take_while!(|x| x == '#')) >>
         level: parse_title_level >>
-        content: parse_line >> ({
-            Ast::Title {
-                level: (level - 1) as u8,
-                content: Box::new(content)
+        content: call!(node, parse_line) >> ({
+            if level > MAX_TITLE_LEVEL {
+                error(hashes, ErrorType::InvalidTitleLevel)
+            } else {
+                Ast::Title {
+                    level: (level - 1) as u8,
+                    content: Box::new(content)
+                }
+            }
+        })
+    )
+);
+
+////////////////////////////////////////////////////////////////////////////////
+// For lists
+////////////////////////////////////////////////////////////////////////////////
+
+/// Parses the indentation at the beginning of a line.
+named!(pub parse_indentation<Span, usize>,
+    map!(take_while!(|x| x == ' ' || x == '\t'), |x| x.fragment.0.chars().count())
+);
+
+/// The marker of a list item.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ListMarker {
+    /// A bullet, `-`.
+    Bullet,
+
+    /// A number followed by a dot, e.g. `1.`.
+    Number(usize),
+}
+
+impl ListMarker {
+    /// Returns whether the marker numbers its item.
+    pub fn is_number(self) -> bool {
+        matches!(self, ListMarker::Number(_))
+    }
+}
+
+/// Parses the marker of a bulleted list item.
+named!(pub parse_bullet_marker<Span, ListMarker>,
+    map!(terminated!(tag!("-"), take_while1!(|x| x == ' ')), |_| ListMarker::Bullet)
+);
+
+/// Parses the marker of a numbered list item.
+named!(pub parse_number_marker<Span, ListMarker>,
+    map_res!(
+        terminated!(
+            terminated!(take_while1!(|x: char| x.is_ascii_digit()), tag!(".")),
+            take_while1!(|x| x == ' ')
+        ),
+        |x: Span| x.fragment.0.parse().map(ListMarker::Number)
+    )
+);
+
+/// Parses the marker of a list item.
+named!(pub parse_list_marker<Span, ListMarker>,
+    alt!(parse_bullet_marker | parse_number_marker)
+);
+
+/// A line of a list bloc.
+struct ListLine<'a> {
+    /// The whole line, used to locate errors.
+    line: Span<'a>,
+
+    /// The indentation of the line.
+    indent: usize,
+
+    /// The marker of the item that the line starts, or None if it continues the previous item.
+    marker: Option<ListMarker>,
+
+    /// The content of the line, after its indentation and marker.
+    content: Span<'a>,
+}
+
+/// A list item that is being built.
+struct PendingItem {
+    /// The position of the beginning of the line of the marker of the item.
+    start: Position,
+
+    /// The inline content of each line of the item.
+    lines: Vec<Node>,
+
+    /// The nested lists of the item.
+    children: Vec<Node>,
+
+    /// The errors of the line of the marker of the item, e.g. an unmatched indentation.
+    errors: Vec<Node>,
+}
+
+impl PendingItem {
+    /// Finalizes the item, joining its lines with spaces.
+    fn into_node(self) -> Node {
+        let span = SourceSpan {
+            start: self.lines[0].span.start,
+            end: self.lines[self.lines.len() - 1].span.end,
+        };
+
+        let end = match self.children.last() {
+            Some(child) => child.span.end,
+            None => span.end,
+        };
+
+        let mut content = self.errors;
+        for (index, line) in self.lines.into_iter().enumerate() {
+            if index > 0 {
+                content.push(Node::from(Ast::Text(String::from(" "))));
+            }
+            content.push(line);
+        }
+
+        let item = Ast::ListItem {
+            content: Box::new(Node::new(Ast::Group(content), span)),
+            children: self.children,
+        };
+
+        Node::new(
+            item,
+            SourceSpan {
+                start: self.start,
+                end,
+            },
+        )
+    }
+}
+
+/// Splits a bloc into the non empty lines of a list.
+fn list_lines(input: Span) -> Vec<ListLine> {
+    let mut lines = vec![];
+    let mut start = 0;
+
+    loop {
+        let end = match input.fragment.0[start..].find('\n') {
+            Some(i) => start + i,
+            None => input.fragment.0.len(),
+        };
+
+        let line = input.slice(start..end);
+        if !line.fragment.0.trim().is_empty() {
+            let (after_indent, indent) = match parse_indentation(line) {
+                Ok(x) => x,
+                Err(_) => (line, 0),
+            };
+
+            let (content, marker) = match parse_list_marker(after_indent) {
+                Ok((content, marker)) => (content, Some(marker)),
+                Err(_) => (after_indent, None),
+            };
+
+            lines.push(ListLine {
+                line,
+                indent,
+                marker,
+                content,
+            });
+        }
+
+        if end == input.fragment.0.len() {
+            break;
+        }
+
+        start = end + 1;
+    }
+
+    lines
+}
+
+/// Parses some inline content, e.g. a line of a list or a cell of a table.
+fn parse_content(content: Span) -> Node {
+    match node(content, parse_group) {
+        Ok((_, node)) => node,
+        // many0 cannot fail on complete input.
+        Err(_) => spanned(content, Ast::Group(vec![])),
+    }
+}
+
+/// Builds the list whose first item is the line at index start.
+///
+/// A list is either bulleted or numbered, so an item with the other kind of marker ends it. Returns
+/// the list and the index of the first line that does not belong to it.
+fn build_list(lines: &[ListLine], start: usize, nested: bool) -> (Node, usize) {
+    let indent = lines[start].indent;
+    let first = lines[start].marker.unwrap_or(ListMarker::Bullet);
+    let mut items: Vec<Node> = vec![];
+    let mut current: Option<PendingItem> = None;
+    let mut nested_indent = None;
+    let mut index = start;
+
+    while index < lines.len() {
+        let line = &lines[index];
+
+        let marker = match line.marker {
+            Some(marker) => marker,
+            None => {
+                // The line continues the current item.
+                if let Some(item) = current.as_mut() {
+                    item.lines.push(parse_content(line.content));
+                }
+                index += 1;
+                continue;
+            }
+        };
+
+        if line.indent < indent && nested {
+            break;
+        }
+
+        if line.indent == indent && marker.is_number() != first.is_number() {
+            break;
+        }
+
+        if line.indent > indent {
+            // A nested list, or the next one if the previous nested list ended with a marker of
+            // the other kind.
+            let can_nest = match &current {
+                Some(item) => item.children.is_empty() || nested_indent == Some(line.indent),
+                None => false,
+            };
+
+            if can_nest {
+                let (sublist, next) = build_list(lines, index, true);
+                if let Some(item) = current.as_mut() {
+                    item.children.push(sublist);
+                }
+                nested_indent = Some(line.indent);
+                index = next;
+                continue;
             }
+        }
+
+        if let Some(item) = current.take() {
+            items.push(item.into_node());
+        }
+
+        // This line matches neither the current list nor the enclosing one, we report it and
+        // consider it as an item of the current list.
+        let errors = if line.indent != indent {
+            let error = error(line.line, ErrorType::UnmatchedIndentation);
+            vec![spanned(line.line, error)]
+        } else {
+            vec![]
+        };
+
+        current = Some(PendingItem {
+            start: position(&line.line),
+            lines: vec![parse_content(line.content)],
+            children: vec![],
+            errors,
+        });
+
+        nested_indent = None;
+        index += 1;
+    }
+
+    if let Some(item) = current.take() {
+        items.push(item.into_node());
+    }
+
+    let span = SourceSpan {
+        start: items[0].span.start,
+        end: items[items.len() - 1].span.end,
+    };
+
+    let list = Ast::List {
+        ordered: first.is_number(),
+        start: match first {
+            ListMarker::Number(number) => number,
+            ListMarker::Bullet => 1,
+        },
+        items,
+    };
+
+    (Node::new(list, span), index)
+}
+
+/// Parses a bloc containing a list.
+///
+/// Items start with `-` or with a number followed by `.`, and are nested by indentation. A bloc
+/// whose items change from bullets to numbers or the other way around contains several lists.
+pub fn parse_list(input: Span) -> IResult<Span, Ast> {
+    let lines = list_lines(input);
+
+    match lines.first() {
+        Some(ListLine {
+            marker: Some(_), ..
+        }) => (),
+        _ => return Err(Err::Error(error_position!(input, ErrorKind::Custom(0)))),
+    }
+
+    let mut lists = vec![];
+    let mut index = 0;
+
+    while index < lines.len() {
+        let (list, next) = build_list(&lines, index, false);
+        lists.push(list);
+        index = next;
+    }
+
+    let ast = match lists.len() {
+        1 => lists.remove(0).ast,
+        _ => Ast::Group(lists),
+    };
+
+    Ok((input.slice(input.fragment.0.len()..), ast))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// For includes
+////////////////////////////////////////////////////////////////////////////////
+
+/// Parses an include directive, e.g. `!include chapters/intro.dex`.
+named!(pub parse_include_line<Span, Ast>,
+    do_parse!(
+        directive: tag!("!include") >>
+        take_while1!(|x| x == ' ' || x == '\t') >>
+        path: take_till1!(|x| x == '\n') >>
+        opt!(tag!("\n")) >>
+        (Ast::Include {
+            path: path.fragment.0.trim_end().into(),
+            position: position(&directive),
         })
     )
 );
 
+/// Parses a bloc containing include directives, one per line.
+named!(pub parse_include<Span, Ast>,
+    map!(
+        terminated!(many1!(call!(node, parse_include_line)), eof!()),
+        |mut x| if x.len() == 1 { x.remove(0).ast } else { Ast::Group(x) }
+    )
+);
+
+////////////////////////////////////////////////////////////////////////////////
+// For figures
+////////////////////////////////////////////////////////////////////////////////
+
+/// Creates a figure, or an error if its width is not a percentage between 1 and 100.
+pub fn figure(directive: Span, path: Span, width: Span, caption: Option<Node>) -> Ast {
+    let percentage = width.fragment.0.trim_end();
+
+    let width = if percentage.is_empty() {
+        None
+    } else {
+        match percentage
+            .strip_suffix('%')
+            .and_then(|x| x.parse::<u32>().ok())
+        {
+            Some(x) if x > 0 && x <= 100 => Some(x),
+            _ => return error(width, ErrorType::InvalidWidth),
+        }
+    };
+
+    let caption = match caption {
+        Some(caption) => match &caption.ast {
+            Ast::Group(children) if children.is_empty() => None,
+            _ => Some(Box::new(caption)),
+        },
+        None => None,
+    };
+
+    Ast::Figure {
+        path: path.fragment.0.into(),
+        width,
+        caption,
+        position: position(&directive),
+    }
+}
+
+/// Parses a bloc containing a figure, e.g. `!image plot.png 50%`, whose caption is on the next
+/// lines.
+named!(pub parse_figure<Span, Ast>,
+    do_parse!(
+        directive: tag!("!image") >>
+        take_while1!(|x| x == ' ' || x == '\t') >>
+        path: take_till1!(char::is_whitespace) >>
+        take_while!(|x| x == ' ' || x == '\t') >>
+        width: take_till!(|x| x == '\n') >>
+        caption: opt!(preceded!(tag!("\n"), call!(node, parse_group))) >>
+        eof!() >>
+        (figure(directive, path, width, caption))
+    )
+);
+
+////////////////////////////////////////////////////////////////////////////////
+// For tables
+////////////////////////////////////////////////////////////////////////////////
+
+/// Splits a bloc into its non empty lines.
+fn split_lines(input: Span) -> Vec<Span> {
+    let mut lines = vec![];
+    let mut start = 0;
+
+    loop {
+        let end = match input.fragment.0[start..].find('\n') {
+            Some(i) => start + i,
+            None => input.fragment.0.len(),
+        };
+
+        let line = input.slice(start..end);
+        if !line.fragment.0.trim().is_empty() {
+            lines.push(line);
+        }
+
+        if end == input.fragment.0.len() {
+            break;
+        }
+
+        start = end + 1;
+    }
+
+    lines
+}
+
+/// Removes the whitespaces at the beginning and at the end of a span.
+fn trim(span: Span) -> Span {
+    let content = span.fragment.0;
+    let start = content.len() - content.trim_start().len();
+    let end = content.trim_end().len().max(start);
+    span.slice(start..end)
+}
+
+/// Splits a row of a table into its cells, which are separated by pipes.
+///
+/// The pipe at the end of the row is optional, and the pipes that are escaped or in some code or
+/// some math don't separate cells.
+fn table_cells(line: Span) -> Vec<Span> {
+    let line = trim(line);
+    let content = line.fragment.0;
+
+    let mut escaped = false;
+    let mut verbatim = None;
+    let mut pipes = vec![];
+
+    for (index, c) in content.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if let Some(delimiter) = verbatim {
+            if c == delimiter {
+                verbatim = None;
+            }
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '`' || c == '$' {
+            verbatim = Some(c);
+        } else if c == '|' {
+            pipes.push(index);
+        }
+    }
+
+    let end = match pipes.last() {
+        Some(&index) if index == content.len() - 1 => {
+            pipes.pop();
+            index
+        }
+        _ => content.len(),
+    };
+
+    let mut cells = vec![];
+    let mut start = 1;
+
+    for index in pipes {
+        cells.push(trim(line.slice(start..index)));
+        start = index + 1;
+    }
+
+    cells.push(trim(line.slice(start..end)));
+    cells
+}
+
+/// Returns the alignment of a column from its cell in the alignment row, e.g. `:---:`.
+fn column_alignment(cell: Span) -> Option<Alignment> {
+    let content = cell.fragment.0;
+    let dashes = content.trim_start_matches(':').trim_end_matches(':');
+
+    if dashes.is_empty() || !dashes.chars().all(|x| x == '-') {
+        return None;
+    }
+
+    match (content.starts_with(':'), content.ends_with(':')) {
+        (true, true) => Some(Alignment::Center),
+        (false, true) => Some(Alignment::Right),
+        _ => Some(Alignment::Left),
+    }
+}
+
+/// Parses the cells of a row of a table, or returns an error if it doesn't have as many cells as
+/// the table has columns.
+fn table_row(line: Span, columns: usize) -> Vec<Node> {
+    let cells = table_cells(line);
+
+    if cells.len() != columns {
+        return vec![spanned(line, error(line, ErrorType::UnmatchedColumns))];
+    }
+
+    cells.into_iter().map(parse_content).collect()
+}
+
+/// Parses a bloc containing a table.
+///
+/// Rows start with a pipe, and the second one gives the alignments of the columns, e.g.
+/// `|:---|:---:|---:|`. The lines after the rows are the caption of the table.
+pub fn parse_table(input: Span) -> IResult<Span, Ast> {
+    let lines = split_lines(input);
+    let not_a_table = Err(Err::Error(error_position!(input, ErrorKind::Custom(0))));
+
+    let rows = lines
+        .iter()
+        .take_while(|x| x.fragment.0.starts_with('|'))
+        .count();
+
+    if rows < 2 {
+        return not_a_table;
+    }
+
+    let alignments = match table_cells(lines[1])
+        .into_iter()
+        .map(column_alignment)
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(alignments) => alignments,
+        None => return not_a_table,
+    };
+
+    let header = table_row(lines[0], alignments.len());
+    let body = lines[2..rows]
+        .iter()
+        .map(|x| table_row(*x, alignments.len()))
+        .collect();
+
+    let caption = lines.get(rows).map(|line| {
+        let caption = trim(input.slice(line.offset - input.offset..));
+        Box::new(parse_content(caption))
+    });
+
+    let table = Ast::Table {
+        alignments,
+        header,
+        rows: body,
+        caption,
+    };
+
+    Ok((input.slice(input.fragment.0.len()..), table))
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // For main
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Gets a code bloc, which can contain empty lines, up to its closing fence.
+named!(pub get_code_bloc<Span, Span>,
+    terminated!(
+        recognize!(tuple!(
+            tag!("```"),
+            take_until_and_consume!("\n```"),
+            take_till!(|x| x == '\n')
+        )),
+        many0!(tag!("\n"))
+    )
+);
+
 /// Gets a bloc of content.
 named!(pub get_bloc<Span, Span>,
     alt!(
-        terminated!(take_until_and_consume!("\n\n"), many0!(tag!("\n")))
+        get_code_bloc
+        | terminated!(take_until_and_consume!("\n\n"), many0!(tag!("\n")))
         | terminated!(take_until_and_consume!("\n"), eof!())
         | call!(rest)
     )
 );
 
+/// Parses a display math, e.g. `$$x^2$$`.
+///
+/// The equation is numbered unless the opening dollars are followed by a star.
+named!(pub parse_display_formula<Span, Ast>,
+    do_parse!(
+        tag!("$$") >>
+        star: opt!(tag!("*")) >>
+        content: take_until_and_consume!("$$") >>
+        (display_math(content, star.is_none()))
+    )
+);
+
+/// Parses a bloc containing a display math, which can be followed by a label.
+named!(pub parse_display_math<Span, Ast>,
+    do_parse!(
+        math: call!(node, parse_display_formula) >>
+        label: opt!(preceded!(take_while!(char::is_whitespace), call!(node, parse_label))) >>
+        take_while!(char::is_whitespace) >>
+        eof!() >> ({
+            match label {
+                Some(label) => Ast::Group(vec![math, label]),
+                None => math.ast,
+            }
+        })
+    )
+);
+
+/// Parses a bloc containing a code block, e.g. ` ```rust ` followed by some code and a closing
+/// ` ``` `.
+named!(pub parse_code_block<Span, Ast>,
+    alt!(
+        do_parse!(
+            tag!("```") >>
+            language: take_till!(|x| x == '\n') >>
+            tag!("\n") >>
+            content: take_until_and_consume!("\n```") >>
+            take_while!(char::is_whitespace) >>
+            eof!() >>
+            (Ast::CodeBlock {
+                language: match language.fragment.0.trim() {
+                    "" => None,
+                    language => Some(language.into()),
+                },
+                content: content.fragment.0.into(),
+            })
+        )
+        | terminated!(tag!("```"), call!(rest)) => { |x| error(x, ErrorType::UnclosedCodeBlock) }
+    )
+);
+
+/// Gets the front matter at the top of a file, between two lines of three dashes.
+named!(pub get_front_matter<Span, Span>,
+    do_parse!(
+        tag!("---") >>
+        take_while!(|x| x == ' ' || x == '\t') >>
+        tag!("\n") >>
+        content: take_until_and_consume!("\n---") >>
+        take_till!(|x| x == '\n') >>
+        many0!(tag!("\n")) >>
+        (content)
+    )
+);
+
+/// Parses the front matter at the top of a file, whose lines are keys and values separated by a
+/// colon, e.g. `title: A document`.
+///
+/// Returns the metadata along with the errors of the front matter.
+pub fn parse_front_matter(input: Span) -> IResult<Span, (Metadata, Vec<EmptyError>)> {
+    let (rest, content) = get_front_matter(input)?;
+    let mut metadata = Metadata::default();
+    let mut errors = vec![];
+
+    for line in split_lines(content) {
+        let line = trim(line);
+
+        let (key, value) = match line.fragment.0.find(':') {
+            Some(index) => (
+                trim(line.slice(..index)),
+                line.fragment.0[index + 1..].trim(),
+            ),
+            None => {
+                errors.push(EmptyError {
+                    position: position(&line),
+                    ty: ErrorType::InvalidMetadata,
+                });
+                continue;
+            }
+        };
+
+        if !metadata.set(key.fragment.0, value) {
+            errors.push(EmptyError {
+                position: position(&key),
+                ty: ErrorType::UnknownMetadata,
+            });
+        }
+    }
+
+    Ok((rest, (metadata, errors)))
+}
+
+/// Parses a bloc containing the directive of the table of contents, `!contents`.
+named!(pub parse_table_of_contents<Span, Ast>,
+    do_parse!(
+        tag!("!contents") >>
+        take_while!(char::is_whitespace) >>
+        eof!() >>
+        (Ast::TableOfContents)
+    )
+);
+
+/// Parses a bloc containing the directive of the bibliography, `!bibliography`.
+named!(pub parse_bibliography<Span, Ast>,
+    do_parse!(
+        tag!("!bibliography") >>
+        take_while!(char::is_whitespace) >>
+        eof!() >>
+        (Ast::Bibliography)
+    )
+);
+
 /// Parses a bloc of content.
 named!(pub parse_bloc_content<Span, Ast>,
     alt!(
-        parse_title | parse_paragraph
+        parse_title
+            | parse_include
+            | parse_code_block
+            | parse_table_of_contents
+            | parse_bibliography
+            | parse_figure
+            | parse_display_math
+            | parse_table
+            | parse_list
+            | parse_paragraph
     )
 );
 
+/// Returns the inline contents of a bloc, e.g. the lines of the items of a list or the cells of a
+/// table, in the order of the document.
+fn inline_contents(bloc: &mut Node) -> Vec<&mut Vec<Node>> {
+    match &mut bloc.ast {
+        Ast::Paragraph(children) | Ast::Group(children) => vec![children],
+        Ast::Title { content, .. } => inline_contents(content),
+        Ast::Figure {
+            caption: Some(caption),
+            ..
+        } => inline_contents(caption),
+        Ast::Table {
+            header,
+            rows,
+            caption,
+            ..
+        } => header
+            .iter_mut()
+            .chain(rows.iter_mut().flatten())
+            .chain(caption.iter_mut().map(|x| &mut **x))
+            .flat_map(inline_contents)
+            .collect(),
+        Ast::List { items, .. } => items.iter_mut().flat_map(inline_contents).collect(),
+        Ast::ListItem { content, children } => {
+            let lines = match &mut content.ast {
+                Ast::Group(lines) => lines
+                    .iter_mut()
+                    .filter(|x| matches!(x.ast, Ast::Group(_)))
+                    .collect(),
+                _ => vec![],
+            };
+
+            lines
+                .into_iter()
+                .chain(children.iter_mut())
+                .flat_map(inline_contents)
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// Returns the type of the error of a delimiter that is never closed, if the node is one.
+fn unmatched_delimiter(node: &Node) -> Option<ErrorType> {
+    match &node.ast {
+        Ast::Error(EmptyError { ty, .. }) => match ty {
+            ErrorType::UnmatchedStar
+            | ErrorType::UnmatchedSlash
+            | ErrorType::UnmatchedDollar
+            | ErrorType::UnmatchedBacktick
+            | ErrorType::UnmatchedFootnote => Some(*ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the index of the node that closes a delimiter opened in a previous bloc, if any.
+///
+/// A star, a slash, a dollar or a backtick is closed by the first unmatched one of the same kind,
+/// and a footnote by the first closing bracket that matches no opening one.
+fn closing_delimiter(content: &[Node], ty: ErrorType) -> Option<usize> {
+    if ty != ErrorType::UnmatchedFootnote {
+        return content
+            .iter()
+            .position(|x| unmatched_delimiter(x) == Some(ty));
+    }
+
+    let mut depth = 0;
+
+    content.iter().position(|x| match &x.ast {
+        Ast::Text(text) if text == "[" => {
+            depth += 1;
+            false
+        }
+        Ast::Text(text) if text == "]" && depth > 0 => {
+            depth -= 1;
+            false
+        }
+        Ast::Text(text) => text == "]",
+        _ => false,
+    })
+}
+
+/// Reports the contents that are closed in the bloc following the one they are opened in.
+///
+/// The innermost delimiter left open at the end of a bloc becomes a `SpanAcrossParagraphs` error,
+/// and the first unmatched delimiter of the same kind at the beginning of the next bloc, which was
+/// meant to close it, is not reported again.
+fn join_unclosed_styles(blocs: &mut [Node]) {
+    for index in 1..blocs.len() {
+        let (previous, next) = blocs.split_at_mut(index);
+
+        let previous = match inline_contents(&mut previous[index - 1]).pop() {
+            Some(previous) => previous,
+            None => continue,
+        };
+
+        let next = match inline_contents(&mut next[0]).into_iter().next() {
+            Some(next) => next,
+            None => continue,
+        };
+
+        let opening = previous
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, x)| Some((index, unmatched_delimiter(x)?)));
+
+        let (opening, ty) = match opening {
+            Some(x) => x,
+            None => continue,
+        };
+
+        if let Some(closing) = closing_delimiter(next, ty) {
+            if let Ast::Error(error) = &mut previous[opening].ast {
+                error.ty = ErrorType::SpanAcrossParagraphs;
+            }
+
+            if ty != ErrorType::UnmatchedFootnote {
+                next.remove(closing);
+            }
+        }
+    }
+}
+
+/// Splits a whole dex file into blocs separated by empty lines.
+pub fn blocs(input: Span) -> Vec<Span> {
+    let mut blocs = vec![];
+    let mut input = input;
+
+    while let Ok((rest, bloc)) = get_bloc(input) {
+        blocs.push(bloc);
+
+        if rest.fragment.0.is_empty() || rest.offset == input.offset {
+            break;
+        }
+
+        input = rest;
+    }
+
+    blocs
+}
+
 /// Parses a whole dex file.
-named!(pub parse<Span, Ast>,
-    do_parse!(
-        title: many1!(map_res!(call!(get_bloc), parse_bloc_content)) >> ({
-            Ast::Group(title.into_iter().map(|x| x.1).collect())
+///
+/// Each bloc is parsed on its own, so that an error in a bloc never prevents the following ones
+/// from being parsed.
+pub fn parse(input: Span) -> Ast {
+    let mut blocs = blocs(input)
+        .into_iter()
+        .map(|bloc| match parse_bloc_content(bloc) {
+            Ok((_, ast)) => spanned(trim(bloc), ast),
+            // The paragraph parser accepts any bloc, but the content is kept as plain text rather
+            // than lost if it ever fails.
+            Err(_) => {
+                let text = spanned(bloc, Ast::Text(bloc.fragment.0.into()));
+                spanned(trim(bloc), Ast::Paragraph(vec![text]))
+            }
         })
-    )
-);
+        .collect::<Vec<_>>();
+
+    join_unclosed_styles(&mut blocs);
+    Ast::Group(blocs)
+}
+
+/// Returns the errors of the control characters of some content, which are never part of a
+/// text, except for tabulations and line breaks.
+pub fn control_characters(input: Span) -> Vec<EmptyError> {
+    input
+        .fragment
+        .0
+        .char_indices()
+        .filter(|(_, c)| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+        .map(|(index, _)| EmptyError {
+            position: position(&input.slice(index..)),
+            ty: ErrorType::ControlCharacter,
+        })
+        .collect()
+}
@@ -5,12 +5,14 @@ use std::fmt;
 use std::path::PathBuf;
 
 use colored::*;
+use serde::{Deserialize, Serialize};
 
 use crate::parser::utils::{next_new_line, previous_new_line, replicate};
-use crate::parser::Position;
+use crate::parser::warning::WarningType;
+use crate::parser::{Inclusion, Position};
 
 /// The different types errors that can occur while parsing.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ErrorType {
     /// A star for bold content is unmatched.
     UnmatchedStar,
@@ -21,8 +23,84 @@ pub enum ErrorType {
     /// A dollar for a inlinemath is unmatched.
     UnmatchedDollar,
 
+    /// A bracket for a footnote is unmatched.
+    UnmatchedFootnote,
+
+    /// A backtick for some inline code is unmatched.
+    UnmatchedBacktick,
+
+    /// The fence of a code block is never closed.
+    UnclosedCodeBlock,
+
+    /// A styled content, some math, some code or a footnote is closed in the bloc after the one it
+    /// starts in.
+    SpanAcrossParagraphs,
+
     /// A title is on multiple lines.
     MultipleLinesTitle,
+
+    /// A title has more hashes than the deepest level of title.
+    InvalidTitleLevel,
+
+    /// A list item is indented like none of the items before it.
+    UnmatchedIndentation,
+
+    /// A command in a formula is unknown.
+    UnknownMathCommand,
+
+    /// A brace in a formula is unmatched.
+    UnmatchedBrace,
+
+    /// A script or a command in a formula lacks its argument.
+    MissingMathArgument,
+
+    /// An included file cannot be read.
+    IncludeNotFound,
+
+    /// A file includes itself, directly or not.
+    IncludeCycle,
+
+    /// An image cannot be read.
+    ImageNotFound,
+
+    /// An image cannot be decoded.
+    InvalidImage,
+
+    /// The width of an image is not a valid percentage.
+    InvalidWidth,
+
+    /// A row of a table doesn't have as many cells as the table has columns.
+    UnmatchedColumns,
+
+    /// A label is defined more than once.
+    DuplicateLabel,
+
+    /// A line of the front matter is not a key and a value.
+    InvalidMetadata,
+
+    /// A key of the front matter is not supported.
+    UnknownMetadata,
+
+    /// An included file starts with a front matter.
+    IncludedFrontMatter,
+
+    /// A citation refers to a key that is not in the bibliography.
+    UnknownCitation,
+
+    /// An entry of a BibTeX file is malformed.
+    InvalidBibliography,
+
+    /// A reference refers to a label that is not defined.
+    UndefinedLabel,
+
+    /// A span of text has a style that does not exist.
+    UnknownStyle,
+
+    /// The content contains a control character.
+    ControlCharacter,
+
+    /// A warning whose lint is denied.
+    Lint(WarningType),
 }
 
 impl ErrorType {
@@ -32,7 +110,32 @@ impl ErrorType {
             ErrorType::UnmatchedStar => "unmatched *",
             ErrorType::UnmatchedSlash => "unmactched /",
             ErrorType::UnmatchedDollar => "unmactched $",
+            ErrorType::UnmatchedFootnote => "unmatched ^[",
+            ErrorType::UnmatchedBacktick => "unmatched `",
+            ErrorType::UnclosedCodeBlock => "unclosed code block",
+            ErrorType::SpanAcrossParagraphs => "unclosed span at the end of a bloc",
             ErrorType::MultipleLinesTitle => "titles must be followed by an empty line",
+            ErrorType::InvalidTitleLevel => "invalid title level",
+            ErrorType::UnmatchedIndentation => "unmatched indentation",
+            ErrorType::UnknownMathCommand => "unknown command",
+            ErrorType::UnmatchedBrace => "unmatched brace",
+            ErrorType::MissingMathArgument => "missing argument",
+            ErrorType::IncludeNotFound => "cannot include file",
+            ErrorType::IncludeCycle => "cyclic include",
+            ErrorType::ImageNotFound => "cannot read image",
+            ErrorType::InvalidImage => "invalid image",
+            ErrorType::InvalidWidth => "invalid width",
+            ErrorType::UnmatchedColumns => "wrong number of cells",
+            ErrorType::DuplicateLabel => "duplicate label",
+            ErrorType::InvalidMetadata => "invalid metadata",
+            ErrorType::UnknownMetadata => "unknown metadata",
+            ErrorType::IncludedFrontMatter => "front matter in an included file",
+            ErrorType::UnknownCitation => "unknown citation",
+            ErrorType::InvalidBibliography => "invalid bibliography entry",
+            ErrorType::UndefinedLabel => "undefined label",
+            ErrorType::UnknownStyle => "unknown style",
+            ErrorType::ControlCharacter => "control character",
+            ErrorType::Lint(ty) => ty.title(),
         }
     }
 
@@ -42,7 +145,34 @@ impl ErrorType {
             ErrorType::UnmatchedStar => "bold content starts here but never ends",
             ErrorType::UnmatchedSlash => "italic content starts here but never ends",
             ErrorType::UnmatchedDollar => "inline inlinemath starts here but never ends",
+            ErrorType::UnmatchedFootnote => "footnote starts here but never ends",
+            ErrorType::UnmatchedBacktick => "inline code starts here but never ends",
+            ErrorType::UnclosedCodeBlock => "code block starts here but never ends",
+            ErrorType::SpanAcrossParagraphs => {
+                "this content starts here but its bloc ends before it is closed"
+            }
             ErrorType::MultipleLinesTitle => "expected empty line here",
+            ErrorType::InvalidTitleLevel => "too many hashes for a title",
+            ErrorType::UnmatchedIndentation => "this item is not aligned with any previous item",
+            ErrorType::UnknownMathCommand => "this command is not supported in formulas",
+            ErrorType::UnmatchedBrace => "this brace has no matching brace",
+            ErrorType::MissingMathArgument => "expected an argument here",
+            ErrorType::IncludeNotFound => "this file cannot be read",
+            ErrorType::IncludeCycle => "this file is already being included",
+            ErrorType::ImageNotFound => "this image cannot be read",
+            ErrorType::InvalidImage => "this image cannot be decoded",
+            ErrorType::InvalidWidth => "expected a percentage here",
+            ErrorType::UnmatchedColumns => "this row doesn't have as many cells as the table",
+            ErrorType::DuplicateLabel => "this label is already defined",
+            ErrorType::InvalidMetadata => "expected a key and a value separated by a colon",
+            ErrorType::UnknownMetadata => "this key is not supported",
+            ErrorType::IncludedFrontMatter => "this front matter is not in the main file",
+            ErrorType::UnknownCitation => "this key is not in the bibliography",
+            ErrorType::InvalidBibliography => "unexpected character here",
+            ErrorType::UndefinedLabel => "this label is never defined",
+            ErrorType::UnknownStyle => "this style is not supported",
+            ErrorType::ControlCharacter => "this character cannot be written",
+            ErrorType::Lint(ty) => ty.detail(),
         }
     }
 
@@ -52,13 +182,81 @@ impl ErrorType {
             ErrorType::UnmatchedStar => None,
             ErrorType::UnmatchedSlash => None,
             ErrorType::UnmatchedDollar => None,
+            ErrorType::UnmatchedFootnote => Some("footnotes end with a bracket, e.g. '^[A note.]'"),
+            ErrorType::UnmatchedBacktick => None,
+            ErrorType::UnclosedCodeBlock => {
+                Some("code blocks end with three backticks at the beginning of a line")
+            }
+            ErrorType::SpanAcrossParagraphs => Some(
+                "styled contents, math, code and footnotes must end in the bloc they start in, and blocs are separated by empty lines",
+            ),
             ErrorType::MultipleLinesTitle => None,
+            ErrorType::InvalidTitleLevel => {
+                Some("titles have between one and six hashes, e.g. '## A section'")
+            }
+            ErrorType::UnmatchedIndentation => {
+                Some("nested items must have the same indentation as their siblings")
+            }
+            ErrorType::UnknownMathCommand => Some(
+                "formulas support greek letters, \\frac, \\pm, \\cdot, \\div, \\neq, \\leq, \\geq, \\neg and \\ldots",
+            ),
+            ErrorType::UnmatchedBrace => None,
+            ErrorType::MissingMathArgument => {
+                Some("scripts need one argument, e.g. 'x^2', and \\frac needs two, e.g. '\\frac{a}{b}'")
+            }
+            ErrorType::IncludeNotFound => {
+                Some("included paths are relative to the file containing the directive")
+            }
+            ErrorType::IncludeCycle => None,
+            ErrorType::ImageNotFound => {
+                Some("image paths are relative to the file containing the figure")
+            }
+            ErrorType::InvalidImage => Some("images must be PNG or JPEG files"),
+            ErrorType::InvalidWidth => {
+                Some("widths are percentages of the width of the text, e.g. '50%'")
+            }
+            ErrorType::UnmatchedColumns => {
+                Some("tables have as many columns as their alignment row, e.g. '|:---|---:|'")
+            }
+            ErrorType::DuplicateLabel => None,
+            ErrorType::InvalidMetadata => Some("metadata are written as 'key: value', e.g. 'title: A document'"),
+            ErrorType::UnknownMetadata => {
+                Some("the front matter supports title, authors, date, language and keywords")
+            }
+            ErrorType::IncludedFrontMatter => {
+                Some("only the main file can start with a front matter")
+            }
+            ErrorType::UnknownCitation => {
+                Some("citations refer to the entries of the bibliography file given in spandex.toml")
+            }
+            ErrorType::InvalidBibliography => {
+                Some("entries are written as '@book{key, title = {A title}, year = 1984}'")
+            }
+            ErrorType::UndefinedLabel => {
+                Some("labels are defined after the element they refer to, e.g. '# Introduction {#intro}'")
+            }
+            ErrorType::UnknownStyle => Some(
+                "the styles are smallcaps, underline, strikethrough, superscript and subscript",
+            ),
+            ErrorType::ControlCharacter => {
+                Some("only tabulations and line breaks are allowed, the file may not be a text file")
+            }
+            ErrorType::Lint(ty) => ty.note(),
+        }
+    }
+
+    /// Returns a note for the error of an ast that has no source, e.g. one read from JSON, whose
+    /// image paths are relative to the current directory.
+    pub fn note_without_source(self) -> Option<&'static str> {
+        match self {
+            ErrorType::ImageNotFound => Some("image paths are relative to the current directory"),
+            ty => ty.note(),
         }
     }
 }
 
 /// An error that occured during the parsing.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EmptyError {
     /// The position of the error.
     pub position: Position,
@@ -73,11 +271,24 @@ pub struct Errors {
     /// The path to the corresponding file.
     pub path: PathBuf,
 
-    /// The content that produced the errors.
+    /// The content that produced the errors, which is empty for an ast that has no source.
     pub content: String,
 
     /// The errors that were produced.
     pub errors: Vec<EmptyError>,
+
+    /// The include directives that lead to the file, starting from the main file.
+    pub chain: Vec<Inclusion>,
+
+    /// The errors of the files included by the file.
+    pub children: Vec<Errors>,
+}
+
+impl Errors {
+    /// Returns true if neither the file nor the files it includes produced errors.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty() && self.children.iter().all(Errors::is_empty)
+    }
 }
 
 impl fmt::Display for Errors {
@@ -89,41 +300,63 @@ impl fmt::Display for Errors {
             let line = error.position.line;
             let column = error.position.column;
 
+            // An ast that has no source, e.g. one read from JSON, has no line to show.
             let line_number = format!("{} ", line);
-            let space = replicate(' ', line_number.len() - 1);
+            let space = if self.content.is_empty() {
+                String::from(" ")
+            } else {
+                replicate(' ', line_number.len() - 1)
+            };
             let margin = replicate(' ', column);
             let hats = replicate('^', 1);
 
             writeln!(fmt, "{}{}", "error: ".bold().red(), error.ty.title().bold())?;
 
-            writeln!(
-                fmt,
-                "{}{} {}:{}:{}",
-                space,
-                "-->".bold().blue(),
-                self.path.display(),
-                line,
-                column
-            )?;
-
-            writeln!(fmt, "{} {}", space, "|".blue().bold())?;
-            writeln!(
-                fmt,
-                "{} {}",
-                &format!("{}|", line_number).blue().bold(),
-                &self.content[start..end]
-            )?;
-            writeln!(
-                fmt,
-                "{} {}{}{} {}",
-                space,
-                "|".blue().bold(),
-                margin,
-                hats.bold().red(),
-                error.ty.detail().bold().red()
-            )?;
-            writeln!(fmt, "{} {}", space, "|".blue().bold())?;
-            if let Some(note) = error.ty.note() {
+            if self.content.is_empty() {
+                writeln!(
+                    fmt,
+                    "{}{} {}",
+                    space,
+                    "-->".bold().blue(),
+                    self.path.display()
+                )?;
+                writeln!(fmt, "{} {}", space, "|".blue().bold())?;
+            } else {
+                writeln!(
+                    fmt,
+                    "{}{} {}:{}:{}",
+                    space,
+                    "-->".bold().blue(),
+                    self.path.display(),
+                    line,
+                    column
+                )?;
+
+                writeln!(fmt, "{} {}", space, "|".blue().bold())?;
+                writeln!(
+                    fmt,
+                    "{} {}",
+                    &format!("{}|", line_number).blue().bold(),
+                    &self.content[start..end]
+                )?;
+                writeln!(
+                    fmt,
+                    "{} {}{}{} {}",
+                    space,
+                    "|".blue().bold(),
+                    margin,
+                    hats.bold().red(),
+                    error.ty.detail().bold().red()
+                )?;
+                writeln!(fmt, "{} {}", space, "|".blue().bold())?;
+            }
+            let note = if self.content.is_empty() {
+                error.ty.note_without_source()
+            } else {
+                error.ty.note()
+            };
+
+            if let Some(note) = note {
                 writeln!(
                     fmt,
                     "{} {} {}{}",
@@ -133,6 +366,22 @@ impl fmt::Display for Errors {
                     note
                 )?;
             }
+            for inclusion in self.chain.iter().rev() {
+                writeln!(
+                    fmt,
+                    "{} {} {}included from {}:{}:{}",
+                    space,
+                    "=".blue().bold(),
+                    "note: ".bold(),
+                    inclusion.path.display(),
+                    inclusion.position.line,
+                    inclusion.position.column
+                )?;
+            }
+        }
+
+        for child in &self.children {
+            write!(fmt, "{}", child)?;
         }
 
         Ok(())
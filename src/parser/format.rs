@@ -0,0 +1,812 @@
+//! This module contains the formatter, which writes the source of a dex file back in a canonical
+//! style: a single space after the hashes of titles and the markers of list items, paragraphs
+//! wrapped at a column, single spaces between words, and only the escapes that are needed.
+//!
+//! Formatting never changes the content of the document: the formatted source is parsed to the
+//! same ast as the original one once both are normalized, see `same_document`. The normalization
+//! only forgets how the inline content is written: how its texts are split and grouped, the number
+//! of whitespaces between its words and at its edges, the paragraphs without content, and the
+//! positions. A bloc that would be parsed differently once formatted is a bug of the formatter,
+//! and prevents the file from being formatted.
+
+use nom::types::CompleteStr;
+
+use crate::ligature::unligature;
+use crate::parser::ast::{Alignment, Ast, Node};
+use crate::parser::combinators::{blocs, get_front_matter, parse};
+use crate::parser::error::EmptyError;
+use crate::parser::visit::{fold_ast, Fold};
+use crate::parser::warning::{EmptyWarning, WarningType};
+use crate::parser::{position, Position, Span};
+
+/// The column at which paragraphs are wrapped by default.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Splits the source of a dex file into its front matter, if any, and its content.
+fn split(content: &str) -> (Option<&str>, Span<'_>) {
+    let span = Span::new(CompleteStr(content));
+
+    match get_front_matter(span) {
+        Ok((rest, _)) => (Some(content[..rest.offset].trim_end()), rest),
+        Err(_) => (None, span),
+    }
+}
+
+/// Replaces the runs of whitespaces of a text by single spaces.
+fn collapse(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut space = false;
+
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            space = true;
+        } else {
+            if space {
+                output.push(' ');
+                space = false;
+            }
+            output.push(c);
+        }
+    }
+
+    if space {
+        output.push(' ');
+    }
+
+    output
+}
+
+/// Removes the whitespaces at the beginning and at the end of some inline content.
+fn trim(mut children: Vec<Ast>) -> Vec<Ast> {
+    if let Some(Ast::Text(text)) = children.first_mut() {
+        *text = text.trim_start().into();
+    }
+
+    if let Some(Ast::Text(text)) = children.last_mut() {
+        *text = text.trim_end().into();
+    }
+
+    children.retain(|x| !matches!(x, Ast::Text(text) if text.is_empty()));
+    children
+}
+
+/// Adds the children of some inline content to a list, replacing the groups by their children.
+fn flatten(children: Vec<Node>, output: &mut Vec<Ast>) {
+    for child in children {
+        match child.ast {
+            Ast::Group(children) => flatten(children, output),
+            ast => output.push(ast),
+        }
+    }
+}
+
+/// Rewrites an ast in the form it is formatted in, without spans nor positions.
+///
+/// The groups of inline content are replaced by their children, the consecutive texts are merged
+/// and their whitespaces are collapsed. A comment ends its line, so the whitespace that follows it
+/// is moved before it.
+struct Normalize;
+
+impl Normalize {
+    /// Rewrites some inline content.
+    fn inline(&mut self, children: Vec<Node>) -> Vec<Ast> {
+        let mut flat = vec![];
+        flatten(children, &mut flat);
+
+        let mut merged: Vec<Ast> = vec![];
+        for ast in flat {
+            match (merged.last_mut(), self.fold_ast(ast)) {
+                (Some(Ast::Text(previous)), Ast::Text(text)) => previous.push_str(&text),
+                (_, ast) => merged.push(ast),
+            }
+        }
+
+        let mut output: Vec<Ast> = vec![];
+        let mut after_comment = false;
+
+        for ast in merged {
+            let ast = match ast {
+                Ast::Text(text) => {
+                    let text = collapse(&text);
+
+                    match text.strip_prefix(' ') {
+                        Some(rest) if after_comment => {
+                            let comment = output.pop();
+
+                            match output.last_mut() {
+                                Some(Ast::Text(previous)) if !previous.ends_with(' ') => {
+                                    previous.push(' ')
+                                }
+                                Some(Ast::Text(_)) | None => (),
+                                Some(_) => output.push(Ast::Text(String::from(" "))),
+                            }
+
+                            output.extend(comment);
+                            Ast::Text(rest.into())
+                        }
+                        _ => Ast::Text(text),
+                    }
+                }
+                ast => ast,
+            };
+
+            after_comment = matches!(ast, Ast::Comment(_));
+
+            if !matches!(&ast, Ast::Text(text) if text.is_empty()) {
+                output.push(ast);
+            }
+        }
+
+        output
+    }
+
+    /// Rewrites the content of a bloc, e.g. the content of a title, without the whitespaces at its
+    /// beginning and at its end.
+    fn content(&mut self, node: Node) -> Node {
+        match node.ast {
+            Ast::Group(children) => {
+                let children = trim(self.inline(children));
+                Ast::Group(children.into_iter().map(Node::from).collect()).into()
+            }
+            ast => self.fold_ast(ast).into(),
+        }
+    }
+
+    /// Rewrites the boxed content of a bloc.
+    fn boxed(&mut self, mut node: Box<Node>) -> Box<Node> {
+        *node = self.content(*node);
+        node
+    }
+}
+
+impl Fold for Normalize {
+    fn fold_node(&mut self, node: Node) -> Node {
+        self.fold_ast(node.ast).into()
+    }
+
+    fn fold_ast(&mut self, ast: Ast) -> Ast {
+        let position = Position::default();
+
+        match ast {
+            Ast::Text(text) => Ast::Text(collapse(&text)),
+
+            // The formatter may escape characters that were not escaped.
+            Ast::Escaped(text) => Ast::Text(text),
+
+            Ast::Group(children) => {
+                Ast::Group(self.inline(children).into_iter().map(Node::from).collect())
+            }
+
+            Ast::Paragraph(children) => {
+                let children = trim(self.inline(children));
+                Ast::Paragraph(children.into_iter().map(Node::from).collect())
+            }
+
+            Ast::Title { level, content } => Ast::Title {
+                level,
+                content: self.boxed(content),
+            },
+
+            Ast::ListItem { content, children } => Ast::ListItem {
+                content: self.boxed(content),
+                children: children.into_iter().map(|x| self.fold_node(x)).collect(),
+            },
+
+            Ast::Figure {
+                path,
+                width,
+                caption,
+                ..
+            } => Ast::Figure {
+                path,
+                width,
+                caption: caption.map(|x| self.boxed(x)),
+                position,
+            },
+
+            Ast::Table {
+                alignments,
+                header,
+                rows,
+                caption,
+            } => {
+                let mut cells =
+                    |row: Vec<Node>| row.into_iter().map(|x| self.content(x)).collect::<Vec<_>>();
+                let header = cells(header);
+                let rows = rows.into_iter().map(&mut cells).collect();
+
+                Ast::Table {
+                    alignments,
+                    header,
+                    rows,
+                    caption: caption.map(|x| self.boxed(x)),
+                }
+            }
+
+            Ast::Link { url, content, .. } => Ast::Link {
+                url,
+                content: Box::new(self.fold_node(*content)),
+                position,
+            },
+
+            Ast::Label { name, .. } => Ast::Label { name, position },
+
+            Ast::Reference { label, page, .. } => Ast::Reference {
+                label,
+                page,
+                position,
+            },
+
+            Ast::Citation { keys } => Ast::Citation {
+                keys: keys.into_iter().map(|x| (x.0, position)).collect(),
+            },
+            Ast::Include { path, .. } => Ast::Include { path, position },
+            Ast::Error(error) => Ast::Error(EmptyError { position, ..error }),
+            Ast::Warning(warning) => Ast::Warning(EmptyWarning {
+                position,
+                ..warning
+            }),
+            _ => fold_ast(self, ast),
+        }
+    }
+}
+
+/// Rewrites the blocs of a dex file in the form they are formatted in, without the empty
+/// paragraphs.
+fn normalize(ast: Ast) -> Vec<Node> {
+    let blocs = match ast {
+        Ast::Group(blocs) => blocs,
+        ast => vec![ast.into()],
+    };
+
+    blocs
+        .into_iter()
+        .map(|x| Normalize.fold_node(x))
+        .filter(|x| !matches!(&x.ast, Ast::Paragraph(children) if children.is_empty()))
+        .collect()
+}
+
+/// Returns the front matter of the source of a dex file, if any, and the normalized ast of its
+/// blocs.
+pub fn normalized(content: &str) -> (Option<&str>, Vec<Node>) {
+    let (front_matter, span) = split(content);
+    (front_matter, normalize(parse(span)))
+}
+
+/// Returns true if two sources of dex files have the same front matter and are parsed to the same
+/// ast once normalized.
+pub fn same_document(first: &str, second: &str) -> bool {
+    normalized(first) == normalized(second)
+}
+
+/// Escapes the characters of a text that would be parsed as markup.
+///
+/// Some characters only need to be escaped before some others, and the ones at the end of the text
+/// are escaped since they can be followed by anything. The characters that start a title, a list or
+/// a directive are escaped if the text starts a bloc, and the pipes are escaped in the cells of
+/// tables.
+fn escape(text: &str, start: bool, cell: bool) -> String {
+    let text = unligature(text);
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut first = start;
+
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+
+        let escaped = match c {
+            '\\' | '*' | '/' | '$' | '`' | '[' | ']' => true,
+            '^' => next.is_none(),
+            '|' => cell || first || next.is_none() || next == Some('|'),
+            '{' => matches!(next, None | Some('#') | Some('@')),
+            '<' => next.is_none_or(|x| !x.is_whitespace()),
+            '-' => first && next.is_none_or(char::is_whitespace),
+            '#' | '!' => first,
+            _ => false,
+        };
+
+        if escaped {
+            output.push('\\');
+        }
+
+        output.push(c);
+        first = false;
+    }
+
+    output
+}
+
+/// A part of the source of some inline content.
+enum Piece {
+    /// Some source that is written as is.
+    Chunk(String),
+
+    /// A space between two words, where the line can be broken.
+    Space,
+
+    /// The end of a line, after a comment.
+    Break,
+}
+
+/// Writes some inline content as pieces of source.
+struct Printer {
+    /// The pieces written so far.
+    pieces: Vec<Piece>,
+
+    /// The number of markups the content being written is in.
+    depth: usize,
+
+    /// Whether the lines can only be broken outside of the markups, which is the case in list
+    /// items since each of their lines is parsed on its own.
+    flat: bool,
+
+    /// Whether the next text starts a bloc.
+    start: bool,
+
+    /// Whether the content is in a cell of a table, whose pipes separate the cells.
+    cell: bool,
+}
+
+impl Printer {
+    /// Returns the pieces of some inline content.
+    fn pieces(ast: &Ast, start: bool, flat: bool) -> Vec<Piece> {
+        let mut printer = Printer {
+            pieces: vec![],
+            depth: 0,
+            flat,
+            start,
+            cell: false,
+        };
+
+        printer.ast(ast);
+        printer.pieces
+    }
+
+    /// Returns the pieces of the content of a cell of a table.
+    fn cell(ast: &Ast) -> Vec<Piece> {
+        let mut printer = Printer {
+            pieces: vec![],
+            depth: 0,
+            flat: false,
+            start: false,
+            cell: true,
+        };
+
+        printer.ast(ast);
+        printer.pieces
+    }
+
+    /// Writes some source.
+    fn chunk<S: Into<String>>(&mut self, chunk: S) {
+        self.pieces.push(Piece::Chunk(chunk.into()));
+        self.start = false;
+    }
+
+    /// Writes a space, which can break the line unless it is in a markup of a list item.
+    fn space(&mut self) {
+        if self.flat && self.depth > 0 {
+            self.chunk(" ");
+        } else {
+            self.pieces.push(Piece::Space);
+        }
+    }
+
+    /// Writes some content in a markup.
+    fn nested(&mut self, open: &str, content: &Node, close: &str) {
+        self.chunk(open);
+        self.depth += 1;
+        self.ast(content);
+        self.depth -= 1;
+        self.chunk(close);
+    }
+
+    /// Writes a text, whose spaces can break the line.
+    fn text(&mut self, text: &str) {
+        let escaped = escape(text, self.start, self.cell);
+        self.start = false;
+
+        for (index, word) in escaped.split(' ').enumerate() {
+            if index > 0 {
+                self.space();
+            }
+
+            if !word.is_empty() {
+                self.chunk(word);
+            }
+        }
+    }
+
+    /// Writes some inline content.
+    fn ast(&mut self, ast: &Ast) {
+        match ast {
+            Ast::Text(text) => self.text(text),
+            Ast::Escaped(_) => {
+                self.start = false;
+                self.chunk(ast.to_string());
+            }
+            Ast::Bold(content) => self.nested("*", content, "*"),
+            Ast::Italic(content) => self.nested("/", content, "/"),
+            Ast::SmallCaps(content) => self.nested("[", content, "]{.smallcaps}"),
+            Ast::Underline(content) => self.nested("[", content, "]{.underline}"),
+            Ast::Strikethrough(content) => self.nested("[", content, "]{.strikethrough}"),
+            Ast::Superscript(content) => self.nested("[", content, "]{.superscript}"),
+            Ast::Subscript(content) => self.nested("[", content, "]{.subscript}"),
+            Ast::Footnote(content) => self.nested("^[", content, "]"),
+
+            Ast::Link { url, content, .. } => match &content.ast {
+                Ast::Text(text) if text == url => self.chunk(format!("<{}>", url)),
+                _ => self.nested("[", content, &format!("]({})", url)),
+            },
+
+            Ast::InlineMath { content, .. } => self.chunk(format!("${}$", content)),
+            Ast::Code(content) => self.chunk(format!("`{}`", content)),
+            Ast::Label { name, .. } => self.chunk(format!("{{#{}}}", name)),
+
+            Ast::Reference { label, page, .. } => self.chunk(format!(
+                "{{@{}{}}}",
+                if *page { "page:" } else { "" },
+                label
+            )),
+
+            Ast::Citation { keys } => {
+                let keys = keys.iter().map(|x| format!("@{}", x.0)).collect::<Vec<_>>();
+                self.chunk(format!("[{}]", keys.join("; ")))
+            }
+
+            Ast::Comment(comment) if comment.is_empty() => {
+                self.chunk("||");
+                self.pieces.push(Piece::Break);
+            }
+
+            Ast::Comment(comment) => {
+                self.chunk(format!("|| {}", comment));
+                self.pieces.push(Piece::Break);
+            }
+
+            Ast::Newline => self.pieces.push(Piece::Break),
+
+            Ast::Warning(EmptyWarning { ty, .. }) => match ty {
+                WarningType::ConsecutiveStars => self.chunk("**"),
+                WarningType::UnknownEscape => self.chunk("\\"),
+                _ => (),
+            },
+
+            Ast::Group(children) | Ast::Paragraph(children) => {
+                for child in children {
+                    self.ast(child);
+                }
+            }
+
+            // The other asts are blocs, or errors that prevent the formatting.
+            _ => (),
+        }
+    }
+}
+
+/// Returns true if a word can start a line without starting a list item or a row of a table.
+fn can_start_line(word: &str) -> bool {
+    let digits = word.len() - word.trim_start_matches(|x: char| x.is_ascii_digit()).len();
+    !(word.starts_with('-')
+        || word.starts_with('|')
+        || (digits > 0 && word[digits..].starts_with('.')))
+}
+
+/// Writes some pieces on lines, which are broken at their spaces so that they don't exceed a width
+/// when possible.
+///
+/// The first line starts with a prefix, e.g. the marker of a list item, and the other ones with
+/// another one, e.g. the indentation of the item.
+fn wrap(pieces: &[Piece], width: usize, first: &str, rest: &str) -> String {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+
+    for piece in pieces {
+        match piece {
+            Piece::Chunk(chunk) => word.get_or_insert_with(String::new).push_str(chunk),
+            Piece::Space => words.extend(word.take().map(Some)),
+            Piece::Break => {
+                words.extend(word.take().map(Some));
+                words.push(None);
+            }
+        }
+    }
+
+    words.extend(word.take().map(Some));
+
+    let mut output = String::new();
+    let mut line = String::from(first);
+    let mut empty = true;
+    let mut broken = false;
+
+    for word in words {
+        let word = match word {
+            Some(word) => word,
+            None => {
+                broken = !empty;
+                continue;
+            }
+        };
+
+        let length = line.chars().count() + 1 + word.chars().count();
+
+        if broken || (!empty && length > width && can_start_line(&word)) {
+            output.push_str(&line);
+            output.push('\n');
+            line = String::from(rest);
+            empty = true;
+            broken = false;
+        }
+
+        if !empty {
+            line.push(' ');
+        }
+
+        line.push_str(&word);
+        empty = false;
+    }
+
+    output.push_str(&line);
+    output
+}
+
+/// Writes the lines of a list, whose items are indented by some spaces.
+fn list(ast: &Ast, indent: usize, width: usize, lines: &mut Vec<String>) {
+    let (ordered, start, items) = match ast {
+        Ast::List {
+            ordered,
+            start,
+            items,
+        } => (*ordered, *start, items),
+        _ => return,
+    };
+
+    for (index, item) in items.iter().enumerate() {
+        let (content, children) = match &item.ast {
+            Ast::ListItem { content, children } => (content, children),
+            _ => continue,
+        };
+
+        let marker = if ordered {
+            format!("{}. ", start.saturating_add(index))
+        } else {
+            String::from("- ")
+        };
+
+        let first = format!("{}{}", " ".repeat(indent), marker);
+        let rest = " ".repeat(first.len());
+        lines.push(wrap(
+            &Printer::pieces(content, false, true),
+            width,
+            &first,
+            &rest,
+        ));
+
+        for child in children {
+            list(child, rest.len(), width, lines);
+        }
+    }
+}
+
+/// Writes a table, whose columns are aligned.
+fn table(alignments: &[Alignment], header: &[Node], rows: &[Vec<Node>]) -> String {
+    let cells = Some(header)
+        .into_iter()
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(|row| {
+            row.iter()
+                .map(|x| wrap(&Printer::cell(x), usize::MAX, "", ""))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let widths = (0..alignments.len())
+        .map(|column| {
+            cells
+                .iter()
+                .filter_map(|row| row.get(column))
+                .map(|x| x.chars().count())
+                .fold(3, usize::max)
+        })
+        .collect::<Vec<_>>();
+
+    let line = |row: &[String]| {
+        let row = row
+            .iter()
+            .zip(alignments.iter().zip(&widths))
+            .map(|(cell, (alignment, width))| match alignment {
+                Alignment::Left => format!("{:<width$}", cell, width = width),
+                Alignment::Center => format!("{:^width$}", cell, width = width),
+                Alignment::Right => format!("{:>width$}", cell, width = width),
+            })
+            .collect::<Vec<_>>();
+        format!("| {} |", row.join(" | "))
+    };
+
+    let separator = alignments
+        .iter()
+        .zip(&widths)
+        .map(|(alignment, width)| match alignment {
+            Alignment::Left => "-".repeat(*width),
+            Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+            Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+        })
+        .collect::<Vec<_>>();
+
+    let mut lines = vec![line(&cells[0]), line(&separator)];
+    lines.extend(cells[1..].iter().map(|x| line(x)));
+    lines.join("\n")
+}
+
+/// Writes a bloc.
+fn bloc(ast: &Ast, width: usize) -> String {
+    match ast {
+        Ast::Title { level, content } => {
+            let hashes = format!("{} ", "#".repeat(*level as usize + 1));
+            let title = wrap(
+                &Printer::pieces(content, false, false),
+                usize::MAX,
+                &hashes,
+                "",
+            );
+            String::from(title.trim_end())
+        }
+
+        Ast::Paragraph(_) => wrap(&Printer::pieces(ast, true, false), width, "", ""),
+
+        Ast::List { .. } => {
+            let mut lines = vec![];
+            list(ast, 0, width, &mut lines);
+            lines.join("\n")
+        }
+
+        Ast::CodeBlock { language, content } => {
+            format!("```{}\n{}\n```", language.as_deref().unwrap_or(""), content)
+        }
+
+        Ast::DisplayMath {
+            content, numbered, ..
+        } => {
+            let star = match (numbered, content.starts_with('*')) {
+                (false, _) => "*",
+                (true, true) => " ",
+                (true, false) => "",
+            };
+            format!("$${}{}$$", star, content)
+        }
+
+        Ast::Include { path, .. } => format!("!include {}", path),
+        Ast::TableOfContents => String::from("!contents"),
+        Ast::Bibliography => String::from("!bibliography"),
+
+        Ast::Figure {
+            path,
+            width: percentage,
+            caption,
+            ..
+        } => {
+            let mut output = format!("!image {}", path);
+            if let Some(percentage) = percentage {
+                output.push_str(&format!(" {}%", percentage));
+            }
+            if let Some(caption) = caption {
+                output.push('\n');
+                output.push_str(&wrap(&Printer::pieces(caption, true, false), width, "", ""));
+            }
+            output
+        }
+
+        Ast::Table {
+            alignments,
+            header,
+            rows,
+            caption,
+        } => {
+            let mut output = table(alignments, header, rows);
+            if let Some(caption) = caption {
+                output.push('\n');
+                output.push_str(&wrap(&Printer::pieces(caption, true, false), width, "", ""));
+            }
+            output
+        }
+
+        // A display math followed by its label, or some include directives.
+        Ast::Group(children) => {
+            let mut output = String::new();
+            for child in children {
+                if !output.is_empty() {
+                    let label = matches!(child.ast, Ast::Label { .. });
+                    output.push(if label { ' ' } else { '\n' });
+                }
+                output.push_str(&bloc(child, width));
+            }
+            output
+        }
+
+        _ => wrap(&Printer::pieces(ast, false, false), width, "", ""),
+    }
+}
+
+/// An error that prevents a dex file from being formatted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// The errors of the file, whose content cannot be known for sure.
+    Errors(Vec<EmptyError>),
+
+    /// The position of a bloc that would not be parsed back to the same content once formatted,
+    /// which is a bug of the formatter.
+    Unformattable(Position),
+}
+
+/// Formats a bloc, or returns its position if it would not be parsed back to the same content.
+///
+/// The last bloc ends the file, along with its line break.
+fn format_bloc(source: Span, node: &Node, width: usize, last: bool) -> Result<String, FormatError> {
+    let end = if last { "\n" } else { "" };
+    let formatted = format!("{}{}", bloc(node, width), end);
+
+    if normalize(parse(Span::new(CompleteStr(&formatted)))) == [node.clone()] {
+        Ok(formatted)
+    } else {
+        Err(FormatError::Unformattable(position(&source)))
+    }
+}
+
+/// Formats the source of a dex file, whose paragraphs are wrapped at a column.
+///
+/// The front matter is kept as is. Returns the errors of the file if it has some, since its
+/// content cannot be known for sure, or the position of the first bloc that cannot be formatted
+/// without changing the document.
+pub fn format(content: &str, width: usize) -> Result<String, FormatError> {
+    let (front_matter, span) = split(content);
+    let ast = parse(span);
+
+    let errors = ast.errors();
+    if !errors.is_empty() {
+        return Err(FormatError::Errors(errors));
+    }
+
+    let nodes = match ast {
+        Ast::Group(nodes) => nodes,
+        ast => vec![ast.into()],
+    };
+
+    let sources = blocs(span)
+        .into_iter()
+        .zip(nodes)
+        .flat_map(|(source, node)| {
+            normalize(Ast::Group(vec![node]))
+                .into_iter()
+                .map(move |x| (source, x))
+        })
+        .collect::<Vec<_>>();
+
+    let count = sources.len();
+    let blocs = sources
+        .iter()
+        .enumerate()
+        .map(|(index, (source, node))| format_bloc(*source, node, width, index + 1 == count))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let output = front_matter
+        .map(String::from)
+        .into_iter()
+        .chain(blocs)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    // Each bloc is parsed back to the same content, and so should the whole file.
+    let formatted = normalize(parse(split(&output).1));
+    let difference = (0..count.max(formatted.len()))
+        .find(|&index| formatted.get(index) != sources.get(index).map(|x| &x.1));
+
+    if let Some(index) = difference {
+        let source = sources.get(index).or_else(|| sources.last());
+        let position = source.map_or(Position::default(), |(source, _)| position(source));
+        return Err(FormatError::Unformattable(position));
+    }
+
+    match (front_matter, count) {
+        (Some(_), 0) => Ok(format!("{}\n", output)),
+        _ => Ok(output),
+    }
+}
@@ -0,0 +1,365 @@
+//! This module contains the lints, which check the source of dex and Markdown files for mistakes
+//! that don't prevent them from being typeset.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use nom::Slice;
+use serde::{Deserialize, Serialize};
+
+use crate::parser::ast::{Ast, Node};
+use crate::parser::visit::{self, Visit};
+use crate::parser::warning::{EmptyWarning, WarningType};
+use crate::parser::{position, Span};
+
+/// The number of words above which a paragraph is too long.
+pub const LONG_PARAGRAPH_WORDS: usize = 300;
+
+/// What happens when a lint finds a mistake.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintLevel {
+    /// The mistake is ignored.
+    Allow,
+
+    /// The mistake is reported as a warning.
+    Warn,
+
+    /// The mistake is reported as an error, and the document is not built.
+    Deny,
+}
+
+/// The levels of the lints, given by the `[lints]` table of `spandex.toml`, e.g.
+/// `repeated-word = "deny"`.
+///
+/// The lints that are not in the table are warnings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Lints(BTreeMap<String, LintLevel>);
+
+impl Lints {
+    /// Sets the level of a lint.
+    pub fn set(&mut self, ty: WarningType, level: LintLevel) {
+        self.0.insert(String::from(ty.name()), level);
+    }
+
+    /// Returns the level of a lint.
+    pub fn level(&self, ty: WarningType) -> LintLevel {
+        match self.0.get(ty.name()) {
+            Some(level) => *level,
+            None => LintLevel::Warn,
+        }
+    }
+
+    /// Returns the first name of the table that is not the name of a lint, if any.
+    pub fn unknown(&self) -> Option<&str> {
+        self.0
+            .keys()
+            .find(|name| WarningType::from_name(name).is_none())
+            .map(String::as_str)
+    }
+}
+
+/// A comment that allows some lints in the bloc containing it, e.g.
+/// `|| allow(repeated-word, doubled-spaces)` on the line before a paragraph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Allow {
+    /// The offsets of the bloc containing the comment.
+    pub range: Range<usize>,
+
+    /// The lints that are allowed.
+    pub lints: Vec<WarningType>,
+}
+
+impl Allow {
+    /// Returns true if the comment allows a warning.
+    pub fn allows(&self, warning: &EmptyWarning) -> bool {
+        self.range.contains(&warning.position.offset) && self.lints.contains(&warning.ty)
+    }
+}
+
+/// Returns the warning of a lint at an offset of some source.
+fn warning(source: Span, offset: usize, ty: WarningType) -> EmptyWarning {
+    EmptyWarning {
+        position: position(&source.slice(offset..)),
+        ty,
+    }
+}
+
+/// Returns true if the source of a bloc is prose, in which spaces and words can be checked.
+fn is_prose(ast: &Ast) -> bool {
+    matches!(
+        ast,
+        Ast::Title { .. } | Ast::Paragraph(_) | Ast::List { .. }
+    )
+}
+
+/// Replaces the bytes of a bloc that are not prose, i.e. escapes, inline code, inline math and
+/// comments, by null bytes, so that the offsets of the prose are kept.
+fn mask(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut verbatim = None;
+    let mut comment = false;
+    let mut chars = text.char_indices();
+
+    let hide = |masked: &mut String, c: char| {
+        masked.extend((0..c.len_utf8()).map(|_| '\0'));
+    };
+
+    while let Some((index, c)) = chars.next() {
+        if comment || verbatim.is_some() {
+            if c == '\n' {
+                comment = false;
+            }
+
+            if verbatim == Some(c) {
+                verbatim = None;
+            }
+
+            if c == '\n' {
+                masked.push(c);
+            } else {
+                hide(&mut masked, c);
+            }
+        } else if c == '\\' {
+            hide(&mut masked, c);
+            if let Some((_, escaped)) = chars.next() {
+                hide(&mut masked, escaped);
+            }
+        } else if c == '`' || c == '$' {
+            verbatim = Some(c);
+            hide(&mut masked, c);
+        } else if text[index..].starts_with("||") {
+            comment = true;
+            hide(&mut masked, c);
+        } else {
+            masked.push(c);
+        }
+    }
+
+    masked
+}
+
+/// Returns the source of a bloc of an ast, from the beginning of its first line to the end of its
+/// last line.
+fn source<'a>(input: Span<'a>, bloc: &Node) -> Span<'a> {
+    let text = input.fragment.0;
+    let start = bloc.span.start.offset - input.offset;
+    let end = bloc.span.end.offset - input.offset;
+
+    let start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+
+    input.slice(start..end)
+}
+
+/// Collects the offsets of the comments of an ast.
+struct Comments(Vec<usize>);
+
+impl Visit for Comments {
+    fn visit_node(&mut self, node: &Node) {
+        match node.ast {
+            Ast::Comment(_) => self.0.push(node.span.start.offset),
+            _ => visit::visit_node(self, node),
+        }
+    }
+}
+
+/// Returns the names given by the allow comments of a bloc, with their offsets in the source.
+fn allowed_names<'a>(input: Span<'a>, bloc: &Node) -> Vec<(usize, &'a str)> {
+    let text = input.fragment.0;
+    let mut names = vec![];
+
+    let mut comments = Comments(vec![]);
+    comments.visit_node(bloc);
+
+    for comment in comments.0 {
+        let start = comment - input.offset + 2;
+        let end = match text[start..].find('\n') {
+            Some(i) => start + i,
+            None => text.len(),
+        };
+
+        let comment = text[start..end].trim_start();
+        let start = end - comment.len();
+
+        let list = match comment.strip_prefix("allow(") {
+            Some(list) => list,
+            None => continue,
+        };
+
+        let list = match list.find(')') {
+            Some(i) => &list[..i],
+            None => continue,
+        };
+
+        let mut offset = start + "allow(".len();
+        for name in list.split(',') {
+            let trimmed = name.trim();
+            if !trimmed.is_empty() {
+                names.push((offset + name.find(trimmed).unwrap_or(0), trimmed));
+            }
+            offset += name.len() + 1;
+        }
+    }
+
+    names
+}
+
+/// Returns the allow comments of a file, given its source and its ast.
+pub fn allow_comments(input: Span, ast: &Ast) -> Vec<Allow> {
+    let mut allows = vec![];
+
+    for bloc in ast.children() {
+        if !is_prose(&bloc.ast) {
+            continue;
+        }
+
+        let lints = allowed_names(input, bloc)
+            .into_iter()
+            .filter_map(|(_, name)| WarningType::from_name(name))
+            .collect::<Vec<_>>();
+
+        if !lints.is_empty() {
+            let bloc = source(input, bloc);
+            allows.push(Allow {
+                range: bloc.offset..bloc.offset + bloc.fragment.0.len(),
+                lints,
+            });
+        }
+    }
+
+    allows
+}
+
+/// Checks the trailing whitespaces of the lines of a file.
+fn trailing_whitespace(input: Span, warnings: &mut Vec<EmptyWarning>) {
+    let mut start = 0;
+
+    for line in input.fragment.0.split('\n') {
+        let trimmed = line.trim_end_matches([' ', '\t']);
+
+        if trimmed.len() < line.len() {
+            warnings.push(warning(
+                input,
+                start + trimmed.len(),
+                WarningType::TrailingWhitespace,
+            ));
+        }
+
+        start += line.len() + 1;
+    }
+}
+
+/// Checks the spaces between the words of some masked prose.
+///
+/// The spaces at the beginning of a line, after its indentation and its markers, are not checked.
+fn doubled_spaces(bloc: Span, masked: &str, warnings: &mut Vec<EmptyWarning>) {
+    let mut start = 0;
+
+    for line in masked.split('\n') {
+        let content = line.trim_end_matches([' ', '\t']);
+        let markers = content
+            .find(|x: char| !(x.is_whitespace() || x.is_ascii_digit() || "#-.".contains(x)))
+            .unwrap_or(content.len());
+
+        let mut index = markers;
+        while let Some(i) = content[index..].find("  ") {
+            let run = index + i;
+            warnings.push(warning(bloc, start + run, WarningType::DoubledSpaces));
+            index = run
+                + content[run..]
+                    .find(|x| x != ' ')
+                    .unwrap_or(content.len() - run);
+        }
+
+        start += line.len() + 1;
+    }
+}
+
+/// Checks the words of some masked prose that are written twice in a row.
+fn repeated_words(bloc: Span, masked: &str, warnings: &mut Vec<EmptyWarning>) {
+    let mut previous: Option<(usize, usize)> = None;
+    let mut word_start = None;
+
+    for (index, c) in masked.char_indices().chain(Some((masked.len(), '\0'))) {
+        match (word_start, c.is_alphabetic()) {
+            (None, true) => word_start = Some(index),
+            (Some(start), false) => {
+                if let Some((previous_start, previous_end)) = previous {
+                    let separator = &masked[previous_end..start];
+                    let repeated = !separator.is_empty()
+                        && separator.chars().all(char::is_whitespace)
+                        && masked[previous_start..previous_end].to_lowercase()
+                            == masked[start..index].to_lowercase();
+
+                    if repeated {
+                        warnings.push(warning(bloc, start, WarningType::RepeatedWord));
+                    }
+                }
+
+                previous = Some((start, index));
+                word_start = None;
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Checks the source of a file, given its ast, and returns the warnings of the lints.
+///
+/// The warnings of the allow comments are not removed, see `allow_comments`.
+pub fn lint(input: Span, ast: &Ast) -> Vec<EmptyWarning> {
+    let mut warnings = vec![];
+    let mut previous_level = None;
+
+    trailing_whitespace(input, &mut warnings);
+
+    for bloc in ast.children() {
+        let source = source(input, bloc);
+
+        match &bloc.ast {
+            Ast::Title { level, .. } => {
+                if previous_level.is_some_and(|previous| *level > previous + 1) {
+                    warnings.push(warning(source, 0, WarningType::SkippedTitleLevel));
+                }
+                previous_level = Some(*level);
+            }
+
+            Ast::Paragraph(children) => {
+                let empty = children.iter().all(|child| match &child.ast {
+                    Ast::Text(text) => text.trim().is_empty(),
+                    _ => false,
+                });
+
+                // The only paragraph of an empty file has no source.
+                if empty && !source.fragment.0.is_empty() {
+                    warnings.push(warning(source, 0, WarningType::EmptyParagraph));
+                }
+
+                if source.fragment.0.split_whitespace().count() > LONG_PARAGRAPH_WORDS {
+                    warnings.push(warning(source, 0, WarningType::LongParagraph));
+                }
+            }
+
+            _ => (),
+        }
+
+        if !is_prose(&bloc.ast) {
+            continue;
+        }
+
+        let masked = mask(source.fragment.0);
+        doubled_spaces(source, &masked, &mut warnings);
+        repeated_words(source, &masked, &mut warnings);
+
+        for (offset, name) in allowed_names(input, bloc) {
+            if WarningType::from_name(name).is_none() {
+                warnings.push(warning(input, offset, WarningType::UnknownLint));
+            }
+        }
+    }
+
+    warnings.sort_by_key(|x| x.position.offset);
+    warnings
+}
@@ -0,0 +1,1043 @@
+//! This module contains a reader for Markdown files, which produces the same ast as the dex files.
+//!
+//! It reads the subset of CommonMark that the dex files can express: headings, paragraphs,
+//! emphasis, strong emphasis, code spans, code blocks, lists, links and block quotes, whose content
+//! is read as if it was not quoted. Images alone in a paragraph become figures, and headings can
+//! end with a label, e.g. `# Introduction {#intro}`. Thematic breaks are ignored.
+//!
+//! As in dex, the content of a list item is a single paragraph followed by the nested lists: the
+//! paragraphs of an item are joined, its code blocks become code spans, and only the text of its
+//! headings and figures is kept.
+//!
+//! Like in pandoc, `~~struck~~`, `^superscript^` and `~subscript~` are supported, and so are
+//! spans of text in a style, e.g. `[NASA]{.smallcaps}`.
+
+use std::path::Path;
+
+use crate::ligature::ligature;
+use crate::parser::ast::{Ast, Node};
+use crate::parser::combinators::is_label_char;
+use crate::parser::{Position, SourceSpan};
+
+/// Returns true if a path has the extension of a Markdown file.
+pub fn is_markdown<P: AsRef<Path>>(path: P) -> bool {
+    match path.as_ref().extension().and_then(|x| x.to_str()) {
+        Some(extension) => extension == "md" || extension == "markdown",
+        None => false,
+    }
+}
+
+/// Parses the content of a Markdown file, starting from an offset.
+pub fn parse(content: &str, start: usize) -> Ast {
+    let mut lines = vec![];
+    let mut offset = start;
+
+    for text in content[start..].split('\n') {
+        lines.push(Line {
+            text,
+            start: offset,
+        });
+        offset += text.len() + 1;
+    }
+
+    Ast::Group(Reader::new(content).blocks(&lines))
+}
+
+/// A line of a Markdown file, or what remains of it once the markers of its containers are
+/// removed.
+#[derive(Copy, Clone)]
+struct Line<'a> {
+    /// The text of the line.
+    text: &'a str,
+
+    /// The offset of the text from the beginning of the file.
+    start: usize,
+}
+
+impl<'a> Line<'a> {
+    /// Returns the number of columns of whitespace at the beginning of the line, tabulations
+    /// counting as four columns.
+    fn indent(&self) -> usize {
+        self.text
+            .chars()
+            .take_while(|x| *x == ' ' || *x == '\t')
+            .map(|x| if x == '\t' { 4 } else { 1 })
+            .sum()
+    }
+
+    /// Returns true if the line contains only whitespace.
+    fn is_blank(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    /// Removes the beginning of the line.
+    fn skip(&self, bytes: usize) -> Line<'a> {
+        let bytes = bytes.min(self.text.len());
+        Line {
+            text: &self.text[bytes..],
+            start: self.start + bytes,
+        }
+    }
+
+    /// Removes up to some columns of whitespace at the beginning of the line.
+    fn strip(&self, columns: usize) -> Line<'a> {
+        let mut stripped = 0;
+        let mut bytes = 0;
+
+        for c in self.text.chars() {
+            if stripped >= columns || (c != ' ' && c != '\t') {
+                break;
+            }
+            stripped += if c == '\t' { 4 } else { 1 };
+            bytes += 1;
+        }
+
+        self.skip(bytes)
+    }
+
+    /// Removes the whitespace at the beginning of the line.
+    fn trim_start(&self) -> Line<'a> {
+        self.skip(self.text.len() - self.text.trim_start().len())
+    }
+}
+
+/// A list marker, e.g. `-` or `1.`.
+#[derive(Copy, Clone)]
+struct Marker {
+    /// Whether the list is numbered.
+    ordered: bool,
+
+    /// The bullet, or the delimiter after the number, which must be the same for all the items of
+    /// a list.
+    delimiter: char,
+
+    /// The number of the item, or 1 for bullets.
+    number: usize,
+
+    /// The number of bytes before the content of the item.
+    width: usize,
+
+    /// Whether the item has no content on the line of the marker.
+    empty: bool,
+}
+
+/// Returns the list marker at the beginning of a line, if any.
+fn list_marker(line: Line) -> Option<Marker> {
+    if line.indent() > 3 {
+        return None;
+    }
+
+    let indent = line.text.len() - line.text.trim_start().len();
+    let text = &line.text[indent..];
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+
+    let (ordered, delimiter, number, length) = match text.chars().next()? {
+        c @ '-' | c @ '+' | c @ '*' => (false, c, 1, 1),
+        _ if digits > 0 && digits < 10 => match text[digits..].chars().next()? {
+            c @ '.' | c @ ')' => (true, c, text[..digits].parse().ok()?, digits + 1),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let rest = &text[length..];
+    if !rest.is_empty() && !rest.starts_with(' ') && !rest.starts_with('\t') {
+        return None;
+    }
+
+    let empty = rest.trim().is_empty();
+    let spaces = rest.len() - rest.trim_start().len();
+
+    // Content indented by more than four spaces is a code block inside the item.
+    let spaces = if empty || spaces > 4 { 1 } else { spaces };
+
+    Some(Marker {
+        ordered,
+        delimiter,
+        number,
+        width: indent + length + spaces,
+        empty,
+    })
+}
+
+/// Returns the character and the length of the fence opening a code block, and the language
+/// given after it, if any.
+fn fence(line: Line<'_>) -> Option<(char, usize, &str)> {
+    if line.indent() > 3 {
+        return None;
+    }
+
+    let text = line.text.trim_start();
+    let c = text.chars().next()?;
+    if c != '`' && c != '~' {
+        return None;
+    }
+
+    let length = text.chars().take_while(|x| *x == c).count();
+    let info = text[length..].trim();
+
+    if length < 3 || (c == '`' && info.contains('`')) {
+        return None;
+    }
+
+    Some((c, length, info))
+}
+
+/// Returns true if a line closes a code block opened by a fence.
+fn is_closing_fence(line: Line, c: char, length: usize) -> bool {
+    let text = line.text.trim();
+    line.indent() <= 3 && text.len() >= length && text.chars().all(|x| x == c)
+}
+
+/// Returns the level and the content of a heading written with hashes, if any.
+fn atx_heading(line: Line<'_>) -> Option<(u8, Line<'_>)> {
+    if line.indent() > 3 {
+        return None;
+    }
+
+    let line = line.trim_start();
+    let level = line.text.chars().take_while(|x| *x == '#').count();
+    let rest = &line.text[level..];
+
+    if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+
+    // The closing hashes are optional.
+    let content = line.skip(level).trim_start();
+    let mut text = content.text.trim_end();
+    let without_hashes = text.trim_end_matches('#');
+    if without_hashes.is_empty() || without_hashes.ends_with(' ') {
+        text = without_hashes.trim_end();
+    }
+
+    Some((
+        level as u8,
+        Line {
+            text,
+            start: content.start,
+        },
+    ))
+}
+
+/// Returns true if a line is a thematic break, e.g. `***` or `- - -`.
+fn is_thematic_break(line: Line) -> bool {
+    let text = line.text.trim();
+    let c = match text.chars().next() {
+        Some(c @ '*') | Some(c @ '-') | Some(c @ '_') => c,
+        _ => return false,
+    };
+
+    line.indent() <= 3
+        && text.chars().filter(|x| *x == c).count() >= 3
+        && text.chars().all(|x| x == c || x == ' ' || x == '\t')
+}
+
+/// Returns the level of the heading underlined by a line, e.g. `===` or `---`, if any.
+fn setext_underline(line: Line) -> Option<u8> {
+    let text = line.text.trim();
+
+    if line.indent() > 3 || text.is_empty() {
+        None
+    } else if text.chars().all(|x| x == '=') {
+        Some(1)
+    } else if text.chars().all(|x| x == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Returns true if a line starts a block that interrupts a paragraph.
+fn interrupts_paragraph(line: Line) -> bool {
+    let quote = line.indent() <= 3 && line.text.trim_start().starts_with('>');
+    let list = match list_marker(line) {
+        Some(marker) => !marker.empty && (!marker.ordered || marker.number == 1),
+        None => false,
+    };
+
+    quote || list || fence(line).is_some() || atx_heading(line).is_some() || is_thematic_break(line)
+}
+
+/// The text of a paragraph or of a heading, along with the offsets of its lines in the file.
+struct Text {
+    /// The lines of the text, separated by line breaks.
+    content: String,
+
+    /// The offset of each line in the text, and its offset in the file.
+    offsets: Vec<(usize, usize)>,
+}
+
+impl Text {
+    /// Joins some lines, without their leading whitespace.
+    fn new(lines: &[Line]) -> Text {
+        let mut content = String::new();
+        let mut offsets = vec![];
+
+        for line in lines {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            let line = line.trim_start();
+            offsets.push((content.len(), line.start));
+            content.push_str(line.text);
+        }
+
+        let length = content.trim_end().len();
+        content.truncate(length);
+
+        Text { content, offsets }
+    }
+
+    /// Returns the offset in the file of an offset in the text.
+    fn offset(&self, offset: usize) -> usize {
+        let (text, file) = self
+            .offsets
+            .iter()
+            .rev()
+            .find(|(text, _)| *text <= offset)
+            .cloned()
+            .unwrap_or((0, 0));
+        file + offset - text
+    }
+}
+
+/// The reader of a Markdown file.
+struct Reader<'a> {
+    /// The content of the file.
+    content: &'a str,
+
+    /// The offset of the beginning of each line of the file.
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Reader<'a> {
+    /// Creates a reader for the content of a file.
+    fn new(content: &'a str) -> Reader<'a> {
+        let line_starts = Some(0)
+            .into_iter()
+            .chain(content.match_indices('\n').map(|(x, _)| x + 1))
+            .collect();
+
+        Reader {
+            content,
+            line_starts,
+        }
+    }
+
+    /// Returns the position of an offset in the file.
+    fn position(&self, offset: usize) -> Position {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let start = self.line_starts[line];
+
+        Position {
+            line: line as u32 + 1,
+            column: self.content[start..offset].chars().count() + 1,
+            offset,
+        }
+    }
+
+    /// Returns the span between two offsets of the file.
+    fn source_span(&self, start: usize, end: usize) -> SourceSpan {
+        SourceSpan {
+            start: self.position(start),
+            end: self.position(end),
+        }
+    }
+
+    /// Returns the span of some lines, without their leading whitespace and their trailing blank
+    /// lines.
+    fn lines_span(&self, lines: &[Line]) -> SourceSpan {
+        let last = lines
+            .iter()
+            .rev()
+            .find(|x| !x.is_blank())
+            .unwrap_or(&lines[0]);
+
+        self.source_span(
+            lines[0].trim_start().start,
+            last.start + last.text.trim_end().len(),
+        )
+    }
+
+    /// Creates a node from the part of a text between two offsets.
+    fn node(&self, text: &Text, start: usize, end: usize, ast: Ast) -> Node {
+        Node::new(ast, self.source_span(text.offset(start), text.offset(end)))
+    }
+
+    /// Parses the blocks of some lines.
+    fn blocks(&self, lines: &[Line]) -> Vec<Node> {
+        let mut blocks = vec![];
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i];
+
+            if line.is_blank() {
+                i += 1;
+            } else if line.indent() >= 4 {
+                let mut end = i;
+                while end < lines.len() && (lines[end].is_blank() || lines[end].indent() >= 4) {
+                    end += 1;
+                }
+                let mut last = end;
+                while lines[last - 1].is_blank() {
+                    last -= 1;
+                }
+
+                let content = lines[i..last]
+                    .iter()
+                    .map(|x| x.strip(4).text)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                let block = Ast::CodeBlock {
+                    language: None,
+                    content,
+                };
+                blocks.push(Node::new(block, self.lines_span(&lines[i..last])));
+                i = end;
+            } else if let Some((c, length, info)) = fence(line) {
+                let indent = line.indent();
+                let mut end = i + 1;
+                while end < lines.len() && !is_closing_fence(lines[end], c, length) {
+                    end += 1;
+                }
+
+                let content = lines[i + 1..end]
+                    .iter()
+                    .map(|x| x.strip(indent).text)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                let block = Ast::CodeBlock {
+                    language: info.split_whitespace().next().map(String::from),
+                    content,
+                };
+                let span = self.lines_span(&lines[i..(end + 1).min(lines.len())]);
+                blocks.push(Node::new(block, span));
+                i = end + 1;
+            } else if let Some((level, content)) = atx_heading(line) {
+                let heading = self.heading(level, &[content]);
+                blocks.push(Node::new(heading, self.lines_span(&[line])));
+                i += 1;
+            } else if is_thematic_break(line) {
+                i += 1;
+            } else if line.text.trim_start().starts_with('>') {
+                let mut quoted = vec![];
+
+                while i < lines.len() && !lines[i].is_blank() {
+                    let line = lines[i].trim_start();
+                    quoted.push(match line.text.strip_prefix('>') {
+                        Some(rest) if rest.starts_with(' ') => line.skip(2),
+                        Some(_) => line.skip(1),
+                        None => line,
+                    });
+                    i += 1;
+                }
+
+                blocks.extend(self.blocks(&quoted));
+            } else if let Some(marker) = list_marker(line) {
+                let (list, end) = self.list(lines, i, marker);
+                blocks.push(list);
+                i = end;
+            } else {
+                let mut end = i + 1;
+                let mut underline = None;
+
+                while end < lines.len() && !lines[end].is_blank() {
+                    underline = setext_underline(lines[end]);
+                    if underline.is_some() || interrupts_paragraph(lines[end]) {
+                        break;
+                    }
+                    end += 1;
+                }
+
+                match underline {
+                    Some(level) => {
+                        let heading = self.heading(level, &lines[i..end]);
+                        blocks.push(Node::new(heading, self.lines_span(&lines[i..=end])));
+                        i = end + 1;
+                    }
+                    None => {
+                        let paragraph = self.paragraph(&lines[i..end]);
+                        blocks.push(Node::new(paragraph, self.lines_span(&lines[i..end])));
+                        i = end;
+                    }
+                }
+            }
+        }
+
+        blocks
+    }
+
+    /// Parses a heading, whose content can end with a label.
+    fn heading(&self, level: u8, lines: &[Line]) -> Ast {
+        let text = Text::new(lines);
+        let mut length = text.content.len();
+        let mut label = None;
+
+        if text.content.ends_with('}') {
+            if let Some(start) = text.content.rfind("{#") {
+                let name = &text.content[start + 2..length - 1];
+                if !name.is_empty() && name.chars().all(is_label_char) {
+                    let ast = Ast::Label {
+                        name: name.into(),
+                        position: self.position(text.offset(start)),
+                    };
+                    label = Some(self.node(&text, start, text.content.len(), ast));
+                    length = start;
+                }
+            }
+        }
+
+        let mut content = self.inline(&text, 0, length);
+        content.extend(label);
+
+        Ast::Title {
+            level: level - 1,
+            content: Box::new(self.node(&text, 0, text.content.len(), Ast::Group(content))),
+        }
+    }
+
+    /// Parses a paragraph, which is a figure if it contains only an image.
+    fn paragraph(&self, lines: &[Line]) -> Ast {
+        let text = Text::new(lines);
+
+        if text.content.starts_with("![") {
+            if let Some((alt, url, end)) = self.link(&text, 1, text.content.len()) {
+                if end == text.content.len() {
+                    let caption = self.inline(&text, alt.0, alt.1);
+
+                    return Ast::Figure {
+                        path: url,
+                        width: None,
+                        caption: if caption.is_empty() {
+                            None
+                        } else {
+                            let caption = Ast::Group(caption);
+                            Some(Box::new(self.node(&text, alt.0, alt.1, caption)))
+                        },
+                        position: self.position(text.offset(0)),
+                    };
+                }
+            }
+        }
+
+        Ast::Paragraph(self.inline(&text, 0, text.content.len()))
+    }
+
+    /// Parses a list, and returns it with the index of the line that follows it.
+    fn list(&self, lines: &[Line], start: usize, first: Marker) -> (Node, usize) {
+        let mut items = vec![];
+        let mut i = start;
+
+        while i < lines.len() {
+            let marker = match list_marker(lines[i]) {
+                Some(marker)
+                    if marker.ordered == first.ordered && marker.delimiter == first.delimiter =>
+                {
+                    marker
+                }
+                _ => break,
+            };
+
+            let marker_line = lines[i];
+            let mut item = vec![lines[i].skip(marker.width)];
+            let mut previous_blank = marker.empty;
+            i += 1;
+
+            while i < lines.len() {
+                let line = lines[i];
+
+                if line.is_blank() {
+                    item.push(line);
+                    previous_blank = true;
+                } else if line.indent() >= marker.width {
+                    item.push(line.strip(marker.width));
+                    previous_blank = false;
+                } else if !previous_blank
+                    && !interrupts_paragraph(line)
+                    && list_marker(line).is_none()
+                {
+                    // A lazy continuation line of the paragraph of the item.
+                    item.push(line.trim_start());
+                } else {
+                    break;
+                }
+
+                i += 1;
+            }
+
+            let mut item = self.list_item(&item);
+            item.span.start = self.position(marker_line.trim_start().start);
+            items.push(item);
+        }
+
+        let list = Ast::List {
+            ordered: first.ordered,
+            start: first.number,
+            items,
+        };
+
+        (Node::new(list, self.lines_span(&lines[start..i])), i)
+    }
+
+    /// Parses the lines of an item of a list.
+    ///
+    /// The nested lists are the children of the item, and the text of its other blocks is
+    /// joined into its content.
+    fn list_item(&self, lines: &[Line]) -> Node {
+        let mut content: Vec<Node> = vec![];
+        let mut children = vec![];
+
+        for block in self.blocks(lines) {
+            if let Ast::List { .. } = block.ast {
+                children.push(block);
+                continue;
+            }
+
+            let span = block.span;
+            let inline = match block.ast {
+                Ast::Paragraph(inline) => inline,
+                Ast::Title { content, .. } => vec![*content],
+                Ast::CodeBlock { content, .. } => vec![Node::new(Ast::Code(content), span)],
+                Ast::Figure {
+                    caption: Some(caption),
+                    ..
+                } => vec![*caption],
+                _ => continue,
+            };
+
+            if !content.is_empty() {
+                content.push(Ast::Text(" ".into()).into());
+            }
+            content.extend(inline);
+        }
+
+        let span = self.lines_span(lines);
+        let content_span = match (content.first(), content.last()) {
+            (Some(first), Some(last)) => SourceSpan {
+                start: first.span.start,
+                end: last.span.end,
+            },
+            _ => span,
+        };
+
+        let item = Ast::ListItem {
+            content: Box::new(Node::new(Ast::Group(content), content_span)),
+            children,
+        };
+
+        Node::new(item, span)
+    }
+
+    /// Parses the inline content of a part of a text.
+    fn inline(&self, text: &Text, start: usize, end: usize) -> Vec<Node> {
+        let content = &text.content;
+        let mut nodes = vec![];
+        let mut buffer = String::new();
+        let mut buffer_start = start;
+        let mut i = start;
+
+        let flush = |buffer: &mut String, nodes: &mut Vec<Node>, start: usize, end: usize| {
+            if !buffer.is_empty() {
+                nodes.push(self.node(text, start, end, Ast::Text(ligature(buffer))));
+                buffer.clear();
+            }
+        };
+
+        while i < end {
+            let rest = &content[i..end];
+            let c = rest.chars().next().unwrap_or(' ');
+
+            match c {
+                '\\' => match rest[1..].chars().next() {
+                    Some(next) if next.is_ascii_punctuation() => {
+                        flush(&mut buffer, &mut nodes, buffer_start, i);
+                        let escaped = Ast::Escaped(next.to_string());
+                        nodes.push(self.node(text, i, i + 2, escaped));
+                        i += 2;
+                        buffer_start = i;
+                        continue;
+                    }
+                    Some('\n') => {
+                        buffer.push('\n');
+                        i += 2;
+                        continue;
+                    }
+                    _ => (),
+                },
+
+                '`' => {
+                    let length = run(rest, '`');
+                    match code_span(content, i, end) {
+                        Some((code, next)) => {
+                            flush(&mut buffer, &mut nodes, buffer_start, i);
+                            nodes.push(self.node(text, i, next, Ast::Code(code)));
+                            i = next;
+                            buffer_start = i;
+                        }
+                        None => {
+                            buffer.push_str(&rest[..length]);
+                            i += length;
+                        }
+                    }
+                    continue;
+                }
+
+                '*' | '_' => {
+                    let length = run(rest, c);
+                    match self.emphasis(text, i, end) {
+                        Some((node, next)) => {
+                            flush(&mut buffer, &mut nodes, buffer_start, i);
+                            nodes.push(node);
+                            i = next;
+                            buffer_start = i;
+                        }
+                        None => {
+                            buffer.push_str(&rest[..length]);
+                            i += length;
+                        }
+                    }
+                    continue;
+                }
+
+                // Images in the middle of a paragraph are replaced by their description.
+                '!' if rest[1..].starts_with('[') => {
+                    if let Some((alt, _, next)) = self.link(text, i + 1, end) {
+                        flush(&mut buffer, &mut nodes, buffer_start, i);
+                        nodes.extend(self.inline(text, alt.0, alt.1));
+                        i = next;
+                        buffer_start = i;
+                        continue;
+                    }
+                }
+
+                '[' => {
+                    if let Some((inner, url, next)) = self.link(text, i, end) {
+                        flush(&mut buffer, &mut nodes, buffer_start, i);
+                        let group = Ast::Group(self.inline(text, inner.0, inner.1));
+                        let link = Ast::Link {
+                            url,
+                            content: Box::new(self.node(text, inner.0, inner.1, group)),
+                            position: self.position(text.offset(i)),
+                        };
+                        nodes.push(self.node(text, i, next, link));
+                        i = next;
+                        buffer_start = i;
+                        continue;
+                    }
+
+                    if let Some((inner, style, next)) = span(content, i, end) {
+                        flush(&mut buffer, &mut nodes, buffer_start, i);
+                        let group = Ast::Group(self.inline(text, inner.0, inner.1));
+                        let group = self.node(text, inner.0, inner.1, group);
+                        nodes.push(self.node(text, i, next, styled(style, group)));
+                        i = next;
+                        buffer_start = i;
+                        continue;
+                    }
+                }
+
+                '~' | '^' => {
+                    let delimiter = if rest.starts_with("~~") {
+                        "~~"
+                    } else {
+                        &rest[..1]
+                    };
+                    if let Some((inner, next)) = delimited(content, i, end, delimiter) {
+                        flush(&mut buffer, &mut nodes, buffer_start, i);
+                        let group = Ast::Group(self.inline(text, inner.0, inner.1));
+                        let group = Box::new(self.node(text, inner.0, inner.1, group));
+                        let ast = match delimiter {
+                            "~~" => Ast::Strikethrough(group),
+                            "~" => Ast::Subscript(group),
+                            _ => Ast::Superscript(group),
+                        };
+                        nodes.push(self.node(text, i, next, ast));
+                        i = next;
+                        buffer_start = i;
+                        continue;
+                    }
+                }
+
+                '<' => {
+                    if let Some(length) = rest.find('>') {
+                        let url = &rest[1..length];
+                        if (url.contains(':') || url.contains('@'))
+                            && !url.contains(|x: char| x.is_whitespace() || x == '<')
+                        {
+                            flush(&mut buffer, &mut nodes, buffer_start, i);
+                            let link = Ast::Link {
+                                url: if url.contains(':') {
+                                    url.into()
+                                } else {
+                                    format!("mailto:{}", url)
+                                },
+                                content: Box::new(self.node(
+                                    text,
+                                    i + 1,
+                                    i + length,
+                                    Ast::Text(url.into()),
+                                )),
+                                position: self.position(text.offset(i)),
+                            };
+                            nodes.push(self.node(text, i, i + length + 1, link));
+                            i += length + 1;
+                            buffer_start = i;
+                            continue;
+                        }
+                    }
+                }
+
+                _ => (),
+            }
+
+            buffer.push(c);
+            i += c.len_utf8();
+        }
+
+        flush(&mut buffer, &mut nodes, buffer_start, end);
+        nodes
+    }
+
+    /// Parses an emphasis starting at an offset of a text, and returns it with the offset that
+    /// follows it.
+    ///
+    /// One delimiter is an emphasis, two are a strong emphasis, and three are both.
+    fn emphasis(&self, text: &Text, start: usize, end: usize) -> Option<(Node, usize)> {
+        let content = &text.content;
+        let c = content[start..].chars().next()?;
+        let length = run(&content[start..end], c);
+        let after = content[start + length..end].chars().next()?;
+        let before = content[..start].chars().next_back();
+
+        if length > 3
+            || after.is_whitespace()
+            || (c == '_' && before.is_some_and(char::is_alphanumeric))
+        {
+            return None;
+        }
+
+        let mut i = start + length;
+        while i < end {
+            let rest = &content[i..end];
+            let current = rest.chars().next()?;
+
+            if current == '\\' {
+                i += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+                continue;
+            }
+
+            if current == '`' {
+                i = match code_span(content, i, end) {
+                    Some((_, next)) => next,
+                    None => i + run(rest, '`'),
+                };
+                continue;
+            }
+
+            if current == c {
+                let closing = run(rest, c);
+                let before = content[..i].chars().next_back();
+                let after = rest[closing..].chars().next();
+
+                if closing == length
+                    && i > start + length
+                    && !before.is_none_or(char::is_whitespace)
+                    && !(c == '_' && after.is_some_and(char::is_alphanumeric))
+                {
+                    let inner = Ast::Group(self.inline(text, start + length, i));
+                    let inner = Box::new(self.node(text, start + length, i, inner));
+                    let ast = match length {
+                        1 => Ast::Italic(inner),
+                        2 => Ast::Bold(inner),
+                        _ => {
+                            let italic = self.node(text, start + 2, i + 1, Ast::Italic(inner));
+                            let group = self.node(text, start + 2, i + 1, Ast::Group(vec![italic]));
+                            Ast::Bold(Box::new(group))
+                        }
+                    };
+                    return Some((self.node(text, start, i + length, ast), i + length));
+                }
+
+                i += closing;
+                continue;
+            }
+
+            i += current.len_utf8();
+        }
+
+        None
+    }
+
+    /// Parses a link starting at an offset of a text, e.g. `[spandex](https://example.com)`, and
+    /// returns the offsets of its content, its url and the offset that follows it.
+    fn link(
+        &self,
+        text: &Text,
+        start: usize,
+        end: usize,
+    ) -> Option<((usize, usize), String, usize)> {
+        let content = &text.content;
+        let close = closing_bracket(content, start, end)?;
+
+        if !content[close + 1..end].starts_with('(') {
+            return None;
+        }
+
+        let mut depth = 0;
+        let mut i = close + 2;
+        let mut parenthesis = None;
+
+        while i < end {
+            match content[i..].chars().next()? {
+                '\\' => i += 1,
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    parenthesis = Some(i);
+                    break;
+                }
+                ')' => depth -= 1,
+                _ => (),
+            }
+            i += content[i..].chars().next().map_or(1, char::len_utf8);
+        }
+
+        let parenthesis = parenthesis?;
+        let destination = content[close + 2..parenthesis].trim();
+
+        // The destination can be followed by a title, which is ignored.
+        let url = match destination.strip_prefix('<') {
+            Some(destination) => destination.split('>').next().unwrap_or(""),
+            None => destination.split_whitespace().next().unwrap_or(""),
+        };
+
+        Some(((start + 1, close), url.into(), parenthesis + 1))
+    }
+}
+
+/// Parses a span of text in a style starting at an offset of a text, e.g. `[NASA]{.smallcaps}`,
+/// and returns the offsets of its content, its style and the offset that follows it.
+fn span(content: &str, start: usize, end: usize) -> Option<((usize, usize), &str, usize)> {
+    let close = closing_bracket(content, start, end)?;
+    let rest = content[close + 1..end].strip_prefix("{.")?;
+    let length = rest.find('}')?;
+    let style = &rest[..length];
+
+    if style.is_empty() || !style.chars().all(char::is_alphanumeric) {
+        return None;
+    }
+
+    Some(((start + 1, close), style, close + 3 + length + 1))
+}
+
+/// Returns some content in a style, or the content itself if the style does not exist.
+fn styled(style: &str, content: Node) -> Ast {
+    let content = Box::new(content);
+
+    match style {
+        "smallcaps" => Ast::SmallCaps(content),
+        "underline" => Ast::Underline(content),
+        "strikethrough" => Ast::Strikethrough(content),
+        "superscript" => Ast::Superscript(content),
+        "subscript" => Ast::Subscript(content),
+        _ => content.ast,
+    }
+}
+
+/// Parses some content between two delimiters starting at an offset of a text, e.g.
+/// `~~struck~~`, and returns the offsets of the content with the offset that follows it.
+///
+/// The content cannot start or end with whitespace, and cannot contain any between single
+/// delimiters, e.g. `H~2~O` or `2^10^`.
+fn delimited(
+    content: &str,
+    start: usize,
+    end: usize,
+    delimiter: &str,
+) -> Option<((usize, usize), usize)> {
+    let inner_start = start + delimiter.len();
+    let inner_end = inner_start + content[inner_start..end].find(delimiter)?;
+    let inner = &content[inner_start..inner_end];
+    let single = delimiter.len() == 1;
+
+    if inner.is_empty()
+        || inner.starts_with(char::is_whitespace)
+        || inner.ends_with(char::is_whitespace)
+        || (single && inner.contains(char::is_whitespace))
+        || (single && content[inner_end + 1..].starts_with(delimiter))
+    {
+        return None;
+    }
+
+    Some(((inner_start, inner_end), inner_end + delimiter.len()))
+}
+
+/// Returns the offset of the bracket closing the one at an offset of a text, if any.
+fn closing_bracket(content: &str, start: usize, end: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start + 1;
+
+    while i < end {
+        let rest = &content[i..end];
+        match rest.chars().next()? {
+            '\\' => i += 1,
+            '`' => {
+                if let Some((_, next)) = code_span(content, i, end) {
+                    i = next;
+                    continue;
+                }
+            }
+            '[' => depth += 1,
+            ']' if depth == 0 => return Some(i),
+            ']' => depth -= 1,
+            _ => (),
+        }
+        i += content[i..].chars().next().map_or(1, char::len_utf8);
+    }
+
+    None
+}
+
+/// Returns the number of times a character is repeated at the beginning of a text.
+fn run(text: &str, c: char) -> usize {
+    text.chars().take_while(|x| *x == c).count() * c.len_utf8()
+}
+
+/// Parses a code span starting at an offset of a text, and returns its content with the offset
+/// that follows it.
+///
+/// The code span ends with as many backticks as it starts with.
+fn code_span(content: &str, start: usize, end: usize) -> Option<(String, usize)> {
+    let length = run(&content[start..end], '`');
+    let mut i = start + length;
+
+    while i < end {
+        let found = content[i..end].find('`')?;
+        i += found;
+
+        let closing = run(&content[i..end], '`');
+        if closing == length {
+            let code = content[start + length..i].replace('\n', " ");
+            let code = if code.len() > 1
+                && code.starts_with(' ')
+                && code.ends_with(' ')
+                && !code.trim().is_empty()
+            {
+                code[1..code.len() - 1].to_owned()
+            } else {
+                code
+            };
+            return Some((code, i + length));
+        }
+
+        i += closing;
+    }
+
+    None
+}
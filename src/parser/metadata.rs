@@ -0,0 +1,61 @@
+//! This module contains the metadata of a document, which are given in the front matter of its
+//! main file.
+
+/// The metadata of a document.
+///
+/// They are given at the top of the main dex file, between two lines of three dashes, e.g.
+///
+/// ```text
+/// ---
+/// title: A document
+/// authors: Ada Lovelace, Charles Babbage
+/// date: 1843-09-05
+/// language: en
+/// keywords: engine, notes
+/// ---
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The title of the document.
+    pub title: Option<String>,
+
+    /// The authors of the document.
+    pub authors: Vec<String>,
+
+    /// The date of the document.
+    pub date: Option<String>,
+
+    /// The language of the document, e.g. `en` or `fr-FR`.
+    pub language: Option<String>,
+
+    /// The keywords of the document.
+    pub keywords: Vec<String>,
+}
+
+impl Metadata {
+    /// Sets the value of a key of the front matter, and returns false if the key is unknown.
+    ///
+    /// Authors and keywords are lists separated by commas.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "title" => self.title = Some(value.into()),
+            "author" | "authors" => self.authors = list(value),
+            "date" => self.date = Some(value.into()),
+            "lang" | "language" => self.language = Some(value.into()),
+            "keywords" => self.keywords = list(value),
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+/// Splits a list separated by commas.
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+        .collect()
+}
@@ -3,29 +3,44 @@
 pub mod ast;
 pub mod combinators;
 pub mod error;
+pub mod format;
+pub mod lint;
+pub mod markdown;
+pub mod metadata;
 pub mod utils;
+pub mod visit;
 pub mod warning;
 
 #[cfg(test)]
 mod tests;
 
-use std::fs::File;
+use std::collections::HashSet;
+use std::fs::{self, File};
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
 use nom::types::CompleteStr;
 use nom_locate::LocatedSpan;
+use printpdf::image::{self, ImageError};
+use serde::{Deserialize, Serialize};
 
+use crate::bibliography::Bibliography;
 use crate::parser::ast::Ast;
-use crate::parser::error::Errors;
-use crate::parser::warning::Warnings;
+use crate::parser::error::{EmptyError, ErrorType, Errors};
+use crate::parser::lint::{Allow, LintLevel, Lints};
+use crate::parser::metadata::Metadata;
+use crate::parser::warning::{EmptyWarning, Warnings};
+use crate::smart::smart_typography;
 use crate::Error;
 
 /// This type will allow us to know where we are while we're parsing the content.
 pub type Span<'a> = LocatedSpan<CompleteStr<'a>>;
 
 /// A position is a span but without the reference to the complete str.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+///
+/// The default position, whose line and column are zero, is the position of the content that
+/// does not come from a file.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Position {
     /// The line number of the position.
     pub line: u32,
@@ -46,6 +61,35 @@ pub fn position<'a>(span: &Span<'a>) -> Position {
     }
 }
 
+/// The part of a file between two positions, which some content was parsed from.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    /// The position of the first character of the content.
+    pub start: Position,
+
+    /// The position right after the last character of the content.
+    pub end: Position,
+}
+
+/// Returns the source span of the content that was consumed from a span, given the span that
+/// remains.
+pub fn source_span<'a>(span: &Span<'a>, rest: &Span<'a>) -> SourceSpan {
+    SourceSpan {
+        start: position(span),
+        end: position(rest),
+    }
+}
+
+/// An include directive that lead to a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inclusion {
+    /// The path to the file containing the directive.
+    pub path: PathBuf,
+
+    /// The position of the directive in this file.
+    pub position: Position,
+}
+
 /// An ast that was successfully parsed.
 #[derive(Debug)]
 pub struct Parsed {
@@ -54,37 +98,376 @@ pub struct Parsed {
 
     /// The warnings that were produced.
     pub warnings: Warnings,
+
+    /// The metadata given in the front matter of the main file.
+    pub metadata: Metadata,
 }
 
-/// Parses a dex file.
+/// Parses a dex file and the files it includes, without bibliography.
 pub fn parse<P: AsRef<Path>>(path: P) -> Result<Parsed, Error> {
+    parse_with_bibliography(path, &Bibliography::default())
+}
+
+/// Parses a dex file and the files it includes, whose citations refer to the entries of a
+/// bibliography.
+pub fn parse_with_bibliography<P: AsRef<Path>>(
+    path: P,
+    bibliography: &Bibliography,
+) -> Result<Parsed, Error> {
+    parse_with_lints(path, bibliography, &Lints::default())
+}
+
+/// Parses a dex file and the files it includes, whose citations refer to the entries of a
+/// bibliography, and whose warnings are reported according to the levels of their lints.
+pub fn parse_with_lints<P: AsRef<Path>>(
+    path: P,
+    bibliography: &Bibliography,
+    lints: &Lints,
+) -> Result<Parsed, Error> {
+    if let Some(name) = lints.unknown() {
+        return Err(Error::UnknownLint(String::from(name)));
+    }
+
     let path = path.as_ref();
-    let mut file = File::open(&path)?;
+    let mut file = File::open(path)?;
     let mut content = String::new();
     file.read_to_string(&mut content)?;
 
-    let ast = match combinators::parse(Span::new(CompleteStr(&content))) {
-        Ok((_, ast)) => ast,
-        Err(_) => unreachable!(),
+    let mut loader = Loader {
+        lints: lints.clone(),
+        ..Loader::default()
     };
+    loader.visited.push(path.canonicalize()?);
+    let (ast, errors, warnings) = loader.load(path, content);
+    loader.finish(ast, errors, warnings, bibliography)
+}
 
-    let errors = ast.errors();
-    let warnings = ast.warnings();
+/// Checks an ast that was loaded by another tool or deserialized from JSON as if it was parsed
+/// from a dex file: its errors, labels, references, citations and images are checked, and its
+/// warnings are reported according to the levels of their lints.
+///
+/// The lints that check the source of files are not run since there is no source, and the paths
+/// of the images are relative to the current directory.
+pub fn check_ast<P: AsRef<Path>>(
+    path: P,
+    ast: Ast,
+    bibliography: &Bibliography,
+    lints: &Lints,
+) -> Result<Parsed, Error> {
+    if let Some(name) = lints.unknown() {
+        return Err(Error::UnknownLint(String::from(name)));
+    }
 
-    if errors.is_empty() {
-        Ok(Parsed {
-            ast,
-            warnings: Warnings {
-                path: PathBuf::from(&path),
-                warnings,
-                content,
-            },
-        })
-    } else {
-        Err(Error::DexError(Errors {
+    let path = path.as_ref();
+    let mut loader = Loader {
+        lints: lints.clone(),
+        loaded: true,
+        ..Loader::default()
+    };
+    loader.visited.push(path.canonicalize()?);
+    let (ast, errors, warnings) = loader.check(path, ast, String::new(), vec![], vec![], &[]);
+    loader.finish(ast, errors, warnings, bibliography)
+}
+
+/// The state of the parsing of a dex file and the files it includes.
+#[derive(Default)]
+struct Loader {
+    /// The include directives that lead to the file being loaded.
+    chain: Vec<Inclusion>,
+
+    /// The canonical paths of the files being loaded, which allows to detect cycles.
+    visited: Vec<PathBuf>,
+
+    /// The indices of the errors of the file being loaded in the tree of errors.
+    indices: Vec<usize>,
+
+    /// The labels defined so far.
+    labels: HashSet<String>,
+
+    /// The references found so far, with the indices of the errors of their file.
+    references: Vec<(Vec<usize>, String, Position)>,
+
+    /// The citations found so far, with the indices of the errors of their file.
+    citations: Vec<(Vec<usize>, String, Position)>,
+
+    /// The metadata given in the front matter of the main file.
+    metadata: Metadata,
+
+    /// The levels of the lints.
+    lints: Lints,
+
+    /// Whether the paths of the images are already relative to the current directory, as in the
+    /// ast of a loaded file.
+    loaded: bool,
+}
+
+impl Loader {
+    /// Parses the content of a dex or Markdown file and replaces its include directives by the
+    /// included files.
+    ///
+    /// Only the main file can start with a front matter, the front matter of an included file is an
+    /// error. The front matter of a Markdown file is often written for other tools, so its unknown
+    /// keys are not errors.
+    ///
+    /// The source of the file is checked by the lints, and the warnings are filtered by the allow
+    /// comments and the levels of their lints. The warnings of denied lints become errors.
+    fn load(&mut self, path: &Path, content: String) -> (Ast, Errors, Warnings) {
+        let span = Span::new(CompleteStr(&content));
+        let is_markdown = markdown::is_markdown(path);
+
+        let (span, front_matter_errors) = match combinators::parse_front_matter(span) {
+            Ok((rest, (metadata, errors))) if self.chain.is_empty() => {
+                self.metadata = metadata;
+                let errors = errors
+                    .into_iter()
+                    .filter(|x| !is_markdown || x.ty != ErrorType::UnknownMetadata)
+                    .collect();
+                (rest, errors)
+            }
+            Ok((rest, _)) => {
+                let error = EmptyError {
+                    position: position(&span),
+                    ty: ErrorType::IncludedFrontMatter,
+                };
+                (rest, vec![error])
+            }
+            Err(_) => (span, vec![]),
+        };
+
+        let mut ast = if is_markdown {
+            markdown::parse(&content, span.offset)
+        } else {
+            combinators::parse(span)
+        };
+
+        let linted = lint::lint(span, &ast);
+        let allows = lint::allow_comments(span, &ast);
+
+        smart_typography(&mut ast);
+
+        let source_errors = front_matter_errors
+            .into_iter()
+            .chain(combinators::control_characters(Span::new(CompleteStr(
+                &content,
+            ))))
+            .collect();
+
+        self.check(path, ast, content, source_errors, linted, &allows)
+    }
+
+    /// Checks a parsed ast, whose source has the errors and the warnings of the lints given as
+    /// parameters, and replaces its include directives by the included files.
+    fn check(
+        &mut self,
+        path: &Path,
+        mut ast: Ast,
+        content: String,
+        source_errors: Vec<EmptyError>,
+        linted: Vec<EmptyWarning>,
+        allows: &[Allow],
+    ) -> (Ast, Errors, Warnings) {
+        let mut errors = Errors {
+            path: PathBuf::from(&path),
+            content: content.clone(),
+            errors: source_errors.into_iter().chain(ast.errors()).collect(),
+            chain: self.chain.clone(),
+            children: vec![],
+        };
+
+        let mut warnings = Warnings {
             path: PathBuf::from(&path),
             content,
-            errors,
-        }))
+            warnings: vec![],
+            chain: self.chain.clone(),
+            children: vec![],
+        };
+
+        for warning in ast.warnings().into_iter().chain(linted) {
+            if allows.iter().any(|x| x.allows(&warning)) {
+                continue;
+            }
+
+            match self.lints.level(warning.ty) {
+                LintLevel::Allow => (),
+                LintLevel::Warn => warnings.warnings.push(warning),
+                LintLevel::Deny => errors.errors.push(EmptyError {
+                    position: warning.position,
+                    ty: ErrorType::Lint(warning.ty),
+                }),
+            }
+        }
+
+        warnings.warnings.sort_by_key(|w| w.position.offset);
+
+        for (label, position) in ast.labels() {
+            if !self.labels.insert(label) {
+                errors.errors.push(EmptyError {
+                    position,
+                    ty: ErrorType::DuplicateLabel,
+                });
+            }
+        }
+
+        for (label, position) in ast.references() {
+            self.references
+                .push((self.indices.clone(), label, position));
+        }
+
+        for (key, position) in ast.citations() {
+            self.citations.push((self.indices.clone(), key, position));
+        }
+
+        self.include(&mut ast, path, &mut errors, &mut warnings);
+        errors.errors.sort_by_key(|e| e.position.offset);
+
+        (ast, errors, warnings)
+    }
+
+    /// Replaces the include directives of an ast by the content of the included files, and checks
+    /// its images.
+    ///
+    /// The errors and the warnings of the included files are added as children of the ones of the
+    /// including file, even if there are none, so that their indices match the include directives.
+    fn include(
+        &mut self,
+        ast: &mut Ast,
+        path: &Path,
+        errors: &mut Errors,
+        warnings: &mut Warnings,
+    ) {
+        let (included, position) = match ast {
+            Ast::Group(children) => {
+                for child in children {
+                    self.include(child, path, errors, warnings);
+                }
+                return;
+            }
+
+            Ast::Include {
+                path: included,
+                position,
+            } => (included.clone(), *position),
+
+            Ast::Figure { .. } => {
+                self.check_image(ast, path, errors);
+                return;
+            }
+
+            _ => return,
+        };
+
+        let included = match path.parent() {
+            Some(parent) => parent.join(included),
+            None => PathBuf::from(included),
+        };
+
+        let read = included
+            .canonicalize()
+            .and_then(|canonical| Ok((canonical, fs::read_to_string(&included)?)));
+
+        let ty = match read {
+            Ok((canonical, _)) if self.visited.contains(&canonical) => ErrorType::IncludeCycle,
+
+            Ok((canonical, content)) => {
+                self.chain.push(Inclusion {
+                    path: PathBuf::from(path),
+                    position,
+                });
+                self.visited.push(canonical);
+                self.indices.push(errors.children.len());
+
+                // The included file is not loaded yet, even if the including ast is.
+                let loaded = std::mem::replace(&mut self.loaded, false);
+                let (child, child_errors, child_warnings) = self.load(&included, content);
+                self.loaded = loaded;
+
+                self.chain.pop();
+                self.visited.pop();
+                self.indices.pop();
+
+                errors.children.push(child_errors);
+                warnings.children.push(child_warnings);
+
+                *ast = child;
+                return;
+            }
+
+            Err(_) => ErrorType::IncludeNotFound,
+        };
+
+        let error = EmptyError { position, ty };
+        errors.errors.push(error.clone());
+        *ast = Ast::Error(error);
+    }
+
+    /// Makes the path of the image of a figure relative to the current directory, and checks that
+    /// the image exists and has a known format.
+    ///
+    /// Only the header of the image is read, it is decoded when the document is rendered.
+    fn check_image(&self, ast: &mut Ast, path: &Path, errors: &mut Errors) {
+        let (image, position) = match ast {
+            Ast::Figure { path, position, .. } => (path, *position),
+            _ => return,
+        };
+
+        let resolved = match path.parent() {
+            Some(parent) if !self.loaded => parent.join(&image),
+            _ => PathBuf::from(&image),
+        };
+
+        let ty = match image::image_dimensions(&resolved) {
+            Ok(_) => {
+                *image = resolved.to_string_lossy().into_owned();
+                return;
+            }
+            Err(ImageError::IoError(_)) => ErrorType::ImageNotFound,
+            Err(_) => ErrorType::InvalidImage,
+        };
+
+        let error = EmptyError { position, ty };
+        errors.errors.push(error.clone());
+        *ast = Ast::Error(error);
+    }
+
+    /// Adds the errors of the references to undefined labels and of the unknown citations to the
+    /// errors of a loaded ast, and returns it if there are no errors.
+    fn finish(
+        self,
+        ast: Ast,
+        mut errors: Errors,
+        warnings: Warnings,
+        bibliography: &Bibliography,
+    ) -> Result<Parsed, Error> {
+        let labels = self.labels;
+        let undefined_labels = self
+            .references
+            .into_iter()
+            .filter(|(_, label, _)| !labels.contains(label))
+            .map(|(indices, _, position)| (indices, position, ErrorType::UndefinedLabel));
+
+        let unknown_citations = self
+            .citations
+            .into_iter()
+            .filter(|(_, key, _)| bibliography.get(key).is_none())
+            .map(|(indices, _, position)| (indices, position, ErrorType::UnknownCitation));
+
+        for (indices, position, ty) in undefined_labels.chain(unknown_citations) {
+            let file = indices
+                .iter()
+                .fold(&mut errors, |errors, index| &mut errors.children[*index]);
+
+            file.errors.push(EmptyError { position, ty });
+            file.errors.sort_by_key(|e| e.position.offset);
+        }
+
+        if errors.is_empty() {
+            Ok(Parsed {
+                ast,
+                warnings,
+                metadata: self.metadata,
+            })
+        } else {
+            Err(Error::DexError(errors))
+        }
     }
 }
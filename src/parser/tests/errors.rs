@@ -1,8 +1,18 @@
 //! This module contains the tests that should fail and checks that the error messages are correct.
 
+use std::path::{Path, PathBuf};
+
+use crate::bibliography::Bibliography;
+use crate::config::Config;
+use crate::parser::ast::Ast;
 use crate::parser::error::ErrorType;
-use crate::parser::parse;
-use crate::{Error, Result};
+use crate::parser::format::{self, same_document, FormatError, DEFAULT_WIDTH};
+use crate::parser::lint::{LintLevel, Lints};
+use crate::parser::warning::WarningType;
+use crate::parser::{
+    check_ast, parse, parse_with_bibliography, parse_with_lints, Loader, Position,
+};
+use crate::{format_file, Error, Result};
 
 macro_rules! to_dex_error {
     ($expr: expr) => {
@@ -112,3 +122,686 @@ fn test_accent() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_list_indentation() -> Result<()> {
+    let p = parse("assets/tests/errors/test-list-indentation.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnmatchedIndentation);
+    assert_eq!(p.position.line, 3);
+    assert_eq!(p.position.column, 1);
+    assert_eq!(p.position.offset, 31);
+
+    // The error is in the content of the misaligned item, and the list only contains items.
+    let path = Path::new("assets/tests/errors/test-list-indentation.dex");
+    let (ast, _, _) = Loader::default().load(path, std::fs::read_to_string(path)?);
+    let items = ast.children()[0].children();
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|x| matches!(x.ast, Ast::ListItem { .. })));
+    assert!(matches!(
+        items[1].children()[0].children()[0].ast,
+        Ast::Error(_)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_math_command() -> Result<()> {
+    let p = parse("assets/tests/errors/test-unknown-math-command.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnknownMathCommand);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 18);
+    assert_eq!(p.position.offset, 17);
+
+    Ok(())
+}
+
+#[test]
+fn test_include_missing() -> Result<()> {
+    let p = parse("assets/tests/errors/test-include-missing.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::IncludeNotFound);
+    assert_eq!(p.position.line, 3);
+    assert_eq!(p.position.column, 1);
+    assert_eq!(p.position.offset, 6);
+
+    Ok(())
+}
+
+#[test]
+fn test_include_cycle() -> Result<()> {
+    let p = parse("assets/tests/errors/test-include-cycle.dex");
+
+    let p = to_dex_error!(p);
+    assert!(p.errors.is_empty());
+    assert_eq!(p.children.len(), 1);
+
+    let p = &p.children[0];
+    assert_eq!(p.chain.len(), 1);
+    assert_eq!(p.chain[0].position.line, 1);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::IncludeCycle);
+    assert_eq!(p.position.line, 3);
+    assert_eq!(p.position.column, 1);
+    assert_eq!(p.position.offset, 6);
+
+    Ok(())
+}
+
+#[test]
+fn test_include_error() -> Result<()> {
+    let p = parse("assets/tests/errors/test-include-error.dex");
+
+    let p = to_dex_error!(p);
+    assert!(p.errors.is_empty());
+    assert_eq!(p.children.len(), 1);
+
+    let p = &p.children[0];
+    assert_eq!(
+        p.path,
+        PathBuf::from("assets/tests/errors/include/error.dex")
+    );
+    assert_eq!(p.chain.len(), 1);
+    assert_eq!(
+        p.chain[0].path,
+        PathBuf::from("assets/tests/errors/test-include-error.dex")
+    );
+    assert_eq!(p.chain[0].position.line, 3);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnmatchedStar);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 6);
+    assert_eq!(p.position.offset, 5);
+
+    Ok(())
+}
+
+#[test]
+fn test_undefined_label() -> Result<()> {
+    let p = parse("assets/tests/errors/test-undefined-label.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UndefinedLabel);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 5);
+    assert_eq!(p.position.offset, 4);
+
+    Ok(())
+}
+
+#[test]
+fn test_undefined_link() -> Result<()> {
+    let p = parse("assets/tests/errors/test-undefined-link.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UndefinedLabel);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 4);
+    assert_eq!(p.position.offset, 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_label() -> Result<()> {
+    let p = parse("assets/tests/errors/test-duplicate-label.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::DuplicateLabel);
+    assert_eq!(p.position.line, 3);
+    assert_eq!(p.position.column, 5);
+    assert_eq!(p.position.offset, 14);
+
+    Ok(())
+}
+
+#[test]
+fn test_unmatched_footnote() -> Result<()> {
+    let p = parse("assets/tests/errors/test-unmatched-footnote.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnmatchedFootnote);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 10);
+    assert_eq!(p.position.offset, 9);
+
+    Ok(())
+}
+
+#[test]
+fn test_image_missing() -> Result<()> {
+    let p = parse("assets/tests/errors/test-image-missing.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::ImageNotFound);
+    assert_eq!(p.position.line, 3);
+    assert_eq!(p.position.column, 1);
+    assert_eq!(p.position.offset, 12);
+
+    Ok(())
+}
+
+#[test]
+fn test_image_invalid() -> Result<()> {
+    let p = parse("assets/tests/errors/test-image-invalid.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::InvalidImage);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 1);
+    assert_eq!(p.position.offset, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_image_width() -> Result<()> {
+    let p = parse("assets/tests/errors/test-image-width.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::InvalidWidth);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 26);
+    assert_eq!(p.position.offset, 25);
+
+    Ok(())
+}
+
+#[test]
+fn test_table_columns() -> Result<()> {
+    let p = parse("assets/tests/errors/test-table-columns.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnmatchedColumns);
+    assert_eq!(p.position.line, 3);
+    assert_eq!(p.position.column, 1);
+    assert_eq!(p.position.offset, 20);
+
+    Ok(())
+}
+
+#[test]
+fn test_unmatched_backtick() -> Result<()> {
+    let p = parse("assets/tests/errors/test-unmatched-backtick.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnmatchedBacktick);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 6);
+    assert_eq!(p.position.offset, 5);
+
+    Ok(())
+}
+
+#[test]
+fn test_unclosed_code_block() -> Result<()> {
+    let p = parse("assets/tests/errors/test-unclosed-code-block.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnclosedCodeBlock);
+    assert_eq!(p.position.line, 3);
+    assert_eq!(p.position.column, 1);
+    assert_eq!(p.position.offset, 7);
+
+    Ok(())
+}
+
+#[test]
+fn test_metadata() -> Result<()> {
+    let p = parse("assets/tests/errors/test-metadata.dex");
+
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 2);
+
+    let e = &p.errors[0];
+
+    assert_eq!(e.ty, ErrorType::InvalidMetadata);
+    assert_eq!(e.position.line, 3);
+    assert_eq!(e.position.column, 1);
+    assert_eq!(e.position.offset, 22);
+
+    let e = &p.errors[1];
+
+    assert_eq!(e.ty, ErrorType::UnknownMetadata);
+    assert_eq!(e.position.line, 4);
+    assert_eq!(e.position.column, 3);
+    assert_eq!(e.position.offset, 44);
+
+    Ok(())
+}
+
+#[test]
+fn test_markdown_metadata() -> Result<()> {
+    let p = parse("assets/tests/errors/test-markdown-metadata.md");
+
+    // The unknown keys of a Markdown front matter are ignored, but not its invalid lines.
+    let p = to_dex_error!(p);
+    assert_eq!(p.errors.len(), 1);
+
+    let e = &p.errors[0];
+
+    assert_eq!(e.ty, ErrorType::InvalidMetadata);
+    assert_eq!(e.position.line, 2);
+    assert_eq!(e.position.column, 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_included_front_matter() -> Result<()> {
+    let p = parse("assets/tests/errors/test-include-front-matter.dex");
+
+    let p = to_dex_error!(p);
+    assert!(p.errors.is_empty());
+    assert_eq!(p.children.len(), 1);
+
+    let p = &p.children[0];
+    assert_eq!(p.errors.len(), 1);
+
+    let e = &p.errors[0];
+
+    assert_eq!(e.ty, ErrorType::IncludedFrontMatter);
+    assert_eq!(e.position.line, 1);
+    assert_eq!(e.position.column, 1);
+    assert_eq!(e.position.offset, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_citation() -> Result<()> {
+    let bibliography = Bibliography::load("assets/tests/successes/test-citations.bib")?;
+    let path = "assets/tests/errors/test-unknown-citation.dex";
+
+    let p = to_dex_error!(parse_with_bibliography(path, &bibliography));
+    assert_eq!(p.errors.len(), 2);
+
+    // The errors are at the unknown keys.
+    let e = &p.errors[0];
+
+    assert_eq!(e.ty, ErrorType::UnknownCitation);
+    assert_eq!(e.position.line, 1);
+    assert_eq!(e.position.column, 22);
+    assert_eq!(e.position.offset, 21);
+
+    let e = &p.errors[1];
+
+    assert_eq!(e.ty, ErrorType::UnknownCitation);
+    assert_eq!(e.position.line, 3);
+    assert_eq!(e.position.column, 19);
+    assert_eq!(e.position.offset, 50);
+
+    // Without bibliography, no citation can be resolved.
+    let p = to_dex_error!(parse(path));
+    assert_eq!(p.errors.len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_style() -> Result<()> {
+    let p = to_dex_error!(parse("assets/tests/errors/test-unknown-style.dex"));
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnknownStyle);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 14);
+    assert_eq!(p.position.offset, 13);
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_unclosed() -> Result<()> {
+    let p = to_dex_error!(parse("assets/tests/errors/test-nested-unclosed.dex"));
+    assert_eq!(p.errors.len(), 1);
+
+    // The outer bold and italic are closed, only the innermost star is not.
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::UnmatchedStar);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 20);
+    assert_eq!(p.position.offset, 19);
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_unclosed_styles() -> Result<()> {
+    let p = to_dex_error!(parse("assets/tests/errors/test-nested-unclosed-styles.dex"));
+
+    // Each outer style is closed, only the innermost delimiter is reported.
+    let errors = p
+        .errors
+        .iter()
+        .map(|x| (x.ty, x.position.line, x.position.column))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        errors,
+        vec![
+            (ErrorType::UnmatchedSlash, 1, 9),
+            (ErrorType::UnmatchedStar, 3, 11),
+            (ErrorType::UnmatchedDollar, 5, 9),
+            (ErrorType::UnmatchedBacktick, 7, 9),
+            (ErrorType::UnmatchedFootnote, 9, 9),
+            (ErrorType::UnmatchedStar, 11, 19),
+            (ErrorType::UnmatchedSlash, 13, 14),
+            (ErrorType::UnmatchedStar, 15, 14),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_title_level() -> Result<()> {
+    let p = to_dex_error!(parse("assets/tests/errors/test-title-level.dex"));
+
+    // The parsing goes on after the invalid title.
+    assert_eq!(p.errors.len(), 2);
+
+    let e = &p.errors[0];
+    assert_eq!(e.ty, ErrorType::InvalidTitleLevel);
+    assert_eq!(e.position.line, 1);
+    assert_eq!(e.position.column, 1);
+    assert_eq!(e.position.offset, 0);
+
+    let e = &p.errors[1];
+    assert_eq!(e.ty, ErrorType::UnmatchedStar);
+    assert_eq!(e.position.line, 3);
+    assert_eq!(e.position.column, 36);
+    assert_eq!(e.position.offset, 61);
+
+    Ok(())
+}
+
+#[test]
+fn test_span_across_paragraphs() -> Result<()> {
+    let p = to_dex_error!(parse("assets/tests/errors/test-span-across-paragraphs.dex"));
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::SpanAcrossParagraphs);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 9);
+    assert_eq!(p.position.offset, 8);
+
+    Ok(())
+}
+
+#[test]
+fn test_span_across_blocs() -> Result<()> {
+    let p = to_dex_error!(parse("assets/tests/errors/test-span-across-blocs.dex"));
+
+    let errors = p
+        .errors
+        .iter()
+        .map(|x| (x.ty, x.position.line, x.position.column))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        errors,
+        vec![
+            (ErrorType::SpanAcrossParagraphs, 1, 16),
+            (ErrorType::SpanAcrossParagraphs, 5, 18),
+            (ErrorType::SpanAcrossParagraphs, 9, 16),
+            (ErrorType::SpanAcrossParagraphs, 14, 18),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_control_character() -> Result<()> {
+    let p = to_dex_error!(parse("assets/tests/errors/test-control-character.dex"));
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::ControlCharacter);
+    assert_eq!(p.position.line, 1);
+    assert_eq!(p.position.column, 8);
+    assert_eq!(p.position.offset, 7);
+
+    Ok(())
+}
+
+#[test]
+fn test_arbitrary_input() {
+    let pieces = [
+        "*",
+        "/",
+        "$",
+        "$$",
+        "`",
+        "```",
+        "[",
+        "]",
+        "{",
+        "}",
+        "^[",
+        "~",
+        "\\",
+        "# ",
+        "####### ",
+        "\n",
+        "\n\n",
+        " ",
+        "- ",
+        "1. ",
+        "|",
+        "||",
+        "!contents",
+        "!include ",
+        "!figure ",
+        "---\n",
+        "{#",
+        "{.smallcaps}",
+        "\"",
+        "é",
+        "\u{1}",
+        "\t",
+        "\r",
+        "a",
+    ];
+
+    // A xorshift generator, so that the inputs are the same at each run.
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize
+    };
+
+    for _ in 0..2000 {
+        let content: String = (0..next() % 40)
+            .map(|_| pieces[next() % pieces.len()])
+            .collect();
+
+        for path in &["arbitrary.dex", "arbitrary.md"] {
+            let (_, errors, warnings) = Loader::default().load(Path::new(path), content.clone());
+            // Printing the errors must not panic either.
+            let _ = format!("{}{}", errors, warnings);
+        }
+
+        // The formatted inputs must be the same documents, and must be formatted already.
+        if let Ok(formatted) = format::format(&content, 20) {
+            assert!(same_document(&content, &formatted), "{:?}", content);
+            assert_eq!(format::format(&formatted, 20), Ok(formatted));
+        }
+    }
+}
+
+#[test]
+fn test_denied_lint() -> Result<()> {
+    let mut lints = Lints::default();
+    lints.set(WarningType::RepeatedWord, LintLevel::Deny);
+
+    let p = to_dex_error!(parse_with_lints(
+        "assets/tests/successes/test-lints.dex",
+        &Bibliography::default(),
+        &lints,
+    ));
+    assert_eq!(p.errors.len(), 1);
+
+    let p = &p.errors[0];
+
+    assert_eq!(p.ty, ErrorType::Lint(WarningType::RepeatedWord));
+    assert_eq!(p.position.line, 7);
+    assert_eq!(p.position.column, 9);
+    assert_eq!(p.position.offset, 109);
+
+    Ok(())
+}
+
+#[test]
+fn test_json() -> Result<()> {
+    let path = "assets/tests/errors/test-json.json";
+    let ast = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+    let mut lints = Lints::default();
+    lints.set(WarningType::RepeatedWord, LintLevel::Deny);
+
+    // An ast written by another tool is checked like a parsed one.
+    let p = to_dex_error!(check_ast(path, ast, &Bibliography::default(), &lints));
+    let errors = p.errors.iter().map(|x| x.ty).collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            ErrorType::UndefinedLabel,
+            ErrorType::UnknownCitation,
+            ErrorType::Lint(WarningType::RepeatedWord),
+            ErrorType::UnmatchedStar,
+            ErrorType::ImageNotFound,
+        ]
+    );
+
+    // The report has no source to show, and the images are relative to the current directory.
+    let report = p.to_string();
+    assert!(!report.contains("test-json.json:"));
+    assert!(report.contains("image paths are relative to the current directory"));
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_lint() {
+    // The config written by `spandex init` has an empty table of lints.
+    let toml = toml::to_string(&Config::with_title("Lints")).unwrap();
+    let toml = toml.replace("[lints]", "[lints]\ntypos = \"deny\"");
+    let config: Config = toml::from_str(&toml).unwrap();
+
+    match parse_with_lints(&config.input, &Bibliography::default(), &config.lints) {
+        Err(Error::UnknownLint(name)) => assert_eq!(name, "typos"),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_format_errors() -> Result<()> {
+    let p = to_dex_error!(format_file(
+        "assets/tests/errors/test-unmatched-star.dex",
+        DEFAULT_WIDTH,
+        true
+    ));
+
+    assert_eq!(p.errors.len(), 1);
+    assert_eq!(p.errors[0].ty, ErrorType::UnmatchedStar);
+    Ok(())
+}
+
+#[test]
+fn test_unformattable() -> Result<()> {
+    // The bloc that would not be parsed back to the same content is reported.
+    let path = "assets/tests/errors/test-unformattable.dex";
+    match format_file(path, DEFAULT_WIDTH, true) {
+        Err(Error::Unformattable(p, position)) => {
+            assert_eq!(p, PathBuf::from(path));
+            assert_eq!(position.line, 3);
+            assert_eq!(position.column, 1);
+            assert_eq!(position.offset, 21);
+        }
+        _ => panic!(),
+    }
+
+    assert_eq!(
+        format::format("A paragraph.\n\n\\ ", DEFAULT_WIDTH),
+        Err(FormatError::Unformattable(Position {
+            line: 3,
+            column: 1,
+            offset: 14,
+        }))
+    );
+
+    Ok(())
+}
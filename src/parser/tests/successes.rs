@@ -1,11 +1,25 @@
 //! This module contains the tests that should success and checks that the ast is correct.
 
 use std::error::Error;
+use std::fs;
 
-use crate::parser::{parse, Ast};
+use nom::types::CompleteStr;
+
+use crate::bibliography::Bibliography;
+use crate::math::parser::parse as parse_formula;
+use crate::math::Math;
+use crate::parser::ast::{Alignment, Node};
+use crate::parser::combinators;
+use crate::parser::format::{format, normalized};
+use crate::parser::lint::{LintLevel, Lints};
+use crate::parser::metadata::Metadata;
+use crate::parser::warning::{EmptyWarning, WarningType};
+use crate::parser::{
+    check_ast, parse, parse_with_bibliography, parse_with_lints, Ast, Position, SourceSpan, Span,
+};
 
 #[test]
-fn test_title_1() -> Result<(), Box<Error>> {
+fn test_title_1() -> Result<(), Box<dyn Error>> {
     let p = parse("assets/tests/successes/test-title-1.dex");
     assert!(p.is_ok());
 
@@ -13,16 +27,17 @@ fn test_title_1() -> Result<(), Box<Error>> {
 
     let expected_ast = Ast::Group(vec![Ast::Title {
         level: 0,
-        content: Box::new(Ast::Group(vec![Ast::Text("A title".into())])),
-    }]);
+        content: Box::new(Ast::Group(vec![Ast::Text("A title".into()).into()]).into()),
+    }
+    .into()]);
 
-    assert_eq!(expected_ast, ast);
+    assert_eq!(expected_ast, ast.without_spans());
 
     Ok(())
 }
 
 #[test]
-fn test_title_2() -> Result<(), Box<Error>> {
+fn test_title_2() -> Result<(), Box<dyn Error>> {
     let p = parse("assets/tests/successes/test-title-2.dex");
     assert!(p.is_ok());
 
@@ -30,16 +45,17 @@ fn test_title_2() -> Result<(), Box<Error>> {
 
     let expected_ast = Ast::Group(vec![Ast::Title {
         level: 1,
-        content: Box::new(Ast::Group(vec![Ast::Text("A subtitle".into())])),
-    }]);
+        content: Box::new(Ast::Group(vec![Ast::Text("A subtitle".into()).into()]).into()),
+    }
+    .into()]);
 
-    assert_eq!(expected_ast, ast);
+    assert_eq!(expected_ast, ast.without_spans());
 
     Ok(())
 }
 
 #[test]
-fn test_titles() -> Result<(), Box<Error>> {
+fn test_titles() -> Result<(), Box<dyn Error>> {
     let p = parse("assets/tests/successes/test-titles.dex");
     assert!(p.is_ok());
 
@@ -48,15 +64,1139 @@ fn test_titles() -> Result<(), Box<Error>> {
     let expected_ast = Ast::Group(vec![
         Ast::Title {
             level: 0,
-            content: Box::new(Ast::Group(vec![Ast::Text("A title".into())])),
-        },
+            content: Box::new(Ast::Group(vec![Ast::Text("A title".into()).into()]).into()),
+        }
+        .into(),
         Ast::Title {
             level: 1,
-            content: Box::new(Ast::Group(vec![Ast::Text("With its subtitle".into())])),
+            content: Box::new(
+                Ast::Group(vec![Ast::Text("With its subtitle".into()).into()]).into(),
+            ),
+        }
+        .into(),
+    ]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_list() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-list.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let expected_ast = Ast::Group(vec![Ast::List {
+        ordered: false,
+        start: 1,
+        items: vec![
+            Ast::ListItem {
+                content: Box::new(
+                    Ast::Group(vec![Ast::Group(
+                        vec![Ast::Text("First item".into()).into()],
+                    )
+                    .into()])
+                    .into(),
+                ),
+                children: vec![],
+            }
+            .into(),
+            Ast::ListItem {
+                content: Box::new(
+                    Ast::Group(vec![
+                        Ast::Group(vec![Ast::Text("Second item".into()).into()]).into(),
+                        Ast::Text(" ".into()).into(),
+                        Ast::Group(vec![
+                            Ast::Text("with a ".into()).into(),
+                            Ast::Bold(Box::new(
+                                Ast::Group(vec![Ast::Text("bold".into()).into()]).into(),
+                            ))
+                            .into(),
+                            Ast::Text(" continuation".into()).into(),
+                        ])
+                        .into(),
+                    ])
+                    .into(),
+                ),
+                children: vec![Ast::List {
+                    ordered: true,
+                    start: 1,
+                    items: vec![
+                        Ast::ListItem {
+                            content: Box::new(
+                                Ast::Group(vec![Ast::Group(vec![
+                                    Ast::Text("Nested".into()).into()
+                                ])
+                                .into()])
+                                .into(),
+                            ),
+                            children: vec![],
+                        }
+                        .into(),
+                        Ast::ListItem {
+                            content: Box::new(
+                                Ast::Group(vec![Ast::Group(vec![
+                                    Ast::Text("Numbered".into()).into()
+                                ])
+                                .into()])
+                                .into(),
+                            ),
+                            children: vec![],
+                        }
+                        .into(),
+                    ],
+                }
+                .into()],
+            }
+            .into(),
+            Ast::ListItem {
+                content: Box::new(
+                    Ast::Group(vec![Ast::Group(
+                        vec![Ast::Text("Third item".into()).into()],
+                    )
+                    .into()])
+                    .into(),
+                ),
+                children: vec![],
+            }
+            .into(),
+        ],
+    }
+    .into()]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_list_markers() -> Result<(), Box<dyn Error>> {
+    let ast = parse("assets/tests/successes/test-list-markers.dex")?.ast;
+
+    let item = |text: &str, children| {
+        Node::from(Ast::ListItem {
+            content: Box::new(
+                Ast::Group(vec![Ast::Group(vec![Ast::Text(text.into()).into()]).into()]).into(),
+            ),
+            children,
+        })
+    };
+
+    let list = |ordered, start, items| {
+        Node::from(Ast::List {
+            ordered,
+            start,
+            items,
+        })
+    };
+
+    // Numbered lists keep their first number, and a change of marker starts another list.
+    let expected_ast = Ast::Group(vec![Ast::Group(vec![
+        list(true, 5, vec![item("Fifth", vec![]), item("Sixth", vec![])]),
+        list(
+            false,
+            1,
+            vec![item(
+                "A bullet",
+                vec![
+                    list(false, 1, vec![item("Nested", vec![])]),
+                    list(true, 1, vec![item("Nested number", vec![])]),
+                ],
+            )],
+        ),
+    ])
+    .into()]);
+
+    assert_eq!(ast.without_spans(), expected_ast);
+
+    Ok(())
+}
+
+/// Returns a position given by its line, column and offset.
+fn position((line, column, offset): (u32, usize, usize)) -> Position {
+    Position {
+        line,
+        column,
+        offset,
+    }
+}
+
+/// Returns the span between two positions given by their line, column and offset.
+fn span(start: (u32, usize, usize), end: (u32, usize, usize)) -> SourceSpan {
+    SourceSpan {
+        start: position(start),
+        end: position(end),
+    }
+}
+
+/// Parses a formula of the expected asts.
+fn formula(content: &str) -> Math {
+    parse_formula(Span::new(CompleteStr(content)))
+}
+
+#[test]
+fn test_spans() -> Result<(), Box<dyn Error>> {
+    let ast = parse("assets/tests/successes/test-list.dex")?.ast;
+
+    let list = ast.children()[0];
+    assert_eq!(list.span, span((1, 1, 0), (6, 13, 94)));
+
+    // The item ends with its nested list.
+    let item = list.children()[1];
+    assert_eq!(item.span, span((2, 1, 13), (5, 14, 81)));
+
+    let bold = item.children()[0].children()[2].children()[1];
+    assert!(matches!(bold.ast, Ast::Bold(_)));
+    assert_eq!(bold.span, span((3, 10, 36), (3, 16, 42)));
+    assert_eq!(bold.children()[0].span, span((3, 11, 37), (3, 15, 41)));
+
+    // The spans are compared unless they are removed.
+    assert_ne!(ast.without_spans(), ast);
+    assert_eq!(
+        ast.without_spans().children()[0].span,
+        SourceSpan::default()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_display_math() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-display-math.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let expected_ast = Ast::Group(vec![
+        Ast::Paragraph(vec![Ast::Text("Euler said".into()).into()]).into(),
+        Ast::DisplayMath {
+            content: "e^{i\\pi} + 1 = 0".into(),
+            formula: formula("e^{i\\pi} + 1 = 0"),
+            numbered: true,
+        }
+        .into(),
+        Ast::DisplayMath {
+            content: "\\frac{a}{b}".into(),
+            formula: formula("\\frac{a}{b}"),
+            numbered: false,
+        }
+        .into(),
+    ]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_include() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-include.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let expected_ast = Ast::Group(vec![
+        Ast::Paragraph(vec![Ast::Text("Before".into()).into()]).into(),
+        Ast::Group(vec![
+            Ast::Title {
+                level: 0,
+                content: Box::new(Ast::Group(vec![Ast::Text("Chapter".into()).into()]).into()),
+            }
+            .into(),
+            Ast::Group(vec![Ast::Paragraph(vec![
+                Ast::Text("Section".into()).into()
+            ])
+            .into()])
+            .into(),
+        ])
+        .into(),
+        Ast::Paragraph(vec![Ast::Text("After".into()).into()]).into(),
+    ]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_labels() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-labels.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let reference = |label: &str, page, column, offset| {
+        Node::from(Ast::Reference {
+            label: label.into(),
+            page,
+            position: Position {
+                line: 3,
+                column,
+                offset,
+            },
+        })
+    };
+
+    let expected_ast = Ast::Group(vec![
+        Ast::Title {
+            level: 0,
+            content: Box::new(
+                Ast::Group(vec![
+                    Ast::Text("Introduction ".into()).into(),
+                    Ast::Label {
+                        name: "intro".into(),
+                        position: Position {
+                            line: 1,
+                            column: 16,
+                            offset: 15,
+                        },
+                    }
+                    .into(),
+                ])
+                .into(),
+            ),
+        }
+        .into(),
+        Ast::Paragraph(vec![
+            Ast::Text("See ".into()).into(),
+            reference("intro", false, 5, 29),
+            Ast::Text(" on page ".into()).into(),
+            reference("intro", true, 22, 46),
+            Ast::Text(", and ".into()).into(),
+            reference("euler", false, 41, 65),
+            Ast::Text(".".into()).into(),
+        ])
+        .into(),
+        Ast::Group(vec![
+            Ast::DisplayMath {
+                content: "e^{i\\pi} = -1".into(),
+                formula: formula("e^{i\\pi} = -1"),
+                numbered: true,
+            }
+            .into(),
+            Ast::Label {
+                name: "euler".into(),
+                position: Position {
+                    line: 5,
+                    column: 21,
+                    offset: 96,
+                },
+            }
+            .into(),
+        ])
+        .into(),
+    ]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_footnote() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-footnote.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let expected_ast = Ast::Group(vec![Ast::Paragraph(vec![
+        Ast::Text("Some text".into()).into(),
+        Ast::Footnote(Box::new(
+            Ast::Group(vec![
+                Ast::Text("A ".into()).into(),
+                Ast::Bold(Box::new(
+                    Ast::Group(vec![Ast::Text("short".into()).into()]).into(),
+                ))
+                .into(),
+                Ast::Text(" note.".into()).into(),
+            ])
+            .into(),
+        ))
+        .into(),
+        Ast::Text(" and more.".into()).into(),
+    ])
+    .into()]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_link() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-link.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let expected_ast = Ast::Group(vec![Ast::Paragraph(vec![
+        Ast::Text("See ".into()).into(),
+        Ast::Link {
+            url: "https://rust-spandex.github.io".into(),
+            content: Box::new(
+                Ast::Group(vec![
+                    Ast::Text("the ".into()).into(),
+                    Ast::Bold(Box::new(
+                        Ast::Group(vec![Ast::Text("site".into()).into()]).into(),
+                    ))
+                    .into(),
+                ])
+                .into(),
+            ),
+            position: Position {
+                line: 1,
+                column: 5,
+                offset: 4,
+            },
+        }
+        .into(),
+        Ast::Text(" or ".into()).into(),
+        Ast::Link {
+            url: "https://example.com".into(),
+            content: Box::new(Ast::Text("https://example.com".into()).into()),
+            position: Position {
+                line: 1,
+                column: 53,
+                offset: 52,
+            },
+        }
+        .into(),
+        Ast::Text(".".into()).into(),
+    ])
+    .into()]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_figure() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-figure.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let expected_ast = Ast::Group(vec![
+        Ast::Figure {
+            path: "assets/tests/successes/figure/rectangle.png".into(),
+            width: Some(50),
+            caption: Some(Box::new(
+                Ast::Group(vec![
+                    Ast::Text("A ".into()).into(),
+                    Ast::Bold(Box::new(
+                        Ast::Group(vec![Ast::Text("red".into()).into()]).into(),
+                    ))
+                    .into(),
+                    Ast::Text(" rectangle ".into()).into(),
+                    Ast::Label {
+                        name: "rectangle".into(),
+                        position: Position {
+                            line: 2,
+                            column: 19,
+                            offset: 50,
+                        },
+                    }
+                    .into(),
+                    Ast::Text(".".into()).into(),
+                ])
+                .into(),
+            )),
+            position: Position {
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
+        }
+        .into(),
+        Ast::Figure {
+            path: "assets/tests/successes/figure/rectangle.png".into(),
+            width: None,
+            caption: None,
+            position: Position {
+                line: 4,
+                column: 1,
+                offset: 65,
+            },
+        }
+        .into(),
+    ]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_table() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-table.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let text = |content: &str| Node::from(Ast::Group(vec![Ast::Text(content.into()).into()]));
+
+    let expected_ast = Ast::Group(vec![Ast::Table {
+        alignments: vec![Alignment::Left, Alignment::Center, Alignment::Right],
+        header: vec![text("Name"), text("Value"), text("Comment")],
+        rows: vec![
+            vec![
+                Ast::Group(vec![Ast::Bold(Box::new(text("a"))).into()]).into(),
+                Ast::Group(vec![Ast::InlineMath {
+                    content: "x^2".into(),
+                    formula: formula("x^2"),
+                }
+                .into()])
+                .into(),
+                text("top"),
+            ],
+            vec![text("b"), Ast::Group(vec![]).into(), text("second")],
+        ],
+        caption: Some(Box::new(
+            Ast::Group(vec![
+                Ast::Text("The values ".into()).into(),
+                Ast::Label {
+                    name: "values".into(),
+                    position: Position {
+                        line: 5,
+                        column: 12,
+                        offset: 112,
+                    },
+                }
+                .into(),
+                Ast::Text(".".into()).into(),
+            ])
+            .into(),
+        )),
+    }
+    .into()]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_table_pipes() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-table-pipes.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let text = |content: &str| Node::from(Ast::Group(vec![Ast::Text(content.into()).into()]));
+
+    // The pipes in code, in math, or escaped don't separate cells.
+    let expected_ast = Ast::Group(vec![Ast::Table {
+        alignments: vec![Alignment::Left; 3],
+        header: vec![text("Code"), text("Math"), text("Escape")],
+        rows: vec![vec![
+            Ast::Group(vec![Ast::Code("a|b".into()).into()]).into(),
+            Ast::Group(vec![Ast::InlineMath {
+                content: "|x|".into(),
+                formula: formula("|x|"),
+            }
+            .into()])
+            .into(),
+            Ast::Group(vec![
+                Ast::Text("a ".into()).into(),
+                Ast::Escaped("|".into()).into(),
+                Ast::Text(" b".into()).into(),
+            ])
+            .into(),
+        ]],
+        caption: None,
+    }
+    .into()]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_escapes() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-escapes.dex");
+    assert!(p.is_ok());
+
+    let p = p.unwrap();
+    let ast = p.ast;
+
+    let text = |content: &str| Node::from(Ast::Text(content.into()));
+    let escaped = |content: &str| Node::from(Ast::Escaped(content.into()));
+
+    let warning = EmptyWarning {
+        position: Position {
+            line: 1,
+            column: 37,
+            offset: 36,
         },
+        ty: WarningType::UnknownEscape,
+    };
+
+    let expected_ast = Ast::Group(vec![Ast::Paragraph(vec![
+        text("3 "),
+        escaped("*"),
+        text(" 4 and"),
+        escaped("/"),
+        text("or "),
+        escaped("$"),
+        text("5, "),
+        Ast::Bold(Box::new(
+            Ast::Group(vec![text("bold "), escaped("*"), text(" star")]).into(),
+        ))
+        .into(),
+        text(", "),
+        Ast::Group(vec![Ast::Warning(warning.clone()).into(), text("q")]).into(),
+        text(" and of"),
+        Ast::Group(vec![
+            Ast::Warning(EmptyWarning {
+                position: Position {
+                    line: 1,
+                    column: 46,
+                    offset: 45,
+                },
+                ty: WarningType::UnknownEscape,
+            })
+            .into(),
+            text("f"),
+        ])
+        .into(),
+        text("ice."),
+    ])
+    .into()]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+    assert_eq!(p.warnings.warnings.len(), 2);
+    assert_eq!(p.warnings.warnings[0], warning);
+
+    Ok(())
+}
+
+#[test]
+fn test_code() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-code.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let expected_ast = Ast::Group(vec![
+        Ast::Paragraph(vec![
+            Ast::Text("Call ".into()).into(),
+            Ast::Code("f(*x*, $y$) -- fi".into()).into(),
+            Ast::Text(" now.".into()).into(),
+        ])
+        .into(),
+        Ast::CodeBlock {
+            language: Some("rust".into()),
+            content: "fn main() {\n\n\tprintln!(\"*fi*\");\n}".into(),
+        }
+        .into(),
+        Ast::Paragraph(vec![Ast::Text("After.".into()).into()]).into(),
+    ]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_metadata() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-metadata.dex");
+    assert!(p.is_ok());
+
+    let p = p.unwrap();
+
+    let expected_metadata = Metadata {
+        title: Some("A document".into()),
+        authors: vec!["Ada Lovelace".into(), "Charles Babbage".into()],
+        date: Some("1843-09-05".into()),
+        language: Some("en".into()),
+        keywords: vec!["engine".into(), "notes".into()],
+    };
+
+    let expected_ast = Ast::Group(vec![Ast::Paragraph(vec![
+        Ast::Text("See ".into()).into(),
+        Ast::Link {
+            url: "https://example.com".into(),
+            content: Box::new(Ast::Group(vec![Ast::Text("the notes".into()).into()]).into()),
+            position: Position {
+                line: 9,
+                column: 5,
+                offset: 124,
+            },
+        }
+        .into(),
+        Ast::Text(".".into()).into(),
+    ])
+    .into()]);
+
+    assert_eq!(expected_metadata, p.metadata);
+    assert_eq!(expected_ast, p.ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_contents() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-contents.dex");
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    match ast {
+        Ast::Group(children) => {
+            assert_eq!(children.len(), 5);
+            assert_eq!(children[0].ast, Ast::TableOfContents);
+        }
+        _ => panic!(),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_citations() -> Result<(), Box<dyn Error>> {
+    let bibliography = Bibliography::load("assets/tests/successes/test-citations.bib")?;
+    let p = parse_with_bibliography("assets/tests/successes/test-citations.dex", &bibliography);
+    assert!(p.is_ok());
+
+    let ast = p.unwrap().ast;
+
+    let expected_ast = Ast::Group(vec![
+        Ast::Paragraph(vec![
+            Ast::Text("As shown in ".into()).into(),
+            Ast::Citation {
+                keys: vec![("lamport94".into(), position((1, 15, 14)))],
+            }
+            .into(),
+            Ast::Text(", and before in ".into()).into(),
+            Ast::Citation {
+                keys: vec![
+                    ("knuth84".into(), position((1, 43, 42))),
+                    ("lamport94".into(), position((1, 53, 52))),
+                ],
+            }
+            .into(),
+            Ast::Text(".".into()).into(),
+        ])
+        .into(),
+        Ast::Bibliography.into(),
+    ]);
+
+    assert_eq!(expected_ast, ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_markdown() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-markdown.md");
+    assert!(p.is_ok());
+
+    let p = p.unwrap();
+
+    let group = |text: &str| Box::new(Ast::Group(vec![Ast::Text(text.into()).into()]).into());
+
+    let item = |text: &str, children| {
+        Node::from(Ast::ListItem {
+            content: group(text),
+            children,
+        })
+    };
+
+    let expected = Ast::Group(vec![
+        Ast::Title {
+            level: 0,
+            content: Box::new(
+                Ast::Group(vec![
+                    Ast::Text("Notes ".into()).into(),
+                    Ast::Label {
+                        name: "notes".into(),
+                        position: Position {
+                            line: 6,
+                            column: 7,
+                            offset: 47,
+                        },
+                    }
+                    .into(),
+                ])
+                .into(),
+            ),
+        }
+        .into(),
+        Ast::Paragraph(vec![
+            Ast::Text("Some ".into()).into(),
+            Ast::Italic(group("emphasis")).into(),
+            Ast::Text(", ".into()).into(),
+            Ast::Bold(group("strong")).into(),
+            Ast::Text(" text\nand ".into()).into(),
+            Ast::Code("code".into()).into(),
+            Ast::Text(", see ".into()).into(),
+            Ast::Link {
+                url: "#notes".into(),
+                content: group("above"),
+                position: Position {
+                    line: 10,
+                    column: 17,
+                    offset: 112,
+                },
+            }
+            .into(),
+            Ast::Text(".".into()).into(),
+        ])
+        .into(),
+        Ast::List {
+            ordered: false,
+            start: 1,
+            items: vec![
+                item("An item", vec![]),
+                item(
+                    "Another item",
+                    vec![Ast::List {
+                        ordered: true,
+                        start: 1,
+                        items: vec![item("A nested item", vec![])],
+                    }
+                    .into()],
+                ),
+            ],
+        }
+        .into(),
+        Ast::CodeBlock {
+            language: Some("rust".into()),
+            content: "fn main() {}".into(),
+        }
+        .into(),
+    ]);
+
+    assert_eq!(Some("Some notes".into()), p.metadata.title);
+    assert_eq!(expected, p.ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_markdown_spans() -> Result<(), Box<dyn Error>> {
+    let ast = parse("assets/tests/successes/test-markdown.md")?.ast;
+    let blocks = ast.children();
+
+    // The heading is underlined, and the front matter is not part of any block.
+    assert_eq!(blocks[0].span, span((6, 1, 41), (7, 6, 61)));
+
+    let link = blocks[1].children()[7];
+    assert!(matches!(link.ast, Ast::Link { .. }));
+    assert_eq!(link.span, span((10, 17, 112), (10, 32, 127)));
+    assert_eq!(link.children()[0].span, span((10, 18, 113), (10, 23, 118)));
+
+    assert_eq!(blocks[3].span, span((16, 1, 175), (18, 4, 199)));
+
+    Ok(())
+}
+
+#[test]
+fn test_typography() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-typography.dex");
+    assert!(p.is_ok());
+
+    let expected = Ast::Group(vec![Ast::Paragraph(vec![
+        Ast::Text("“Quoted ".into()).into(),
+        Ast::Bold(Box::new(
+            Ast::Group(vec![Ast::Text("words".into()).into()]).into(),
+        ))
+        .into(),
+        Ast::Text("”, it’s 1984–1994 — or so… Page\u{a0}3, and ".into()).into(),
+        Ast::Code("\"code\" -- ...".into()).into(),
+        Ast::Text(" or ".into()).into(),
+        Ast::InlineMath {
+            content: "a--b".into(),
+            formula: formula("a--b"),
+        }
+        .into(),
+        Ast::Text(".".into()).into(),
+    ])
+    .into()]);
+
+    let ast = p.unwrap().ast.without_spans();
+    assert_eq!(expected.children()[0], ast.children()[0]);
+
+    // Escaped characters and autolinks are left untouched.
+    let text = |content: &str| Node::from(Ast::Text(content.into()));
+    let escaped = |content: &str| Node::from(Ast::Escaped(content.into()));
+    let url = "https://a.org/x--y'z";
+
+    let expected = Node::from(Ast::Paragraph(vec![
+        text("Escaped "),
+        escaped("\""),
+        text("quotes"),
+        escaped("\""),
+        text(", a"),
+        escaped("-"),
+        escaped("-"),
+        text("b, 1"),
+        escaped("."),
+        escaped("."),
+        escaped("."),
+        text(" and "),
+        escaped("~"),
+        text(", and "),
+        Ast::Link {
+            url: url.into(),
+            content: Box::new(text(url)),
+            position: position((3, 49, 135)),
+        }
+        .into(),
+        text("."),
+    ]));
+    assert_eq!(&expected, ast.children()[1]);
+
+    Ok(())
+}
+
+#[test]
+fn test_styles() -> Result<(), Box<dyn Error>> {
+    let group = |text: &str| Box::new(Ast::Group(vec![Ast::Text(text.into()).into()]).into());
+
+    let p = parse("assets/tests/successes/test-styles.dex");
+    assert!(p.is_ok());
+
+    let expected = Ast::Group(vec![Ast::Paragraph(vec![
+        Ast::SmallCaps(group("Nasa")).into(),
+        Ast::Text(" is ".into()).into(),
+        Ast::Underline(group("not")).into(),
+        Ast::Text(" ".into()).into(),
+        Ast::Strikethrough(group("quite")).into(),
+        Ast::Text(" 2".into()).into(),
+        Ast::Superscript(group("10")).into(),
+        Ast::Text(", H".into()).into(),
+        Ast::Subscript(group("2")).into(),
+        Ast::Text("O.".into()).into(),
+    ])
+    .into()]);
+
+    assert_eq!(expected, p.unwrap().ast.without_spans());
+
+    let p = parse("assets/tests/successes/test-styles.md");
+    assert!(p.is_ok());
+
+    // Unknown styles are ignored in Markdown, like in pandoc.
+    let expected = Ast::Group(vec![Ast::Paragraph(vec![
+        Ast::SmallCaps(group("Nasa")).into(),
+        Ast::Text(" is ".into()).into(),
+        Ast::Strikethrough(group("quite")).into(),
+        Ast::Text(" 2".into()).into(),
+        Ast::Superscript(group("10")).into(),
+        Ast::Text(", H".into()).into(),
+        Ast::Subscript(group("2")).into(),
+        Ast::Text("O, ".into()).into(),
+        Ast::Group(vec![Ast::Text("kept".into()).into()]).into(),
+        Ast::Text(".".into()).into(),
+    ])
+    .into()]);
+
+    assert_eq!(expected, p.unwrap().ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_nesting() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-nesting.dex");
+    assert!(p.is_ok());
+
+    let group = |children| Box::new(Ast::Group(children).into());
+    let text = |text: &str| Node::from(Ast::Text(text.into()));
+
+    let expected = Ast::Group(vec![Ast::Paragraph(vec![
+        Ast::Bold(group(vec![
+            text("Bold "),
+            Ast::Italic(group(vec![
+                text("italic "),
+                Ast::Bold(group(vec![text("bold again")])).into(),
+                text(" italic"),
+            ]))
+            .into(),
+            text(" bold"),
+        ]))
+        .into(),
+        Ast::Footnote(group(vec![
+            text("A note with "),
+            Ast::Link {
+                url: "https://example.com".into(),
+                content: group(vec![
+                    text("a "),
+                    Ast::Italic(group(vec![text("link")])).into(),
+                ]),
+                position: Position {
+                    line: 1,
+                    column: 55,
+                    offset: 54,
+                },
+            }
+            .into(),
+            text("."),
+        ]))
+        .into(),
+    ])
+    .into()]);
+
+    assert_eq!(expected, p.unwrap().ast.without_spans());
+
+    Ok(())
+}
+
+#[test]
+fn test_lints() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-lints.dex")?;
+
+    let warning = |line, column, offset, ty| EmptyWarning {
+        position: Position {
+            line,
+            column,
+            offset,
+        },
+        ty,
+    };
+
+    // The repeated words and doubled spaces of the second paragraph are allowed by its comment.
+    let expected = vec![
+        warning(3, 1, 16, WarningType::SkippedTitleLevel),
+        warning(5, 5, 39, WarningType::DoubledSpaces),
+        warning(6, 28, 98, WarningType::TrailingWhitespace),
+        warning(7, 9, 109, WarningType::RepeatedWord),
+        warning(12, 1, 224, WarningType::TrailingWhitespace),
+        warning(12, 1, 224, WarningType::EmptyParagraph),
+        warning(14, 10, 238, WarningType::UnknownLint),
+    ];
+
+    assert_eq!(expected, p.warnings.warnings);
+
+    // The only paragraph of an empty file is not reported as empty.
+    let p = parse("assets/tests/successes/test-empty.dex")?;
+    assert!(p.warnings.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_markdown_lints() -> Result<(), Box<dyn Error>> {
+    let p = parse("assets/tests/successes/test-lints.md")?;
+
+    let warning = |line, column, offset, ty| EmptyWarning {
+        position: Position {
+            line,
+            column,
+            offset,
+        },
+        ty,
+    };
+
+    let expected = vec![
+        warning(3, 1, 16, WarningType::SkippedTitleLevel),
+        warning(5, 5, 39, WarningType::DoubledSpaces),
+        warning(6, 28, 98, WarningType::TrailingWhitespace),
+        warning(7, 9, 109, WarningType::RepeatedWord),
+    ];
+
+    assert_eq!(expected, p.warnings.warnings);
+
+    Ok(())
+}
+
+#[test]
+fn test_allowed_lints() -> Result<(), Box<dyn Error>> {
+    let mut lints = Lints::default();
+    for ty in &WarningType::ALL {
+        lints.set(*ty, LintLevel::Allow);
+    }
+
+    let p = parse_with_lints(
+        "assets/tests/successes/test-lints.dex",
+        &Bibliography::default(),
+        &lints,
+    )?;
+
+    assert!(p.warnings.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_json() -> Result<(), Box<dyn Error>> {
+    for path in &[
+        "assets/tests/successes/test-escapes.dex",
+        "assets/tests/successes/test-figure.dex",
+        "assets/tests/successes/test-labels.dex",
+        "assets/tests/successes/test-list.dex",
+        "assets/tests/successes/test-markdown.md",
+        "assets/tests/successes/test-table.dex",
+    ] {
+        let ast = parse(path)?.ast;
+        let json = serde_json::to_string(&ast)?;
+        assert_eq!(serde_json::from_str::<Ast>(&json)?, ast);
+
+        // The deserialized ast is checked again, and its images are still found.
+        let checked = check_ast(
+            path,
+            serde_json::from_str(&json)?,
+            &Bibliography::default(),
+            &Lints::default(),
+        )?;
+        assert_eq!(checked.ast, ast);
+    }
+
+    // The spans of the asts written by other tools are optional.
+    let json = r#"{"Paragraph": [{"ast": {"Text": "Generated"}}, {"ast": "Newline"}]}"#;
+    let expected = Ast::Paragraph(vec![
+        Ast::Text("Generated".into()).into(),
+        Ast::Newline.into(),
     ]);
+    assert_eq!(serde_json::from_str::<Ast>(json)?, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_format() -> Result<(), Box<dyn Error>> {
+    let unformatted = fs::read_to_string("assets/tests/format/unformatted.dex")?;
+    let formatted = fs::read_to_string("assets/tests/format/formatted.dex")?;
+    assert_eq!(format(&unformatted, 40), Ok(formatted.clone()));
+    assert_eq!(format(&formatted, 40), Ok(formatted));
+
+    // Only the dashes that would start a list item are escaped.
+    let content = "a\n\n---\ntitle: x\n---\n\n\\- b\n";
+    assert_eq!(
+        format(content, 40),
+        Ok("a\n\n--- title: x ---\n\n\\- b\n".into())
+    );
+
+    // Comments are kept in the ast.
+    let ast = combinators::parse(Span::new(CompleteStr("A || comment\ntext")));
+    let expected = Ast::Group(vec![Ast::Paragraph(vec![
+        Ast::Text("A ".into()).into(),
+        Ast::Comment("comment".into()).into(),
+        Ast::Text("text".into()).into(),
+    ])
+    .into()]);
+    assert_eq!(ast.without_spans(), expected);
+
+    for entry in fs::read_dir("assets/tests/successes")? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|x| x != "dex") {
+            continue;
+        }
 
-    assert_eq!(expected_ast, ast);
+        let content = fs::read_to_string(&path)?;
+        let formatted = format(&content, 40).unwrap();
+        assert_eq!(
+            normalized(&formatted),
+            normalized(&content),
+            "{}",
+            path.display()
+        );
+        assert_eq!(format(&formatted, 40), Ok(formatted));
+    }
 
     Ok(())
 }
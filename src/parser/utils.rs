@@ -33,20 +33,15 @@ pub fn compute_column(content: &str, start: usize, current: usize) -> usize {
     column
 }
 
-/// Finds the previous \n char.
+/// Finds the beginning of the line containing a specified byte.
 ///
 /// Returns 0 is no \n was found.
 pub fn previous_new_line(content: &str, byte: usize) -> usize {
-    let mut i = byte;
+    let byte = byte.min(content.len());
 
-    while i != 0 && !is_new_line(content, i) {
-        i -= 1;
-    }
-
-    if &content[i..=i] == "\n" {
-        i + 1
-    } else {
-        i
+    match content.as_bytes()[..byte].iter().rposition(|x| *x == b'\n') {
+        Some(i) => i + 1,
+        None => 0,
     }
 }
 
@@ -54,11 +49,10 @@ pub fn previous_new_line(content: &str, byte: usize) -> usize {
 ///
 /// Returns the length of the string if no \n was found.
 pub fn next_new_line(content: &str, byte: usize) -> usize {
-    let mut i = byte;
+    let byte = byte.min(content.len());
 
-    while i != content.len() && !is_new_line(content, i) {
-        i += 1;
+    match content.as_bytes()[byte..].iter().position(|x| *x == b'\n') {
+        Some(i) => byte + i,
+        None => content.len(),
     }
-
-    i
 }
@@ -0,0 +1,367 @@
+//! This module contains the traits that walk through an ast, so that passes can inspect or
+//! transform a document between its parsing and its rendering, e.g. to link urls or to expand the
+//! terms of a glossary.
+//!
+//! Each method of a trait is called on the corresponding part of the ast, and its default
+//! implementation calls the function of the same name of this module, which walks through the
+//! children. A pass overrides the methods of the parts it cares about, and calls these functions
+//! when it wants to walk through their children too.
+
+use crate::parser::ast::{Ast, Node};
+use crate::parser::error::EmptyError;
+use crate::parser::warning::EmptyWarning;
+
+/// A pass that reads an ast.
+///
+/// ```
+/// # use spandex::parser::ast::Ast;
+/// # use spandex::parser::visit::Visit;
+/// /// Counts the characters of the texts of an ast.
+/// struct Length(usize);
+///
+/// impl Visit for Length {
+///     fn visit_text(&mut self, text: &str) {
+///         self.0 += text.chars().count();
+///     }
+/// }
+///
+/// let mut length = Length(0);
+/// length.visit_ast(&Ast::Bold(Box::new(Ast::Text("bold".into()).into())));
+/// assert_eq!(length.0, 4);
+/// ```
+pub trait Visit {
+    /// Visits a node, and its ast by default.
+    fn visit_node(&mut self, node: &Node) {
+        visit_node(self, node);
+    }
+
+    /// Visits an ast, and its children by default.
+    fn visit_ast(&mut self, ast: &Ast) {
+        visit_ast(self, ast);
+    }
+
+    /// Visits the content of a text.
+    fn visit_text(&mut self, _text: &str) {}
+
+    /// Visits an error.
+    fn visit_error(&mut self, _error: &EmptyError) {}
+
+    /// Visits a warning.
+    fn visit_warning(&mut self, _warning: &EmptyWarning) {}
+}
+
+/// Visits the ast of a node.
+pub fn visit_node<V: Visit + ?Sized>(visitor: &mut V, node: &Node) {
+    visitor.visit_ast(&node.ast);
+}
+
+/// Visits the texts, the errors and the warnings of an ast, and the children of the other asts.
+pub fn visit_ast<V: Visit + ?Sized>(visitor: &mut V, ast: &Ast) {
+    match ast {
+        Ast::Text(text) => visitor.visit_text(text),
+        Ast::Error(error) => visitor.visit_error(error),
+        Ast::Warning(warning) => visitor.visit_warning(warning),
+        _ => {
+            for child in ast.children() {
+                visitor.visit_node(child);
+            }
+        }
+    }
+}
+
+/// A pass that modifies an ast in place.
+pub trait VisitMut {
+    /// Visits a node, and its ast by default.
+    fn visit_node_mut(&mut self, node: &mut Node) {
+        visit_node_mut(self, node);
+    }
+
+    /// Visits an ast, and its children by default.
+    fn visit_ast_mut(&mut self, ast: &mut Ast) {
+        visit_ast_mut(self, ast);
+    }
+
+    /// Visits the content of a text.
+    fn visit_text_mut(&mut self, _text: &mut String) {}
+}
+
+/// Visits the ast of a node.
+pub fn visit_node_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut Node) {
+    visitor.visit_ast_mut(&mut node.ast);
+}
+
+/// Visits the texts of an ast, and the children of the other asts.
+pub fn visit_ast_mut<V: VisitMut + ?Sized>(visitor: &mut V, ast: &mut Ast) {
+    match ast {
+        Ast::Text(text) => visitor.visit_text_mut(text),
+        _ => {
+            for child in ast.children_mut() {
+                visitor.visit_node_mut(child);
+            }
+        }
+    }
+}
+
+/// A pass that rebuilds an ast, which can replace any part of it by another one.
+///
+/// ```
+/// # use spandex::parser::ast::Ast;
+/// # use spandex::parser::visit::Fold;
+/// /// Writes the acronyms in small capitals.
+/// struct Acronyms;
+///
+/// impl Fold for Acronyms {
+///     fn fold_text(&mut self, text: String) -> Ast {
+///         if text == "NASA" {
+///             Ast::SmallCaps(Box::new(Ast::Text(text.to_lowercase()).into()))
+///         } else {
+///             Ast::Text(text)
+///         }
+///     }
+/// }
+///
+/// let ast = Acronyms.fold_ast(Ast::Group(vec![Ast::Text("NASA".into()).into()]));
+/// assert_eq!(format!("{}", ast), "[nasa]{.smallcaps}");
+/// ```
+pub trait Fold {
+    /// Folds a node, keeping its span and folding its ast by default.
+    fn fold_node(&mut self, node: Node) -> Node {
+        fold_node(self, node)
+    }
+
+    /// Folds an ast, folding its children by default.
+    fn fold_ast(&mut self, ast: Ast) -> Ast {
+        fold_ast(self, ast)
+    }
+
+    /// Folds the content of a text, which is kept by default.
+    fn fold_text(&mut self, text: String) -> Ast {
+        Ast::Text(text)
+    }
+}
+
+/// Folds the ast of a node, and keeps its span.
+pub fn fold_node<F: Fold + ?Sized>(folder: &mut F, node: Node) -> Node {
+    Node::new(folder.fold_ast(node.ast), node.span)
+}
+
+/// Folds the boxed content of an ast, reusing its box.
+fn fold_box<F: Fold + ?Sized>(folder: &mut F, mut node: Box<Node>) -> Box<Node> {
+    *node = folder.fold_node(*node);
+    node
+}
+
+/// Folds the children of an ast.
+fn fold_nodes<F: Fold + ?Sized>(folder: &mut F, nodes: Vec<Node>) -> Vec<Node> {
+    nodes.into_iter().map(|x| folder.fold_node(x)).collect()
+}
+
+/// Folds the texts of an ast, and the children of the other asts.
+pub fn fold_ast<F: Fold + ?Sized>(folder: &mut F, ast: Ast) -> Ast {
+    match ast {
+        Ast::Text(text) => folder.fold_text(text),
+
+        Ast::Title { level, content } => Ast::Title {
+            level,
+            content: fold_box(folder, content),
+        },
+
+        Ast::Bold(content) => Ast::Bold(fold_box(folder, content)),
+        Ast::Italic(content) => Ast::Italic(fold_box(folder, content)),
+        Ast::SmallCaps(content) => Ast::SmallCaps(fold_box(folder, content)),
+        Ast::Underline(content) => Ast::Underline(fold_box(folder, content)),
+        Ast::Strikethrough(content) => Ast::Strikethrough(fold_box(folder, content)),
+        Ast::Superscript(content) => Ast::Superscript(fold_box(folder, content)),
+        Ast::Subscript(content) => Ast::Subscript(fold_box(folder, content)),
+        Ast::Footnote(content) => Ast::Footnote(fold_box(folder, content)),
+
+        Ast::Link {
+            url,
+            content,
+            position,
+        } => Ast::Link {
+            url,
+            content: fold_box(folder, content),
+            position,
+        },
+
+        Ast::Paragraph(children) => Ast::Paragraph(fold_nodes(folder, children)),
+        Ast::Group(children) => Ast::Group(fold_nodes(folder, children)),
+
+        Ast::List {
+            ordered,
+            start,
+            items,
+        } => Ast::List {
+            ordered,
+            start,
+            items: fold_nodes(folder, items),
+        },
+
+        Ast::ListItem { content, children } => Ast::ListItem {
+            content: fold_box(folder, content),
+            children: fold_nodes(folder, children),
+        },
+
+        Ast::Figure {
+            path,
+            width,
+            caption,
+            position,
+        } => Ast::Figure {
+            path,
+            width,
+            caption: caption.map(|x| fold_box(folder, x)),
+            position,
+        },
+
+        Ast::Table {
+            alignments,
+            header,
+            rows,
+            caption,
+        } => Ast::Table {
+            alignments,
+            caption: caption.map(|x| fold_box(folder, x)),
+            header: fold_nodes(folder, header),
+            rows: rows.into_iter().map(|x| fold_nodes(folder, x)).collect(),
+        },
+
+        Ast::InlineMath { .. }
+        | Ast::DisplayMath { .. }
+        | Ast::Escaped(_)
+        | Ast::Code(_)
+        | Ast::CodeBlock { .. }
+        | Ast::Label { .. }
+        | Ast::Reference { .. }
+        | Ast::Citation { .. }
+        | Ast::Newline
+        | Ast::Comment(_)
+        | Ast::Include { .. }
+        | Ast::TableOfContents
+        | Ast::Bibliography
+        | Ast::Error(_)
+        | Ast::Warning(_) => ast,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::parser::ast::{Ast, Node};
+    use crate::parser::parse;
+    use crate::parser::visit::{fold_ast, visit_ast, Fold, Visit, VisitMut};
+
+    #[test]
+    fn test_visit() {
+        /// Collects the urls of the links of an ast.
+        struct Urls(Vec<String>);
+
+        impl Visit for Urls {
+            fn visit_ast(&mut self, ast: &Ast) {
+                if let Ast::Link { url, .. } = ast {
+                    self.0.push(url.clone());
+                }
+                visit_ast(self, ast);
+            }
+        }
+
+        let ast = parse("assets/tests/successes/test-link.dex").unwrap().ast;
+        let mut urls = Urls(vec![]);
+        urls.visit_ast(&ast);
+
+        assert_eq!(
+            urls.0,
+            vec!["https://rust-spandex.github.io", "https://example.com"]
+        );
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        /// Writes the texts of an ast in upper case.
+        struct Upper;
+
+        impl VisitMut for Upper {
+            fn visit_text_mut(&mut self, text: &mut String) {
+                *text = text.to_uppercase();
+            }
+        }
+
+        /// Collects the texts of an ast.
+        struct Texts(Vec<String>);
+
+        impl Visit for Texts {
+            fn visit_text(&mut self, text: &str) {
+                self.0.push(text.into());
+            }
+        }
+
+        let mut ast = parse("assets/tests/successes/test-link.dex").unwrap().ast;
+        Upper.visit_ast_mut(&mut ast);
+
+        let mut texts = Texts(vec![]);
+        texts.visit_ast(&ast);
+
+        assert_eq!(
+            texts.0,
+            vec!["SEE ", "THE ", "SITE", " OR ", "HTTPS://EXAMPLE.COM", "."]
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        /// Links the terms of a glossary to their definition, except in the titles.
+        struct Glossary;
+
+        impl Fold for Glossary {
+            fn fold_ast(&mut self, ast: Ast) -> Ast {
+                match ast {
+                    Ast::Title { .. } => ast,
+                    _ => fold_ast(self, ast),
+                }
+            }
+
+            fn fold_text(&mut self, text: String) -> Ast {
+                let mut children = vec![];
+                for (index, part) in text.split("spandex").enumerate() {
+                    if index > 0 {
+                        children.push(Node::from(Ast::Link {
+                            url: "#glossary".into(),
+                            content: Box::new(Ast::Text("SpanDeX".into()).into()),
+                            position: Default::default(),
+                        }));
+                    }
+                    children.push(Ast::Text(part.into()).into());
+                }
+                Ast::Group(children)
+            }
+        }
+
+        let paragraph = Node::from(Ast::Paragraph(vec![Ast::Text("Use spandex".into()).into()]));
+        let title = Ast::Title {
+            level: 0,
+            content: Box::new(Ast::Text("spandex".into()).into()),
+        };
+        let ast = Ast::Group(vec![title.clone().into(), paragraph.clone()]);
+
+        let expected = Ast::Group(vec![
+            title.into(),
+            Node::new(
+                Ast::Paragraph(vec![Ast::Group(vec![
+                    Ast::Text("Use ".into()).into(),
+                    Ast::Link {
+                        url: "#glossary".into(),
+                        content: Box::new(Ast::Text("SpanDeX".into()).into()),
+                        position: Default::default(),
+                    }
+                    .into(),
+                    Ast::Text("".into()).into(),
+                ])
+                .into()]),
+                paragraph.span,
+            ),
+        ]);
+
+        assert_eq!(Glossary.fold_ast(ast), expected);
+    }
+}
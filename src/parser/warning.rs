@@ -4,22 +4,90 @@ use std::fmt;
 use std::path::PathBuf;
 
 use colored::*;
+use serde::{Deserialize, Serialize};
 
 use crate::parser::utils::{next_new_line, previous_new_line, replicate};
-use crate::parser::Position;
+use crate::parser::{Inclusion, Position};
 
 /// The different types of warning that can occur.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+///
+/// Each type of warning is a lint, whose level can be set by its name.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WarningType {
     /// Two consecutive stars only seperated by whitespaces.
     ConsecutiveStars,
+
+    /// A backslash escapes a character that has no special meaning.
+    UnknownEscape,
+
+    /// A title is more than one level deeper than the previous title.
+    SkippedTitleLevel,
+
+    /// Two words are separated by more than one space.
+    DoubledSpaces,
+
+    /// A line ends with whitespaces.
+    TrailingWhitespace,
+
+    /// A word is written twice in a row.
+    RepeatedWord,
+
+    /// A paragraph has no content.
+    EmptyParagraph,
+
+    /// A paragraph has too many words.
+    LongParagraph,
+
+    /// An allow comment refers to a lint that does not exist.
+    UnknownLint,
 }
 
 impl WarningType {
+    /// All the types of warning.
+    pub const ALL: [WarningType; 9] = [
+        WarningType::ConsecutiveStars,
+        WarningType::UnknownEscape,
+        WarningType::SkippedTitleLevel,
+        WarningType::DoubledSpaces,
+        WarningType::TrailingWhitespace,
+        WarningType::RepeatedWord,
+        WarningType::EmptyParagraph,
+        WarningType::LongParagraph,
+        WarningType::UnknownLint,
+    ];
+
+    /// Returns the name of the lint, used in the config and in allow comments.
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningType::ConsecutiveStars => "consecutive-stars",
+            WarningType::UnknownEscape => "unknown-escape",
+            WarningType::SkippedTitleLevel => "skipped-title-level",
+            WarningType::DoubledSpaces => "doubled-spaces",
+            WarningType::TrailingWhitespace => "trailing-whitespace",
+            WarningType::RepeatedWord => "repeated-word",
+            WarningType::EmptyParagraph => "empty-paragraph",
+            WarningType::LongParagraph => "long-paragraph",
+            WarningType::UnknownLint => "unknown-lint",
+        }
+    }
+
+    /// Returns the type of warning whose lint has a name, if any.
+    pub fn from_name(name: &str) -> Option<WarningType> {
+        WarningType::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
     /// Returns the title of the warning.
     pub fn title(self) -> &'static str {
         match self {
             WarningType::ConsecutiveStars => "empty bold section",
+            WarningType::UnknownEscape => "unknown escape",
+            WarningType::SkippedTitleLevel => "skipped title level",
+            WarningType::DoubledSpaces => "doubled spaces",
+            WarningType::TrailingWhitespace => "trailing whitespace",
+            WarningType::RepeatedWord => "repeated word",
+            WarningType::EmptyParagraph => "empty paragraph",
+            WarningType::LongParagraph => "long paragraph",
+            WarningType::UnknownLint => "unknown lint",
         }
     }
 
@@ -27,6 +95,14 @@ impl WarningType {
     pub fn detail(self) -> &'static str {
         match self {
             WarningType::ConsecutiveStars => "this will be ignored",
+            WarningType::UnknownEscape => "this backslash will be ignored",
+            WarningType::SkippedTitleLevel => "this title is too deep for the previous title",
+            WarningType::DoubledSpaces => "these spaces will be typeset as a single one",
+            WarningType::TrailingWhitespace => "these whitespaces will be ignored",
+            WarningType::RepeatedWord => "this word is the same as the previous one",
+            WarningType::EmptyParagraph => "this paragraph will only add vertical space",
+            WarningType::LongParagraph => "this paragraph may be hard to read",
+            WarningType::UnknownLint => "this lint does not exist",
         }
     }
 
@@ -36,12 +112,26 @@ impl WarningType {
             WarningType::ConsecutiveStars => {
                 Some("to use bold, you should use single stars, e.g. '*this is bold*'")
             }
+            WarningType::UnknownEscape => Some(
+                "only markup and punctuation characters can be escaped, i.e. \\ * / $ | { } [ ] < > ^ ` # - ! ~ \" ' .",
+            ),
+            WarningType::SkippedTitleLevel => {
+                Some("a title has at most one more hash than the previous one")
+            }
+            WarningType::DoubledSpaces => None,
+            WarningType::TrailingWhitespace => None,
+            WarningType::RepeatedWord => None,
+            WarningType::EmptyParagraph => Some("paragraphs are separated by a single empty line"),
+            WarningType::LongParagraph => Some("long paragraphs can often be split in several ones"),
+            WarningType::UnknownLint => Some(
+                "the lints are consecutive-stars, unknown-escape, skipped-title-level, doubled-spaces, trailing-whitespace, repeated-word, empty-paragraph, long-paragraph and unknown-lint",
+            ),
         }
     }
 }
 
 /// An warning that occured during the parsing.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EmptyWarning {
     /// The position of the warning.
     pub position: Position,
@@ -56,11 +146,24 @@ pub struct Warnings {
     /// The path to the corresponding file.
     pub path: PathBuf,
 
-    /// The content that produced the warnings.
+    /// The content that produced the warnings, which is empty for an ast that has no source.
     pub content: String,
 
     /// The warnings produced.
     pub warnings: Vec<EmptyWarning>,
+
+    /// The include directives that lead to the file, starting from the main file.
+    pub chain: Vec<Inclusion>,
+
+    /// The warnings of the files included by the file.
+    pub children: Vec<Warnings>,
+}
+
+impl Warnings {
+    /// Returns true if neither the file nor the files it includes produced warnings.
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty() && self.children.iter().all(Warnings::is_empty)
+    }
 }
 
 impl fmt::Display for Warnings {
@@ -72,8 +175,13 @@ impl fmt::Display for Warnings {
             let line = warning.position.line;
             let column = warning.position.column;
 
+            // An ast that has no source, e.g. one read from JSON, has no line to show.
             let line_number = format!("{} ", line);
-            let space = replicate(' ', line_number.len() - 1);
+            let space = if self.content.is_empty() {
+                String::from(" ")
+            } else {
+                replicate(' ', line_number.len() - 1)
+            };
             let margin = replicate(' ', column);
             let hats = replicate('^', 1);
 
@@ -84,33 +192,44 @@ impl fmt::Display for Warnings {
                 warning.ty.title().bold()
             )?;
 
-            writeln!(
-                fmt,
-                "{}{} {}:{}:{}",
-                space,
-                "-->".bold().blue(),
-                self.path.display(),
-                line,
-                column
-            )?;
+            if self.content.is_empty() {
+                writeln!(
+                    fmt,
+                    "{}{} {}",
+                    space,
+                    "-->".bold().blue(),
+                    self.path.display()
+                )?;
+                writeln!(fmt, "{} {}", space, "|".blue().bold())?;
+            } else {
+                writeln!(
+                    fmt,
+                    "{}{} {}:{}:{}",
+                    space,
+                    "-->".bold().blue(),
+                    self.path.display(),
+                    line,
+                    column
+                )?;
 
-            writeln!(fmt, "{} {}", space, "|".blue().bold())?;
-            writeln!(
-                fmt,
-                "{} {}",
-                &format!("{}|", line_number).blue().bold(),
-                &self.content[start..end]
-            )?;
-            writeln!(
-                fmt,
-                "{} {}{}{} {}",
-                space,
-                "|".blue().bold(),
-                margin,
-                hats.bold().yellow(),
-                warning.ty.detail().bold().yellow()
-            )?;
-            writeln!(fmt, "{} {}", space, "|".blue().bold())?;
+                writeln!(fmt, "{} {}", space, "|".blue().bold())?;
+                writeln!(
+                    fmt,
+                    "{} {}",
+                    &format!("{}|", line_number).blue().bold(),
+                    &self.content[start..end]
+                )?;
+                writeln!(
+                    fmt,
+                    "{} {}{}{} {}",
+                    space,
+                    "|".blue().bold(),
+                    margin,
+                    hats.bold().yellow(),
+                    warning.ty.detail().bold().yellow()
+                )?;
+                writeln!(fmt, "{} {}", space, "|".blue().bold())?;
+            }
 
             if let Some(note) = warning.ty.note() {
                 writeln!(
@@ -122,6 +241,22 @@ impl fmt::Display for Warnings {
                     note
                 )?;
             }
+            for inclusion in self.chain.iter().rev() {
+                writeln!(
+                    fmt,
+                    "{} {} {}included from {}:{}:{}",
+                    space,
+                    "=".blue().bold(),
+                    "note: ".bold(),
+                    inclusion.path.display(),
+                    inclusion.position.line,
+                    inclusion.position.column
+                )?;
+            }
+        }
+
+        for child in &self.children {
+            write!(fmt, "{}", child)?;
         }
 
         Ok(())
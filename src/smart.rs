@@ -0,0 +1,100 @@
+//! This module contains the smart typography, which replaces some ASCII punctuation by the
+//! characters a typesetter would use.
+
+use crate::parser::ast::Ast;
+
+/// The non-breaking space, which is written `~`.
+pub const NON_BREAKING_SPACE: char = '\u{a0}';
+
+/// Replaces the straight quotes of the texts of an ast by curly quotes, `--` and `---` by en and
+/// em dashes, `...` by an ellipsis and `~` by a non-breaking space.
+///
+/// Whether a quote opens or closes depends on the character before it, even if it is in another
+/// node, e.g. the quotes in `"*emphasis*"` are an opening and a closing one. Code, math, escaped
+/// characters and the urls of autolinks are left untouched.
+pub fn smart_typography(ast: &mut Ast) {
+    smart_typography_aux(ast, &mut None);
+}
+
+/// Replaces the punctuation of an ast, given the character that precedes it.
+fn smart_typography_aux(ast: &mut Ast, previous: &mut Option<char>) {
+    match ast {
+        Ast::Text(content) => *content = substitute(content, previous),
+
+        Ast::Escaped(content) | Ast::Code(content) | Ast::InlineMath { content, .. } => {
+            *previous = content.chars().last().or(*previous);
+        }
+
+        // The text of an autolink is its url.
+        Ast::Link { url, content, .. } if matches!(&content.ast, Ast::Text(text) if text == url) => {
+            *previous = url.chars().last().or(*previous);
+        }
+
+        // References and citations are written as numbers or between brackets.
+        Ast::Reference { .. } | Ast::Citation { .. } => *previous = Some(']'),
+
+        Ast::Bold(_)
+        | Ast::Italic(_)
+        | Ast::SmallCaps(_)
+        | Ast::Underline(_)
+        | Ast::Strikethrough(_)
+        | Ast::Superscript(_)
+        | Ast::Subscript(_)
+        | Ast::Link { .. }
+        | Ast::Group(_) => {
+            for child in ast.children_mut() {
+                smart_typography_aux(child, previous);
+            }
+        }
+
+        Ast::Paragraph(children) => {
+            let mut previous = None;
+            for child in children {
+                smart_typography_aux(child, &mut previous);
+            }
+        }
+
+        _ => {
+            for child in ast.children_mut() {
+                smart_typography_aux(child, &mut None);
+            }
+        }
+    }
+}
+
+/// Returns true if a quote after a character is an opening quote.
+fn opens_quote(previous: Option<char>) -> bool {
+    match previous {
+        None => true,
+        Some(c) => c.is_whitespace() || "([{‘“–—-/".contains(c),
+    }
+}
+
+/// Replaces the punctuation of a text, given the character that precedes it, and updates this
+/// character.
+fn substitute(input: &str, previous: &mut Option<char>) -> String {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (c, length) = match &chars[i..] {
+            ['-', '-', '-', ..] => ('—', 3),
+            ['-', '-', ..] => ('–', 2),
+            ['.', '.', '.', ..] => ('…', 3),
+            ['~', ..] => (NON_BREAKING_SPACE, 1),
+            ['"', ..] if opens_quote(*previous) => ('“', 1),
+            ['"', ..] => ('”', 1),
+            ['\'', ..] if opens_quote(*previous) => ('‘', 1),
+            ['\'', ..] => ('’', 1),
+            [c, ..] => (*c, 1),
+            [] => break,
+        };
+
+        output.push(c);
+        *previous = Some(c);
+        i += length;
+    }
+
+    output
+}
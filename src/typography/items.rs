@@ -57,6 +57,16 @@ impl<'a> Item<'a> {
         }
     }
 
+    /// Creates a box for a glyph that occupies a specified width.
+    ///
+    /// This is useful for glyphs that overlap the next ones, such as the glyphs of a formula.
+    pub fn from_glyph_with_width(glyph: Glyph<'a>, width: Pt) -> Item<'a> {
+        Item {
+            width,
+            content: Content::BoundingBox(glyph),
+        }
+    }
+
     /// Creates some glue.
     pub fn glue(ideal_spacing: Pt, stretchability: Pt, shrinkability: Pt) -> Item<'a> {
         Item {
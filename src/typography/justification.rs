@@ -102,16 +102,24 @@ pub struct LatexJustifier;
 
 impl Justifier for LatexJustifier {
     fn justify<'a>(paragraph: &Paragraph<'a>, text_width: Pt) -> Vec<Vec<(Glyph<'a>, Pt)>> {
-        let lines_length = vec![text_width];
-        let breakpoints = algorithm(&paragraph, &lines_length);
+        let lines_length = vec![
+            text_width - paragraph.first_indent,
+            text_width - paragraph.indent,
+        ];
+        let breakpoints = algorithm(paragraph, &lines_length);
         let positioned_items = positionate_items(&paragraph.items, &lines_length, &breakpoints);
 
         let mut output = vec![];
 
-        for items in positioned_items {
+        for (index, items) in positioned_items.into_iter().enumerate() {
+            let indent = match index {
+                0 => paragraph.first_indent,
+                _ => paragraph.indent,
+            };
+
             let mut line = vec![];
             for item in items {
-                line.push((item.glyph.clone(), item.horizontal_offset));
+                line.push((item.glyph.clone(), indent + item.horizontal_offset));
             }
             output.push(line);
         }
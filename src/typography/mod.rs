@@ -19,11 +19,50 @@ pub struct Glyph<'a> {
 
     /// The size of the font.
     pub scale: Pt,
+
+    /// The vertical offset of the glyph from the baseline, positive upwards.
+    pub shift: Pt,
+
+    /// The number of the footnote whose marker contains the glyph, if any.
+    pub footnote: Option<usize>,
+
+    /// The index of the link containing the glyph in its paragraph, if any.
+    pub link: Option<usize>,
+
+    /// Whether the glyph is underlined.
+    pub underline: bool,
+
+    /// Whether the glyph is struck through.
+    pub strikethrough: bool,
+
+    /// The width of the rule drawn instead of the glyph, if any.
+    ///
+    /// The rule has the thickness of the glyph and is centered on it, e.g. the bar of a fraction
+    /// is drawn where an en dash would be.
+    pub rule: Option<Pt>,
 }
 
 impl<'a> Glyph<'a> {
     /// Creates a new word from a string and a font style.
     pub fn new(glyph: char, font: &'a Font, scale: Pt) -> Glyph<'a> {
-        Glyph { glyph, font, scale }
+        Glyph {
+            glyph,
+            font,
+            scale,
+            shift: Pt(0.0),
+            footnote: None,
+            link: None,
+            underline: false,
+            strikethrough: false,
+            rule: None,
+        }
+    }
+
+    /// Moves the glyph upwards from the baseline.
+    pub fn shifted(self, shift: Pt) -> Glyph<'a> {
+        Glyph {
+            shift: self.shift + shift,
+            ..self
+        }
     }
 }
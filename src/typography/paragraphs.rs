@@ -3,7 +3,6 @@
 //! words into lines.
 
 use std::cmp::Ordering;
-use std::collections::hash_map::{Entry, HashMap};
 use std::f64;
 use std::fmt;
 use std::hash::{Hash, Hasher};
@@ -16,8 +15,10 @@ use petgraph::visit::Dfs;
 use petgraph::visit::IntoNodeIdentifiers;
 use printpdf::Pt;
 
-use crate::font::{FontConfig, FontStyle};
+use crate::font::{Font, FontConfig, FontStyle};
+use crate::math::layout::layout;
 use crate::parser::ast::Ast;
+use crate::smart::NON_BREAKING_SPACE;
 use crate::typography::items::{Content, Item, PositionedItem};
 use crate::typography::Glyph;
 
@@ -34,17 +35,80 @@ const PLUS_INFINITY: Pt = Pt(f64::INFINITY);
 /// The ideal spacing between two words.
 pub const IDEAL_SPACING: Pt = Pt(5.0);
 
+/// The ratio between the size of footnote markers and the size of the text.
+const FOOTNOTE_MARKER_RATIO: f64 = 0.7;
+
+/// The shift of footnote markers above the baseline, relative to the size of the text.
+const FOOTNOTE_MARKER_SHIFT: f64 = 0.4;
+
+/// The ratio between the size of small capitals and the size of the text.
+const SMALL_CAPS_RATIO: f64 = 0.8;
+
+/// The ratio between the size of superscripts and subscripts and the size of the text.
+const SCRIPT_RATIO: f64 = 0.7;
+
+/// The shift of superscripts above the baseline, relative to the size of the text.
+const SUPERSCRIPT_SHIFT: f64 = 0.35;
+
+/// The shift of subscripts below the baseline, relative to the size of the text.
+const SUBSCRIPT_SHIFT: f64 = 0.15;
+
+/// The space between the marker of a list item and its content.
+pub const LIST_MARKER_SEPARATION: Pt = Pt(5.0);
+
+/// The marker of a list item, which hangs in the indentation of the content of the item.
+#[derive(Clone, Debug)]
+pub struct ListMarker {
+    /// The text of the marker, e.g. a bullet or a number.
+    pub text: String,
+
+    /// The indentation of the content of the item.
+    pub indent: Pt,
+}
+
 /// Holds a list of items describing a paragraph.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Paragraph<'a> {
     /// Sequence of items representing the structure of the paragraph.
     pub items: Vec<Item<'a>>,
+
+    /// The footnotes of the paragraph, with their numbers.
+    pub footnotes: Vec<(usize, Ast)>,
+
+    /// The number of footnotes before the paragraph in the document.
+    pub footnote_offset: usize,
+
+    /// The targets of the links of the paragraph.
+    pub links: Vec<String>,
+
+    /// The indentation of the first line of the paragraph.
+    pub first_indent: Pt,
+
+    /// The indentation of the lines of the paragraph after the first one.
+    pub indent: Pt,
+
+    /// The marker of the list item that the paragraph is made of, if any.
+    pub marker: Option<ListMarker>,
+}
+
+impl Default for Paragraph<'_> {
+    fn default() -> Self {
+        Paragraph::new()
+    }
 }
 
 impl<'a> Paragraph<'a> {
     /// Instantiates a new paragraph.
     pub fn new() -> Paragraph<'a> {
-        Paragraph { items: Vec::new() }
+        Paragraph {
+            items: Vec::new(),
+            footnotes: Vec::new(),
+            footnote_offset: 0,
+            links: Vec::new(),
+            first_indent: Pt(0.0),
+            indent: Pt(0.0),
+            marker: None,
+        }
     }
 
     /// Pushes an item at the end of the paragraph.
@@ -53,11 +117,43 @@ impl<'a> Paragraph<'a> {
     }
 
     /// Returns an iterator to the items of the paragraph.
-    pub fn iter(&self) -> Iter<Item> {
+    pub fn iter(&self) -> Iter<'_, Item<'a>> {
         self.items.iter()
     }
 }
 
+/// Returns the width of an AST written on a single line, without hyphenation nor stretching.
+///
+/// Footnotes are ignored since only their small markers are in the text.
+pub fn natural_width(ast: &Ast, font_config: &FontConfig, style: FontStyle, size: Pt) -> Pt {
+    match ast {
+        Ast::Text(content) | Ast::Escaped(content) if style.small_caps => {
+            let font = font_config.for_style(style);
+            content
+                .chars()
+                .flat_map(|c| styled_glyphs(c, font, size, style))
+                .fold(Pt(0.0), |width, glyph| {
+                    width + glyph.font.char_width(glyph.glyph, glyph.scale)
+                })
+        }
+        Ast::Text(content) | Ast::Escaped(content) => {
+            font_config.for_style(style).text_width(content, size)
+        }
+        Ast::Code(content) => font_config.monospace.text_width(content, size),
+        Ast::Bold(content) => natural_width(content, font_config, style.bold(), size),
+        Ast::Italic(content) => natural_width(content, font_config, style.italic(), size),
+        Ast::SmallCaps(content) => natural_width(content, font_config, style.small_caps(), size),
+        Ast::Superscript(content) | Ast::Subscript(content) => {
+            natural_width(content, font_config, style, size * SCRIPT_RATIO)
+        }
+        Ast::InlineMath { formula, .. } => layout(formula, font_config, size, 0).width,
+        Ast::Footnote(_) => Pt(0.0),
+        _ => ast.children().into_iter().fold(Pt(0.0), |width, child| {
+            width + natural_width(child, font_config, style, size)
+        }),
+    }
+}
+
 /// Parses an AST into a sequence of items.
 pub fn itemize_ast<'a>(
     ast: &Ast,
@@ -65,8 +161,22 @@ pub fn itemize_ast<'a>(
     size: Pt,
     dictionary: &Standard,
     indent: Pt,
+) -> Paragraph<'a> {
+    itemize_ast_with_footnotes(ast, font_config, size, dictionary, indent, 0)
+}
+
+/// Parses an AST into a sequence of items, numbering its footnotes after the ones that precede
+/// it.
+pub fn itemize_ast_with_footnotes<'a>(
+    ast: &Ast,
+    font_config: &'a FontConfig,
+    size: Pt,
+    dictionary: &Standard,
+    indent: Pt,
+    footnote_offset: usize,
 ) -> Paragraph<'a> {
     let mut p = Paragraph::new();
+    p.footnote_offset = footnote_offset;
     let current_style = FontStyle::regular();
 
     if indent > Pt(0.0) {
@@ -77,6 +187,30 @@ pub fn itemize_ast<'a>(
     p
 }
 
+/// Parses an item of a list into a sequence of items, numbering its footnotes after the ones that
+/// precede it, with its marker hanging in the indentation of its content.
+pub fn itemize_list_item<'a>(
+    item: &Ast,
+    marker: ListMarker,
+    font_config: &'a FontConfig,
+    size: Pt,
+    dictionary: &Standard,
+    footnote_offset: usize,
+) -> Paragraph<'a> {
+    let mut p = Paragraph::new();
+    p.footnote_offset = footnote_offset;
+    p.marker = Some(marker);
+    itemize_ast_aux(
+        item,
+        font_config,
+        size,
+        dictionary,
+        FontStyle::regular(),
+        &mut p,
+    );
+    p
+}
+
 /// Parses an AST into a sequence of items.
 pub fn itemize_ast_aux<'a>(
     ast: &Ast,
@@ -123,7 +257,7 @@ pub fn itemize_ast_aux<'a>(
             );
         }
 
-        Ast::Text(content) => {
+        Ast::Text(content) | Ast::Escaped(content) => {
             let font = font_config.for_style(current_style);
             let ideal_spacing = IDEAL_SPACING;
             let mut previous_glyph = None;
@@ -132,12 +266,18 @@ pub fn itemize_ast_aux<'a>(
             // Turn each word of the paragraph into a sequence of boxes for the caracters of the
             // word. This includes potential punctuation marks.
             for c in content.chars() {
-                if c.is_whitespace() {
+                if c == NON_BREAKING_SPACE {
+                    // An infinite penalty before the glue forbids breaking the line there.
+                    add_word_to_paragraph(current_word, dictionary, buffer);
+                    buffer.push(Item::penalty(Pt(0.0), f64::INFINITY, false));
+                    buffer.push(glue_from_context(previous_glyph, ideal_spacing));
+                    current_word = vec![];
+                } else if c.is_whitespace() {
                     add_word_to_paragraph(current_word, dictionary, buffer);
                     buffer.push(glue_from_context(previous_glyph, ideal_spacing));
                     current_word = vec![];
                 } else {
-                    current_word.push(Glyph::new(c, font, size));
+                    current_word.extend(styled_glyphs(c, font, size, current_style));
                 }
 
                 previous_glyph = Some(Glyph::new(c, font, size));
@@ -168,10 +308,232 @@ pub fn itemize_ast_aux<'a>(
             buffer.push(Item::penalty(Pt(0.0), f64::NEG_INFINITY, false));
         }
 
+        Ast::InlineMath { formula, .. } => {
+            for item in layout(formula, font_config, size, 0).into_items() {
+                buffer.push(item);
+            }
+        }
+
+        Ast::Code(content) => {
+            // Code is neither hyphenated nor stretched, but lines can break at its spaces.
+            let font = font_config.monospace;
+            let space = font.char_width(' ', size);
+            let mut chars = content.chars().peekable();
+
+            while let Some(c) = chars.next() {
+                if c.is_whitespace() {
+                    // A run of whitespaces is a single glue, so that a line breaks at most once
+                    // between two words.
+                    let mut width = space;
+                    while chars.next_if(|x| x.is_whitespace()).is_some() {
+                        width += space;
+                    }
+                    buffer.push(Item::glue(width, Pt(0.0), Pt(0.0)));
+                } else {
+                    let style = FontStyle {
+                        small_caps: false,
+                        ..current_style
+                    };
+                    for glyph in styled_glyphs(c, font, size, style) {
+                        buffer.push(Item::from_glyph(glyph));
+                    }
+                }
+            }
+        }
+
+        Ast::SmallCaps(content) => {
+            itemize_ast_aux(
+                content,
+                font_config,
+                size,
+                dictionary,
+                current_style.small_caps(),
+                buffer,
+            );
+        }
+
+        Ast::Underline(content) => {
+            itemize_ast_aux(
+                content,
+                font_config,
+                size,
+                dictionary,
+                current_style.underline(),
+                buffer,
+            );
+        }
+
+        Ast::Strikethrough(content) => {
+            itemize_ast_aux(
+                content,
+                font_config,
+                size,
+                dictionary,
+                current_style.strikethrough(),
+                buffer,
+            );
+        }
+
+        Ast::Superscript(content) | Ast::Subscript(content) => {
+            let shift = match ast {
+                Ast::Superscript(_) => size * SUPERSCRIPT_SHIFT,
+                _ => size * -SUBSCRIPT_SHIFT,
+            };
+
+            let start = buffer.items.len();
+
+            itemize_ast_aux(
+                content,
+                font_config,
+                size * SCRIPT_RATIO,
+                dictionary,
+                current_style,
+                buffer,
+            );
+
+            for item in &mut buffer.items[start..] {
+                if let Content::BoundingBox(ref mut glyph) = item.content {
+                    glyph.shift += shift;
+                }
+            }
+        }
+
+        Ast::Link { url, content, .. } => {
+            let start = buffer.items.len();
+            let link = buffer.links.len();
+            buffer.links.push(url.clone());
+
+            itemize_ast_aux(
+                content,
+                font_config,
+                size,
+                dictionary,
+                current_style,
+                buffer,
+            );
+
+            for item in &mut buffer.items[start..] {
+                if let Content::BoundingBox(ref mut glyph) = item.content {
+                    glyph.link = glyph.link.or(Some(link));
+                }
+            }
+        }
+
+        Ast::Footnote(content) => {
+            let number = buffer.footnote_offset + buffer.footnotes.len() + 1;
+            itemize_footnote_marker(number, font_config, size, Some(number), buffer);
+            buffer.footnotes.push((number, content.ast.clone()));
+        }
+
+        Ast::ListItem { content, .. } => {
+            // The lines of the item are indented, and its marker hangs in the indentation of the
+            // first one, right before the content. Nested lists are laid out by the document,
+            // below the item.
+            if let Some(marker) = buffer.marker.take() {
+                let font = font_config.regular;
+                let width = font.text_width(&marker.text, size);
+                let hanging = marker.indent - LIST_MARKER_SEPARATION - width;
+                buffer.first_indent = if hanging > Pt(0.0) { hanging } else { Pt(0.0) };
+                buffer.indent = marker.indent;
+
+                for c in marker.text.chars() {
+                    buffer.push(Item::from_glyph(Glyph::new(c, font, size)));
+                }
+
+                // The content may not fit in the indentation if the marker is wide.
+                let gap = marker.indent - buffer.first_indent - width;
+                let gap = if gap > LIST_MARKER_SEPARATION {
+                    gap
+                } else {
+                    LIST_MARKER_SEPARATION
+                };
+                buffer.push(Item::penalty(Pt(0.0), f64::INFINITY, false));
+                buffer.push(Item::glue(gap, Pt(0.0), Pt(0.0)));
+            }
+
+            itemize_ast_aux(
+                content,
+                font_config,
+                size,
+                dictionary,
+                current_style,
+                buffer,
+            );
+
+            buffer.push(Item::glue(Pt(0.0), PLUS_INFINITY, Pt(0.0)));
+            buffer.push(Item::penalty(Pt(0.0), f64::NEG_INFINITY, false));
+        }
+
         _ => (),
     }
 }
 
+/// Returns the glyphs of a character in a style.
+///
+/// Lowercase letters in small capitals are written as capitals in a smaller size.
+fn styled_glyphs<'a>(c: char, font: &'a Font, size: Pt, style: FontStyle) -> Vec<Glyph<'a>> {
+    let decorate = |glyph: Glyph<'a>| Glyph {
+        underline: style.underline,
+        strikethrough: style.strikethrough,
+        ..glyph
+    };
+
+    if style.small_caps && c.is_lowercase() {
+        c.to_uppercase()
+            .map(|c| decorate(Glyph::new(c, font, size * SMALL_CAPS_RATIO)))
+            .collect()
+    } else {
+        vec![decorate(Glyph::new(c, font, size))]
+    }
+}
+
+/// Adds the superscript marker of a footnote to a buffer.
+///
+/// The glyphs of the marker are tagged with the footnote if specified, so that the document can
+/// find the line in which the marker ends.
+pub fn itemize_footnote_marker<'a>(
+    number: usize,
+    font_config: &'a FontConfig,
+    size: Pt,
+    footnote: Option<usize>,
+    buffer: &mut Paragraph<'a>,
+) {
+    for c in number.to_string().chars() {
+        let glyph = Glyph {
+            footnote,
+            ..Glyph::new(c, font_config.regular, size * FOOTNOTE_MARKER_RATIO)
+        };
+
+        buffer.push(Item::from_glyph(
+            glyph.shifted(size * FOOTNOTE_MARKER_SHIFT),
+        ));
+    }
+}
+
+/// Parses the content of a footnote into a sequence of items, preceded by its marker.
+pub fn itemize_footnote<'a>(
+    number: usize,
+    content: &Ast,
+    font_config: &'a FontConfig,
+    size: Pt,
+    dictionary: &Standard,
+) -> Paragraph<'a> {
+    let mut p = Paragraph::new();
+    itemize_footnote_marker(number, font_config, size, None, &mut p);
+    p.push(Item::glue(IDEAL_SPACING / 2.0, Pt(0.0), Pt(0.0)));
+
+    let content = Ast::Paragraph(vec![content.clone().into()]);
+    itemize_ast_aux(
+        &content,
+        font_config,
+        size,
+        dictionary,
+        FontStyle::regular(),
+        &mut p,
+    );
+    p
+}
+
 /// Adds a word to a buffer.
 pub fn add_word_to_paragraph<'a>(
     word: Vec<Glyph<'a>>,
@@ -272,7 +634,7 @@ impl fmt::Debug for Node {
 
 impl PartialOrd for Node {
     fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
-        Some(self.index.cmp(&other.index))
+        Some(self.cmp(other))
     }
 }
 
@@ -303,7 +665,7 @@ fn get_line_length(lines_length: &[Pt], index: usize) -> Pt {
     if index < lines_length.len() {
         lines_length[index]
     } else {
-        *lines_length.first().unwrap_or(&DEFAULT_LINE_LENGTH)
+        *lines_length.last().unwrap_or(&DEFAULT_LINE_LENGTH)
     }
 }
 
@@ -338,7 +700,7 @@ fn compute_fitness(adjustment_ratio: f64) -> i64 {
 ///
 /// It returns the indexes of items which have been chosen as
 /// breakpoints.
-#[allow(clippy::cyclomatic_complexity)]
+#[allow(clippy::cognitive_complexity)]
 pub fn algorithm<'a>(paragraph: &'a Paragraph<'a>, lines_length: &[Pt]) -> Vec<usize> {
     let mut graph = StableGraph::<_, f64>::new();
     let mut sum_width = Pt(0.0);
@@ -347,8 +709,8 @@ pub fn algorithm<'a>(paragraph: &'a Paragraph<'a>, lines_length: &[Pt]) -> Vec<u
     let mut best_adjustment_ratio_above_threshold = f64::MAX;
     let current_maximum_adjustment_ratio = f64::MAX;
 
-    let mut lines_best_node = HashMap::new();
-    let mut farthest_line: usize = 0;
+    // The node of the last feasible breakpoint, which ends the paragraph if it is complete.
+    let mut last_node = None;
 
     // Add an initial active node for the beginning of the paragraph.
     graph.add_node(Node {
@@ -378,11 +740,8 @@ pub fn algorithm<'a>(paragraph: &'a Paragraph<'a>, lines_length: &[Pt]) -> Vec<u
             } => {
                 // We can only break at a glue if it is preceeded by
                 // a bounding box.
-                can_break = b > 0
-                    && (match paragraph.items[b - 1].content {
-                        Content::BoundingBox { .. } => true,
-                        _ => false,
-                    });
+                can_break =
+                    b > 0 && matches!(paragraph.items[b - 1].content, Content::BoundingBox { .. });
 
                 if !can_break {
                     sum_width += item.width;
@@ -412,7 +771,7 @@ pub fn algorithm<'a>(paragraph: &'a Paragraph<'a>, lines_length: &[Pt]) -> Vec<u
 
                 let adjustment_ratio = compute_adjustment_ratio(
                     actual_width,
-                    get_line_length(&lines_length, a.line),
+                    get_line_length(lines_length, a.line),
                     line_stretch,
                     line_shrink,
                 );
@@ -427,9 +786,7 @@ pub fn algorithm<'a>(paragraph: &'a Paragraph<'a>, lines_length: &[Pt]) -> Vec<u
                     node_to_remove.push(node);
                 }
 
-                if adjustment_ratio >= MIN_ADJUSTMENT_RATIO
-                    && adjustment_ratio <= MAX_ADJUSTMENT_RATIO
-                {
+                if (MIN_ADJUSTMENT_RATIO..=MAX_ADJUSTMENT_RATIO).contains(&adjustment_ratio) {
                     // This is a feasible breakpoint.
                     let badness = adjustment_ratio.abs().powi(3);
                     let penalty = match item.content {
@@ -513,20 +870,7 @@ pub fn algorithm<'a>(paragraph: &'a Paragraph<'a>, lines_length: &[Pt]) -> Vec<u
                 last_best_node.total_demerits,
             );
 
-            match lines_best_node.entry(last_best_node.line) {
-                Entry::Vacant(entry) => {
-                    entry.insert((last_best_node, inserted_node));
-                    farthest_line += 1;
-                }
-
-                Entry::Occupied(entry) => {
-                    let (best_node_on_current_line, _) = entry.get();
-                    if last_best_node.total_demerits < best_node_on_current_line.total_demerits {
-                        lines_best_node
-                            .insert(last_best_node.line, (last_best_node, inserted_node));
-                    }
-                }
-            }
+            last_node = Some((last_best_node, inserted_node));
         }
 
         if let Content::Glue {
@@ -540,14 +884,11 @@ pub fn algorithm<'a>(paragraph: &'a Paragraph<'a>, lines_length: &[Pt]) -> Vec<u
         }
     }
 
-    // TODO: handle situation where there's no option to fall within the window of
-    // accepted adjustment ratios.
-
     // Follow the edges backwards.
     let mut result: Vec<usize> = Vec::new();
 
-    if let Some((_, best_node_on_last_line)) = lines_best_node.get(&farthest_line) {
-        let mut dfs = Dfs::new(&graph, *best_node_on_last_line);
+    if let Some((_, last_node)) = last_node {
+        let mut dfs = Dfs::new(&graph, last_node);
         while let Some(node_index) = dfs.next(&graph) {
             // use a detached neighbors walker
             if let Some(node) = graph.node_weight(node_index) {
@@ -556,9 +897,98 @@ pub fn algorithm<'a>(paragraph: &'a Paragraph<'a>, lines_length: &[Pt]) -> Vec<u
         }
     }
     result.reverse();
+
+    // When no sequence of lines falls within the window of accepted adjustment ratios, the
+    // paragraph is broken greedily, with overfull lines.
+    let complete = match last_node {
+        Some((node, _)) => paragraph.items[node.index..]
+            .iter()
+            .all(|x| !matches!(x.content, Content::BoundingBox { .. })),
+        None => false,
+    };
+
+    if !complete {
+        return overfull_breakpoints(paragraph, lines_length);
+    }
+
     result
 }
 
+/// Breaks a paragraph at the last legal breakpoint before each line gets longer than its
+/// length, letting the lines overflow when they contain no such breakpoint.
+fn overfull_breakpoints(paragraph: &Paragraph, lines_length: &[Pt]) -> Vec<usize> {
+    let legal_breakpoints = find_legal_breakpoints(paragraph);
+    let mut result = vec![0];
+    let mut candidate: Option<usize> = None;
+
+    for &b in legal_breakpoints.iter().skip(1) {
+        let start = result[result.len() - 1];
+        let beginning = if result.len() == 1 { start } else { start + 1 };
+        let mut width = Pt(0.0);
+
+        for (p, item) in paragraph.items.iter().enumerate().take(b).skip(beginning) {
+            match item.content {
+                Content::BoundingBox { .. } => width += item.width,
+                Content::Glue { .. } if p != beginning => width += item.width,
+                _ => (),
+            }
+        }
+
+        let line_length = get_line_length(lines_length, result.len() - 1);
+        if width > line_length {
+            if let Some(candidate) = candidate.take() {
+                result.push(candidate);
+            }
+        }
+
+        if is_forced_break(&paragraph.items[b]) {
+            result.push(b);
+            candidate = None;
+        } else {
+            candidate = Some(b);
+        }
+    }
+
+    if let Some(candidate) = candidate {
+        result.push(candidate);
+    }
+
+    result
+}
+
+/// Returns the width of the widest sequence of items of a paragraph that cannot be broken, which
+/// is the narrowest width it can be typeset in without overfull lines.
+pub fn minimal_width(paragraph: &Paragraph) -> Pt {
+    let (mut widest, mut current) = (Pt(0.0), Pt(0.0));
+    let mut last_item_was_box = false;
+
+    for item in paragraph.items.iter() {
+        match item.content {
+            Content::BoundingBox { .. } => {
+                current += item.width;
+                if current > widest {
+                    widest = current;
+                }
+                last_item_was_box = true;
+                continue;
+            }
+            Content::Penalty { value, .. } if value < f64::INFINITY => {
+                // A hyphen ends the line when it is broken there.
+                if current + item.width > widest {
+                    widest = current + item.width;
+                }
+                current = Pt(0.0);
+            }
+            Content::Glue { .. } if last_item_was_box => current = Pt(0.0),
+            Content::Glue { .. } => current += item.width,
+            Content::Penalty { .. } => (),
+        }
+        last_item_was_box = false;
+    }
+
+    widest
+}
+
 /// Checks whether or not a given item encodes a forced linebreak.
 fn is_forced_break<'a>(item: &'a Item<'a>) -> bool {
     match item.content {
@@ -644,12 +1074,12 @@ fn compute_adjustment_ratio(
         0.0
     } else if actual_length < desired_length {
         if total_stretchability != Pt(0.0) {
-            ((desired_length.0 - actual_length.0) / total_stretchability.0)
+            (desired_length.0 - actual_length.0) / total_stretchability.0
         } else {
             f64::INFINITY
         }
     } else if total_shrinkability != Pt(0.0) {
-        ((desired_length.0 - actual_length.0) / total_shrinkability.0)
+        (desired_length.0 - actual_length.0) / total_shrinkability.0
     } else {
         f64::INFINITY
     }
@@ -666,7 +1096,7 @@ pub fn positionate_items<'a>(
         compute_adjustment_ratios_with_breakpoints(items, line_lengths, breakpoints);
     let mut lines_breakdown: Vec<Vec<PositionedItem>> = Vec::new();
 
-    for breakpoint_line in 0..(breakpoints.len() - 1) {
+    for breakpoint_line in 0..breakpoints.len().saturating_sub(1) {
         let mut positioned_items: Vec<PositionedItem> = Vec::new();
 
         let breakpoint_index = breakpoints[breakpoint_line];
@@ -727,8 +1157,8 @@ pub fn positionate_items<'a>(
                             width: item.width,
                             glyph: Glyph {
                                 glyph: '-',
-                                font: glyph.font,
-                                scale: glyph.scale,
+                                footnote: None,
+                                ..glyph
                             },
                         })
                     }
@@ -752,15 +1182,17 @@ mod tests {
     use crate::config::Config;
     use crate::parser::ast::Ast;
     use crate::typography::items::Content;
+    use crate::typography::justification::{Justifier, LatexJustifier};
     use crate::typography::paragraphs::{
         algorithm, compute_adjustment_ratios_with_breakpoints, find_legal_breakpoints, itemize_ast,
+        itemize_list_item, minimal_width, positionate_items, ListMarker, LIST_MARKER_SEPARATION,
     };
     use crate::Result;
 
     #[test]
     fn test_paragraph_itemization() -> Result<()> {
         let words = "Lorem ipsum dolor sit amet.";
-        let ast = Ast::Paragraph(vec![Ast::Text(words.into())]);
+        let ast = Ast::Paragraph(vec![Ast::Text(words.into()).into()]);
 
         let en_us = Standard::from_embedded(Language::EnglishUS)?;
 
@@ -781,7 +1213,7 @@ mod tests {
     #[test]
     fn test_legal_breakpoints() -> Result<()> {
         let words = "Lorem ipsum dolor sit amet.";
-        let ast = Ast::Paragraph(vec![Ast::Text(words.into())]);
+        let ast = Ast::Paragraph(vec![Ast::Text(words.into()).into()]);
 
         let en_us = Standard::from_embedded(Language::EnglishUS)?;
 
@@ -798,6 +1230,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_non_breaking_space() -> Result<()> {
+        let ast = Ast::Paragraph(vec![Ast::Text("Lorem ipsum\u{a0}dolor.".into()).into()]);
+
+        let en_us = Standard::from_embedded(Language::EnglishUS)?;
+
+        let (_, font_manager) = Config::with_title("Test").init()?;
+        let config = font_manager.default_config();
+
+        let paragraph = itemize_ast(&ast, &config, Pt(10.0), &en_us, Pt(0.0));
+
+        let legal_breakpoints = find_legal_breakpoints(&paragraph);
+        // Lorem ip-sum~do-lor.
+        assert_eq!(legal_breakpoints, [0, 5, 8, 16, 21, 22]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_code_spaces() -> Result<()> {
+        let ast = Ast::Paragraph(vec![Ast::Code("a  \tb".into()).into()]);
+
+        let en_us = Standard::from_embedded(Language::EnglishUS)?;
+
+        let (_, font_manager) = Config::with_title("Test").init()?;
+        let config = font_manager.default_config();
+
+        let paragraph = itemize_ast(&ast, &config, Pt(10.0), &en_us, Pt(0.0));
+
+        // The three whitespaces are a single glue, as wide as three spaces.
+        let space = config.monospace.char_width(' ', Pt(10.0));
+        let glues = paragraph
+            .items
+            .iter()
+            .filter(|x| matches!(x.content, Content::Glue { .. }))
+            .map(|x| x.width)
+            .collect::<Vec<_>>();
+        assert_eq!(&glues[..1], &[space * 3.0]);
+
+        Ok(())
+    }
+
     // #[test]
     // fn test_adjustment_ratio_computation() -> Result<()> {
     //     let words = "Lorem ipsum dolor sit amet.";
@@ -834,7 +1308,7 @@ mod tests {
 
         let words = "The hallway smelt of boiled cabbage and old rag mats. At one end of it a coloured poster, too large for indoor display, had been tacked to the wall. It depicted simply an enormous face, more than a metre wide: the face of a man of about forty-five, with a heavy black moustache and ruggedly handsome features. Winston made for the stairs. It was no use trying the lift. Even at the best of times it was seldom working, and at present the electric current was cut off during daylight hours. It was part of the economy drive in preparation for Hate Week. The flat was seven flights up, and Winston, who was thirty-nine and had a varicose ulcer above his right ankle, went slowly, resting several times on the way. On each landing, opposite the lift-shaft, the poster with the enormous face gazed from the wall. It was one of those pictures which are so contrived that the eyes follow you about when you move. BIG BROTHER IS WATCHING YOU, the caption beneath it ran.";
 
-        let ast = Ast::Paragraph(vec![Ast::Text(words.into())]);
+        let ast = Ast::Paragraph(vec![Ast::Text(words.into()).into()]);
 
         let en_us = Standard::from_embedded(Language::EnglishUS)?;
 
@@ -882,8 +1356,85 @@ mod tests {
 
         print!("\n\n");
 
+        // The last line ends the paragraph.
+        let last = breakpoints[breakpoints.len() - 1];
+        assert!(paragraph.items[last..]
+            .iter()
+            .all(|x| !matches!(x.content, Content::BoundingBox { .. })));
+
         // panic!("Test");
 
         Ok(())
     }
+
+    #[test]
+    fn test_overfull_lines() -> Result<()> {
+        let words = "Internationalization considerations.";
+        let ast = Ast::Paragraph(vec![Ast::Text(words.into()).into()]);
+
+        let en_us = Standard::from_embedded(Language::EnglishUS)?;
+
+        let (_, font_manager) = Config::with_title("Test").init()?;
+        let config = font_manager.default_config();
+
+        let paragraph = itemize_ast(&ast, &config, Pt(10.0), &en_us, Pt(0.0));
+        let glyphs = paragraph
+            .items
+            .iter()
+            .filter(|x| matches!(x.content, Content::BoundingBox { .. }))
+            .count();
+
+        // No syllable fits on a line, which breaks at every legal breakpoint instead.
+        let minimum = minimal_width(&paragraph);
+        assert!(minimum > Pt(0.0));
+
+        for width in &[Pt(0.0), minimum / 2.0, minimum] {
+            let lines_length = vec![*width];
+            let breakpoints = algorithm(&paragraph, &lines_length);
+            let lines = positionate_items(&paragraph.items, &lines_length, &breakpoints);
+            assert!(lines.len() > 2);
+
+            // Every glyph is typeset.
+            let count = lines.iter().map(|x| x.len()).sum::<usize>();
+            assert_eq!(count, glyphs);
+        }
+
+        assert!(positionate_items(&paragraph.items, &[Pt(10.0)], &[]).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_item_itemization() -> Result<()> {
+        let words = ["Lorem ipsum dolor sit amet."; 4].join(" ");
+        let content = Ast::Group(vec![Ast::Text(words).into()]);
+        let item = Ast::ListItem {
+            content: Box::new(content.into()),
+            children: vec![],
+        };
+
+        let en_us = Standard::from_embedded(Language::EnglishUS)?;
+
+        let (_, font_manager) = Config::with_title("Test").init()?;
+        let config = font_manager.default_config();
+
+        let marker = ListMarker {
+            text: String::from("12."),
+            indent: Pt(30.0),
+        };
+        let paragraph = itemize_list_item(&item, marker, &config, Pt(10.0), &en_us, 0);
+        let lines = LatexJustifier::justify(&paragraph, Pt(150.0));
+        assert!(lines.len() > 1);
+
+        // The marker hangs in the indentation of the first line, and the content is aligned on
+        // every line.
+        let marker = config.regular.text_width("12.", Pt(10.0));
+        assert_eq!(lines[0][0].1, Pt(30.0) - LIST_MARKER_SEPARATION - marker);
+        assert_eq!(lines[0][3].1, Pt(30.0));
+        for line in &lines[1..] {
+            assert_eq!(line[0].1, Pt(30.0));
+        }
+
+        Ok(())
+    }
 }